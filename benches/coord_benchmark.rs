@@ -3,6 +3,8 @@
 use criterion::{criterion_group, criterion_main, black_box, Criterion, BenchmarkId, BatchSize, Throughput, PlotConfiguration, AxisScale};
 
 use algorithms::coord::between_threads::*;
+use algorithms::coord::divide::{self, CountWith, DivideSettings};
+use algorithms::coord::schedule;
 
 use util::*;
 use algorithms::sort::test_data::vec_powers;
@@ -52,8 +54,28 @@ pub fn divide_parallel_atomic_counter_overhead(c: &mut Criterion) {
     group.finish();
 }
 
+// The same CPU-bound work under each scheduling strategy in coord::schedule. See
+// coord::schedule::main() for the thread counts that go with these times.
+pub fn divide_scheduling_strategies(c: &mut Criterion) {
+    let mut group = c.benchmark_group("divide_scheduling_strategies");
+
+    let size = 1_000_000;
+    let min_split_size = 100;
+    let rounds_per_item = 20;
+    for min_thread_size in [1_000, 10_000, 100_000].iter() {
+        for spawn in schedule::strategies(4) {
+            let settings = DivideSettings::new(min_split_size, *min_thread_size, spawn, CountWith::None);
+            group.bench_with_input(BenchmarkId::new(spawn.to_string(), min_thread_size), &settings, |b, &settings| {
+                b.iter(|| divide::run(divide::busy_work(rounds_per_item), 0..size, settings))
+            });
+        }
+    }
+    group.finish();
+}
+
 criterion_group!(benches,
-    divide_simple_parallel_find_min_thread_size
+    divide_simple_parallel_find_min_thread_size,
+    divide_scheduling_strategies
     // divide_parallel_atomic_counter_overhead
     );
 criterion_main!(benches);
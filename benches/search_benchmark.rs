@@ -0,0 +1,63 @@
+#![allow(unused_imports)]
+
+use criterion::{criterion_group, criterion_main, black_box, Criterion, BenchmarkId, BatchSize, Throughput, PlotConfiguration, AxisScale};
+
+use algorithms::search::*;
+use algorithms::sort::test_data::*;
+use util::*;
+
+// Each iteration searches for every value in 1..=count plus the two values just outside the range, so
+// the time per iteration divided by count is the average time per search.
+macro_rules! search_compare {
+    ($name:ident, $data_func:expr, $change_expr:expr, $scale:expr) => {
+        pub fn $name(c: &mut Criterion) {
+            let plot_config = PlotConfiguration::default().summary_scale($scale);
+
+            let mut group = c.benchmark_group(stringify!($name));
+            group.plot_config(plot_config);
+
+            for count in $change_expr {
+                let v = $data_func(count);
+                group.throughput(Throughput::Elements(count as u64));
+                group.bench_with_input(BenchmarkId::new("slice::binary_search", count), &count, |b, &count| {
+                    b.iter(|| for x in 0..=count + 1 { black_box(v.binary_search(&x)).ok(); })
+                });
+                group.bench_with_input(BenchmarkId::new("binary_search_by", count), &count, |b, &count| {
+                    b.iter(|| for x in 0..=count + 1 { black_box(binary_search_by(&v, |item| item.cmp(&x))).ok(); })
+                });
+                group.bench_with_input(BenchmarkId::new("lower_bound", count), &count, |b, &count| {
+                    b.iter(|| for x in 0..=count + 1 { black_box(lower_bound(&v, &x)); })
+                });
+                group.bench_with_input(BenchmarkId::new("exponential_search_by", count), &count, |b, &count| {
+                    b.iter(|| for x in 0..=count + 1 { black_box(exponential_search_by(&v, |item| item.cmp(&x))).ok(); })
+                });
+                group.bench_with_input(BenchmarkId::new("interpolation_search", count), &count, |b, &count| {
+                    b.iter(|| for x in 0..=count + 1 { black_box(interpolation_search(&v, x)).ok(); })
+                });
+                // Use the previous answer as the hint, the way a merge would.
+                group.bench_with_input(BenchmarkId::new("galloping_search_by", count), &count, |b, &count| {
+                    b.iter(|| {
+                        let mut hint = 0;
+                        for x in 0..=count + 1 {
+                            hint = match black_box(galloping_search_by(&v, hint, |item| item.cmp(&x))) {
+                                Ok(index) => index,
+                                Err(index) => index,
+                            };
+                        }
+                    })
+                });
+            }
+            group.finish();
+        }
+    }
+}
+
+search_compare!{ search_compare_ordered, vec_usize_ordered, vec_powers(5, 1_000, 4), Logarithmic }
+
+criterion_group!(benches,
+    search_compare_ordered
+    );
+criterion_main!(benches);
+
+// From the main project folder run:
+//   cargo +nightly bench --bench search_benchmark
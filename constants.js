@@ -3,14 +3,20 @@ const actor = {
     QUICKSORT: 'quicksort',
     BUBBLE_SORT: 'bubble sort',
     PARTITION: 'partition',
+    MERGE_SORT: 'merge sort',
+    INSERTION_SORT: 'insertion sort',
 };
 
 const action = {
     MOVE_TO_THREAD: 'move to thread',
+    MOVE_TO_CALL: 'move to call',
     TAKE: 'take',
     RELEASE: 'release',
     SWAP: 'swap',
     MARK_FINAL: 'mark final',
     ROTATE: 'rotate',
+    COMPARE: 'compare',
+    WRITE_TO_BUFFER: 'write to buffer',
+    COPY_BACK: 'copy back',
 };
 
@@ -6,6 +6,7 @@ use std::mem;
 use std::fmt::{self, Debug};
 
 use crate::*;
+use crate::range::CoverageTracker;
 use std::sync::{Arc, Mutex};
 use std::borrow::BorrowMut;
 use std::collections::{BTreeMap, HashMap};
@@ -32,6 +33,7 @@ pub fn main() {
     // try_atomic_counter_orderings();
     // try_mutex_and_no_return();
     try_monitor();
+    // try_coverage();
     // get_sizes();
 }
 
@@ -82,6 +84,8 @@ pub fn divide_simple_defer_low_few_parms(low: usize, high: usize) -> usize {
         }
 }
 
+// coord::divide::run() with divide::fake_work() and Spawn::ThreadSpawn does the same kind of run
+// within the divide framework, where it can be compared with the other ways of scheduling the halves.
 pub fn divide_simple_parallel(low: usize, high: usize, min_split_size: usize, min_thread_size: usize, nsec_per_item: u64, inline_fake_work: bool, use_atomic_counter: bool) -> (usize, usize) {
     let mid = (low + high) / 2;
     let first_len = mid - low;
@@ -168,6 +172,13 @@ pub struct DivideParallelSettingsMutex {
     inline_fake_work: bool,
 }
 
+#[derive(Copy, Clone)]
+pub struct DivideParallelSettingsCoverage {
+    min_split_size: u8,
+    min_thread_size: u32,
+    nsec_per_item: u8,
+}
+
 /*
 #[derive(Copy, Clone)]
 pub struct DivideParallelSettings2b {
@@ -474,6 +485,51 @@ fn divide_parallel_mutex_internal(low: u32, high: u32, settings: DivideParallelS
     (1 + first_call_count.unwrap() + second_call_count, first_thread_count.unwrap() + second_thread_count)
 }
 
+// Like divide_parallel_monitor() but each leaf reports its range as finished and each call checks
+// that its whole range was finished once both halves are done, so a split that drops or repeats part
+// of the range is caught by the first call to see it. Call keys are built the same way as RangeCall
+// keys: "0" for the whole range, then "0" or "1" added for the low or high half.
+pub fn divide_parallel_coverage(low: u32, high: u32, min_split_size: u8, min_thread_size: u32, nsec_per_item: u8, tracker: Arc<CoverageTracker>) {
+    divide_parallel_coverage_internal(low, high, DivideParallelSettingsCoverage {
+        min_split_size,
+        min_thread_size,
+        nsec_per_item,
+    }, tracker, "0".to_string())
+}
+
+fn divide_parallel_coverage_internal(low: u32, high: u32, settings: DivideParallelSettingsCoverage, tracker: Arc<CoverageTracker>, call_key: String) {
+    let min_split_size = settings.min_split_size as u32;
+    let mid = (low + high) / 2;
+    let first_len = mid - low;
+    let first_key = format!("{}0", call_key);
+    let second_key = format!("{}1", call_key);
+    let first_tracker = Arc::clone(&tracker);
+    let handle = if first_len >= settings.min_thread_size {
+        Some(thread::spawn(move || {
+            divide_parallel_coverage_internal(low, mid, settings, first_tracker, first_key)
+        }))
+    } else if first_len >= min_split_size {
+        divide_parallel_coverage_internal(low, mid, settings, first_tracker, first_key);
+        None
+    } else {
+        fake_work_inline_2(low, mid, settings.nsec_per_item);
+        let _ = tracker.finish_range(low as usize..mid as usize, &first_key);
+        None
+    };
+    if high - mid >= min_split_size {
+        divide_parallel_coverage_internal(mid, high, settings, Arc::clone(&tracker), second_key);
+    } else {
+        fake_work_inline_2(mid, high, settings.nsec_per_item);
+        let _ = tracker.finish_range(mid as usize..high as usize, &second_key);
+    };
+
+    if let Some(handle) = handle {
+        handle.join().unwrap();
+    }
+
+    let _ = tracker.finish_call(low as usize..high as usize, &call_key);
+}
+
 pub fn divide_parallel_settings_3(low: u32, high: u32, min_split_size: u32, min_thread_size: u32, nsec_per_item: u8, inline_fake_work: bool, use_atomic_counter: bool) -> (u32, u32) {
     divide_parallel_settings_internal_3(low, high, DivideParallelSettings3 {
         min_split_size,
@@ -838,6 +894,16 @@ fn try_monitor() {
 
 }
 
+fn try_coverage() {
+    let low = 0;
+    let high = 1_000_000;
+    let tracker = Arc::new(CoverageTracker::new(low as usize..high as usize, false));
+    format::print_elapsed(true, "divide_parallel_coverage", "", || {
+        divide_parallel_coverage(low, high, 10, 10_000, 0, Arc::clone(&tracker));
+    });
+    dbg!(tracker.finished_range_count(), tracker.finish());
+}
+
 fn get_sizes() {
     dbg!(mem::size_of::<atomic::Ordering>());
     dbg!(mem::size_of::<DivideParallelSettings>());
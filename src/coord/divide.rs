@@ -0,0 +1,621 @@
+#![allow(dead_code)]
+
+use crossbeam::channel::{self, Receiver, Sender};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+use std::time;
+
+pub fn main() {
+    try_compare_spawns();
+    // try_merge_sort();
+}
+
+// The divide-and-conquer experiments in between_threads as one framework. The problem itself is a
+// Divide, either a type of its own or a DivideFns made from closures, that says how big a piece is,
+// how to split it in two, what to do with a piece too small to split, and how to put the two halves'
+// results back together. How the halves are run and how calls are counted are settings, so the same
+// problem can be run every way and the runs compared.
+//
+// A piece smaller than min_split_size is a leaf. A bigger piece is split, and if it's also at least
+// min_thread_size the low half goes to another thread using the Spawn policy while the high half
+// runs on the current thread. Otherwise both halves run on the current thread one after the other.
+//
+// thread::spawn needs everything it's given to be 'static, so the problem, the output and the Divide
+// have to be as well whichever policy is used.
+
+pub trait Divide: Send + Sync + 'static {
+    type Problem: Send + 'static;
+    type Output: Send + 'static;
+
+    fn size(&self, problem: &Self::Problem) -> usize;
+
+    fn split(&self, problem: Self::Problem) -> (Self::Problem, Self::Problem);
+
+    fn leaf(&self, problem: Self::Problem) -> Self::Output;
+
+    fn combine(&self, low: Self::Output, high: Self::Output) -> Self::Output;
+}
+
+pub struct DivideFns<P, O, Z, S, L, C> {
+    size: Z,
+    split: S,
+    leaf: L,
+    combine: C,
+    // fn() -> so that DivideFns is Send and Sync whatever P and O are.
+    types: std::marker::PhantomData<fn() -> (P, O)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spawn {
+    // Everything on the calling thread, as a baseline.
+    Inline,
+    // A new OS thread for each low half, joined before combining.
+    ThreadSpawn,
+    // rayon::join() with the high half first, since join() always runs its first closure on the
+    // current thread and only the second can be stolen. So the low half only moves to another thread
+    // if an idle worker steals it.
+    RayonJoin,
+    // A new thread for each low half in a crossbeam scope.
+    CrossbeamScope,
+    // The low half is spawned into a rayon::scope(), where like RayonJoin it waits for an idle worker.
+    RayonScope,
+    // A fixed number of threads started for the run that take low halves from a shared queue. A
+    // thread waiting for its low half runs other queued pieces in the meantime, so the pool can't end
+    // up with every thread waiting on work that's still in the queue.
+    Pool(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CountWith {
+    None,
+    Atomic(atomic::Ordering),
+    Mutex,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DivideSettings {
+    pub min_split_size: usize,
+    pub min_thread_size: usize,
+    pub spawn: Spawn,
+    pub count_with: CountWith,
+}
+
+// calls, splits, leaves and max_depth are added up on the way back from the recursion so they're
+// exact whatever the counter. counted_calls is what the shared counter came to, or None without one.
+//
+// handed_off is the number of low halves run on a thread other than the one that split them. That's
+// every one sent to another thread with ThreadSpawn and CrossbeamScope but only the ones an idle
+// worker stole with RayonJoin and RayonScope or another thread took from the queue with Pool. The
+// high half always stays on the thread that split the piece.
+//
+// threads_created is the number of threads the run started itself, which is none with rayon since
+// its pool already exists. threads_used is the number of different threads that did any of the work,
+// and peak_threads is the most of them working on the run at once, both counting the thread the run
+// starts on. With rayon that's one of its workers rather than the calling thread, since a call from
+// outside its pool hands the whole run to a worker and then only waits for it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DivideStats {
+    pub calls: usize,
+    pub splits: usize,
+    pub leaves: usize,
+    pub max_depth: usize,
+    pub handed_off: usize,
+    pub threads_created: usize,
+    pub threads_used: usize,
+    pub peak_threads: usize,
+    pub counted_calls: Option<usize>,
+    pub elapsed: time::Duration,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Tally {
+    calls: usize,
+    splits: usize,
+    leaves: usize,
+    max_depth: usize,
+    handed_off: usize,
+}
+
+enum CallCounter {
+    None,
+    Atomic(AtomicUsize, atomic::Ordering),
+    Mutex(Mutex<usize>),
+}
+
+// The threads working on a run. A thread can be running more than one piece at a time when it picks
+// up another while waiting, as rayon workers and pool threads do, so each has a count.
+#[derive(Default)]
+struct ThreadTracker {
+    working: HashMap<ThreadId, usize>,
+    used: HashSet<ThreadId>,
+    peak: usize,
+}
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+// None tells a pool thread that the run is over.
+struct WorkQueue {
+    sender: Sender<Option<Job>>,
+    receiver: Receiver<Option<Job>>,
+}
+
+struct Context<D> {
+    divide: D,
+    settings: DivideSettings,
+    counter: CallCounter,
+    threads: Mutex<ThreadTracker>,
+    threads_created: AtomicUsize,
+    work_queue: Option<Arc<WorkQueue>>,
+}
+
+impl <P, O, Z, S, L, C> DivideFns<P, O, Z, S, L, C>
+    where
+        P: Send + 'static,
+        O: Send + 'static,
+        Z: Fn(&P) -> usize + Send + Sync + 'static,
+        S: Fn(P) -> (P, P) + Send + Sync + 'static,
+        L: Fn(P) -> O + Send + Sync + 'static,
+        C: Fn(O, O) -> O + Send + Sync + 'static,
+{
+    pub fn new(size: Z, split: S, leaf: L, combine: C) -> Self {
+        DivideFns { size, split, leaf, combine, types: std::marker::PhantomData }
+    }
+}
+
+impl <P, O, Z, S, L, C> Divide for DivideFns<P, O, Z, S, L, C>
+    where
+        P: Send + 'static,
+        O: Send + 'static,
+        Z: Fn(&P) -> usize + Send + Sync + 'static,
+        S: Fn(P) -> (P, P) + Send + Sync + 'static,
+        L: Fn(P) -> O + Send + Sync + 'static,
+        C: Fn(O, O) -> O + Send + Sync + 'static,
+{
+    type Problem = P;
+    type Output = O;
+
+    fn size(&self, problem: &P) -> usize {
+        (self.size)(problem)
+    }
+
+    fn split(&self, problem: P) -> (P, P) {
+        (self.split)(problem)
+    }
+
+    fn leaf(&self, problem: P) -> O {
+        (self.leaf)(problem)
+    }
+
+    fn combine(&self, low: O, high: O) -> O {
+        (self.combine)(low, high)
+    }
+}
+
+impl DivideSettings {
+    pub fn new(min_split_size: usize, min_thread_size: usize, spawn: Spawn, count_with: CountWith) -> Self {
+        // A piece of one item can't be split.
+        DivideSettings { min_split_size: min_split_size.max(2), min_thread_size, spawn, count_with }
+    }
+}
+
+impl fmt::Display for Spawn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            Spawn::Inline => "inline",
+            Spawn::ThreadSpawn => "thread::spawn",
+            Spawn::RayonJoin => "rayon::join",
+            Spawn::CrossbeamScope => "crossbeam::scope",
+            Spawn::RayonScope => "rayon::scope",
+            Spawn::Pool(threads) => return write!(f, "pool of {}", threads),
+        })
+    }
+}
+
+impl fmt::Display for CountWith {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CountWith::None => write!(f, "none"),
+            CountWith::Atomic(ordering) => write!(f, "atomic {:?}", ordering),
+            CountWith::Mutex => write!(f, "mutex"),
+        }
+    }
+}
+
+impl Tally {
+    fn merge(self, other: Tally) -> Tally {
+        Tally {
+            calls: self.calls + other.calls,
+            splits: self.splits + other.splits,
+            leaves: self.leaves + other.leaves,
+            max_depth: self.max_depth.max(other.max_depth),
+            handed_off: self.handed_off + other.handed_off,
+        }
+    }
+}
+
+impl CallCounter {
+    fn new(count_with: CountWith) -> Self {
+        match count_with {
+            CountWith::None => CallCounter::None,
+            CountWith::Atomic(ordering) => CallCounter::Atomic(AtomicUsize::new(0), ordering),
+            CountWith::Mutex => CallCounter::Mutex(Mutex::new(0)),
+        }
+    }
+
+    fn inc(&self) {
+        match self {
+            CallCounter::None => {},
+            CallCounter::Atomic(count, ordering) => { count.fetch_add(1, *ordering); },
+            CallCounter::Mutex(count) => *count.lock().unwrap() += 1,
+        }
+    }
+
+    fn get(&self) -> Option<usize> {
+        match self {
+            CallCounter::None => None,
+            CallCounter::Atomic(count, _) => Some(count.load(atomic::Ordering::SeqCst)),
+            CallCounter::Mutex(count) => Some(*count.lock().unwrap()),
+        }
+    }
+}
+
+impl ThreadTracker {
+    fn start(&mut self, thread: ThreadId) {
+        *self.working.entry(thread).or_insert(0) += 1;
+        self.used.insert(thread);
+        self.peak = self.peak.max(self.working.len());
+    }
+
+    fn finish(&mut self, thread: ThreadId) {
+        let count = self.working.get_mut(&thread).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.working.remove(&thread);
+        }
+    }
+}
+
+impl WorkQueue {
+    fn new() -> Self {
+        let (sender, receiver) = channel::unbounded();
+        WorkQueue { sender, receiver }
+    }
+
+    fn push(&self, job: Job) {
+        self.sender.send(Some(job)).unwrap();
+    }
+
+    fn stop(&self, threads: usize) {
+        for _ in 0..threads {
+            self.sender.send(None).unwrap();
+        }
+    }
+
+    // What each pool thread does until the run is over. It sleeps while the queue is empty.
+    fn work(&self) {
+        while let Some(job) = self.receiver.recv().unwrap() {
+            job();
+        }
+    }
+
+    // Waits for a low half's result, running queued pieces while it waits and sleeping while there's
+    // neither. The stop signals are only sent once the whole run is done, so no one is waiting here
+    // by then.
+    fn help_until<T>(&self, receiver: &Receiver<T>) -> T {
+        loop {
+            crossbeam::select! {
+                recv(receiver) -> value => return value.expect("A piece of the run panicked on a pool thread."),
+                recv(self.receiver) -> job => job.unwrap().expect("A pool thread was stopped during the run.")(),
+            }
+        }
+    }
+}
+
+impl <D: Divide> Context<D> {
+    // Runs a low half and says whether it ran on a thread other than the one that split it.
+    fn run_low<T, F: FnOnce() -> T>(&self, split_on: ThreadId, f: F) -> (T, usize) {
+        let thread = thread::current().id();
+        if thread == split_on {
+            return (f(), 0);
+        }
+        self.threads.lock().unwrap().start(thread);
+        let result = f();
+        self.threads.lock().unwrap().finish(thread);
+        (result, 1)
+    }
+
+    fn thread_created(&self) {
+        self.threads_created.fetch_add(1, atomic::Ordering::SeqCst);
+    }
+}
+
+pub fn run<D: Divide>(divide: D, problem: D::Problem, settings: DivideSettings) -> (D::Output, DivideStats) {
+    let work_queue = match settings.spawn {
+        Spawn::Pool(_) => Some(Arc::new(WorkQueue::new())),
+        _ => None,
+    };
+    let context = Arc::new(Context {
+        divide,
+        settings,
+        counter: CallCounter::new(settings.count_with),
+        threads: Mutex::new(ThreadTracker::default()),
+        threads_created: AtomicUsize::new(0),
+        work_queue,
+    });
+    // Starting and stopping the pool is part of what it costs, so it's timed along with the work.
+    let start = time::Instant::now();
+    let pool_threads: Vec<_> = match (settings.spawn, context.work_queue.as_ref()) {
+        (Spawn::Pool(threads), Some(work_queue)) => (0..threads)
+            .map(|_| {
+                context.thread_created();
+                let work_queue = Arc::clone(work_queue);
+                thread::spawn(move || work_queue.work())
+            })
+            .collect(),
+        _ => vec![],
+    };
+    let run_root = |problem| {
+        let thread = thread::current().id();
+        context.threads.lock().unwrap().start(thread);
+        let result = divide_internal(&context, problem, 1);
+        context.threads.lock().unwrap().finish(thread);
+        result
+    };
+    let (output, tally) = match settings.spawn {
+        // rayon::join() and rayon::scope() called from outside rayon's pool would hand the split to a
+        // worker and block, so the low half would always look stolen. Moving the whole run onto a
+        // worker first means every split is made on the thread that runs it.
+        Spawn::RayonJoin | Spawn::RayonScope if rayon::current_thread_index().is_none() => rayon::scope(move |_| run_root(problem)),
+        _ => run_root(problem),
+    };
+    if let Some(work_queue) = context.work_queue.as_ref() {
+        work_queue.stop(pool_threads.len());
+    }
+    for pool_thread in pool_threads {
+        pool_thread.join().unwrap();
+    }
+    let elapsed = start.elapsed();
+    let threads = context.threads.lock().unwrap();
+    let stats = DivideStats {
+        calls: tally.calls,
+        splits: tally.splits,
+        leaves: tally.leaves,
+        max_depth: tally.max_depth,
+        handed_off: tally.handed_off,
+        threads_created: context.threads_created.load(atomic::Ordering::SeqCst),
+        threads_used: threads.used.len(),
+        peak_threads: threads.peak,
+        counted_calls: context.counter.get(),
+        elapsed,
+    };
+    (output, stats)
+}
+
+fn divide_internal<D: Divide>(context: &Arc<Context<D>>, problem: D::Problem, depth: usize) -> (D::Output, Tally) {
+    context.counter.inc();
+    let size = context.divide.size(&problem);
+    if size < context.settings.min_split_size {
+        let output = context.divide.leaf(problem);
+        return (output, Tally { calls: 1, splits: 0, leaves: 1, max_depth: depth, handed_off: 0 });
+    }
+    let (low, high) = context.divide.split(problem);
+    let spawn = if size >= context.settings.min_thread_size { context.settings.spawn } else { Spawn::Inline };
+    let split_on = thread::current().id();
+    let ((low_output, low_tally), (high_output, high_tally), handed_off) = match spawn {
+        Spawn::Inline => {
+            let low = divide_internal(context, low, depth + 1);
+            let high = divide_internal(context, high, depth + 1);
+            (low, high, 0)
+        },
+        Spawn::ThreadSpawn => {
+            context.thread_created();
+            let low_context = Arc::clone(context);
+            let handle = thread::spawn(move || {
+                low_context.run_low(split_on, || divide_internal(&low_context, low, depth + 1))
+            });
+            let high = divide_internal(context, high, depth + 1);
+            let (low, handed_off) = handle.join().unwrap();
+            (low, high, handed_off)
+        },
+        Spawn::RayonJoin => {
+            let (high, (low, stolen)) = rayon::join(
+                || divide_internal(context, high, depth + 1),
+                || context.run_low(split_on, || divide_internal(context, low, depth + 1)));
+            (low, high, stolen)
+        },
+        Spawn::CrossbeamScope => {
+            context.thread_created();
+            let ((low, handed_off), high) = crossbeam::scope(|scope| {
+                let handle = scope.spawn(|_| context.run_low(split_on, || divide_internal(context, low, depth + 1)));
+                let high = divide_internal(context, high, depth + 1);
+                (handle.join().unwrap(), high)
+            }).unwrap();
+            (low, high, handed_off)
+        },
+        Spawn::RayonScope => {
+            let mut low_result = None;
+            let mut high_result = None;
+            rayon::scope(|scope| {
+                let low_result = &mut low_result;
+                scope.spawn(move |_| *low_result = Some(context.run_low(split_on, || divide_internal(context, low, depth + 1))));
+                high_result = Some(divide_internal(context, high, depth + 1));
+            });
+            let (low, stolen) = low_result.unwrap();
+            (low, high_result.unwrap(), stolen)
+        },
+        Spawn::Pool(_) => {
+            let work_queue = context.work_queue.as_ref().unwrap();
+            let (sender, receiver) = channel::bounded(1);
+            let low_context = Arc::clone(context);
+            work_queue.push(Box::new(move || {
+                let low = low_context.run_low(split_on, || divide_internal(&low_context, low, depth + 1));
+                // The receiver is only gone if the thread that split this piece has panicked.
+                let _ = sender.send(low);
+            }));
+            let high = divide_internal(context, high, depth + 1);
+            let (low, taken) = work_queue.help_until(&receiver);
+            (low, high, taken)
+        },
+    };
+    let output = context.divide.combine(low_output, high_output);
+    let tally = Tally { calls: 1, splits: 1, leaves: 0, max_depth: depth, handed_off }.merge(low_tally).merge(high_tally);
+    (output, tally)
+}
+
+// The fake work from between_threads: each leaf sleeps for a time proportional to its size and the
+// result is the number of leaves.
+pub fn fake_work(nsec_per_item: u64) -> impl Divide<Problem = Range<usize>, Output = usize> {
+    DivideFns::new(
+        |range: &Range<usize>| range.len(),
+        |range: Range<usize>| {
+            let mid = (range.start + range.end) / 2;
+            (range.start..mid, mid..range.end)
+        },
+        move |range: Range<usize>| {
+            if nsec_per_item > 0 {
+                thread::sleep(time::Duration::from_nanos(range.len() as u64 * nsec_per_item));
+            }
+            1
+        },
+        |low: usize, high: usize| low + high)
+}
+
+// Work that keeps a CPU busy, for comparing how the halves are scheduled without the sleeps in
+// fake_work. Each leaf scrambles every item in its range rounds_per_item times and adds them up, so the
+// result is the same however the range was split.
+pub fn busy_work(rounds_per_item: u32) -> impl Divide<Problem = Range<usize>, Output = u64> {
+    DivideFns::new(
+        |range: &Range<usize>| range.len(),
+        |range: Range<usize>| {
+            let mid = (range.start + range.end) / 2;
+            (range.start..mid, mid..range.end)
+        },
+        move |range: Range<usize>| {
+            range
+                .map(|item| {
+                    let mut x = item as u64;
+                    for _ in 0..rounds_per_item {
+                        x = x.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+                        x ^= x >> 33;
+                    }
+                    x
+                })
+                .fold(0, u64::wrapping_add)
+        },
+        |low: u64, high: u64| low.wrapping_add(high))
+}
+
+// A merge sort, to show a problem and output that aren't just numbers.
+pub fn merge_sort<T: Ord + Send + 'static>() -> impl Divide<Problem = Vec<T>, Output = Vec<T>> {
+    DivideFns::new(
+        |v: &Vec<T>| v.len(),
+        |mut v: Vec<T>| {
+            let high = v.split_off(v.len() / 2);
+            (v, high)
+        },
+        |mut v: Vec<T>| {
+            v.sort();
+            v
+        },
+        |low: Vec<T>, high: Vec<T>| {
+            let mut merged = Vec::with_capacity(low.len() + high.len());
+            let mut low = low.into_iter().peekable();
+            let mut high = high.into_iter().peekable();
+            loop {
+                let take_low = match (low.peek(), high.peek()) {
+                    (Some(a), Some(b)) => a <= b,
+                    (Some(_), None) => true,
+                    (None, Some(_)) => false,
+                    (None, None) => break,
+                };
+                merged.push(if take_low { low.next().unwrap() } else { high.next().unwrap() });
+            }
+            merged
+        })
+}
+
+fn try_compare_spawns() {
+    let size = 1_000_000;
+    let min_split_size = 10;
+    let min_thread_size = 50_000;
+    let nsec_per_item = 0;
+    println!("\nfake_work: size = {}; min_split_size = {}; min_thread_size = {}; nsec_per_item = {}", size, min_split_size, min_thread_size, nsec_per_item);
+    println!("\n{:<18} {:<16} {:>10} {:>9} {:>10} {:>8} {:>5} {:>5} {:>10} {:>12}", "spawn", "counter", "leaves", "calls", "counted", "created", "used", "peak", "handed off", "elapsed");
+    let counters = [CountWith::None, CountWith::Atomic(atomic::Ordering::Relaxed), CountWith::Atomic(atomic::Ordering::SeqCst), CountWith::Mutex];
+    for spawn in [Spawn::Inline, Spawn::ThreadSpawn, Spawn::RayonJoin, Spawn::CrossbeamScope, Spawn::RayonScope, Spawn::Pool(4)].iter() {
+        for count_with in counters.iter() {
+            let settings = DivideSettings::new(min_split_size, min_thread_size, *spawn, *count_with);
+            let (leaves, stats) = run(fake_work(nsec_per_item), 0..size, settings);
+            let counted = stats.counted_calls.map_or("-".to_string(), |counted| counted.to_string());
+            println!("{:<18} {:<16} {:>10} {:>9} {:>10} {:>8} {:>5} {:>5} {:>10} {:>12?}", spawn.to_string(), count_with.to_string(), leaves, stats.calls, counted, stats.threads_created, stats.threads_used, stats.peak_threads, stats.handed_off, stats.elapsed);
+        }
+    }
+}
+
+fn try_merge_sort() {
+    let v = crate::sort::test_data::vec_usize_shuffled(100_000);
+    let settings = DivideSettings::new(32, 10_000, Spawn::RayonJoin, CountWith::None);
+    let (sorted, stats) = run(merge_sort(), v, settings);
+    assert!(sorted.is_sorted());
+    dbg!(stats);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spawns_and_counters_agree() {
+        let mut expected = None;
+        for spawn in [Spawn::Inline, Spawn::ThreadSpawn, Spawn::RayonJoin, Spawn::CrossbeamScope, Spawn::RayonScope, Spawn::Pool(3)].iter() {
+            for count_with in [CountWith::None, CountWith::Atomic(atomic::Ordering::Relaxed), CountWith::Mutex].iter() {
+                let (leaves, stats) = run(fake_work(0), 0..10_000, DivideSettings::new(10, 1_000, *spawn, *count_with));
+                assert_eq!(stats.leaves, leaves);
+                assert_eq!(stats.calls, stats.splits + stats.leaves);
+                assert_eq!(stats.leaves, stats.splits + 1);
+                assert_eq!(if *count_with == CountWith::None { None } else { Some(stats.calls) }, stats.counted_calls);
+                if *spawn == Spawn::ThreadSpawn || *spawn == Spawn::CrossbeamScope {
+                    // The pieces of 1,000 or more are 10,000, 5,000, 2,500 and 1,250.
+                    assert_eq!(1 + 2 + 4 + 8, stats.handed_off);
+                    assert_eq!(1 + 2 + 4 + 8, stats.threads_created);
+                }
+                if *spawn == Spawn::Inline {
+                    assert_eq!((0, 1, 1), (stats.threads_created, stats.threads_used, stats.peak_threads));
+                }
+                if *spawn == Spawn::RayonJoin || *spawn == Spawn::RayonScope {
+                    // The test thread isn't one of rayon's workers, so it only waits and isn't counted.
+                    assert!(stats.threads_used <= rayon::current_num_threads());
+                }
+                if *spawn == Spawn::Pool(3) {
+                    assert_eq!(3, stats.threads_created);
+                }
+                let shape = (leaves, stats.calls, stats.max_depth);
+                assert_eq!(*expected.get_or_insert(shape), shape);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rayon_steals() {
+        // The leaves sleep, which leaves the other workers free to steal.
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        for spawn in [Spawn::RayonJoin, Spawn::RayonScope].iter() {
+            let (_, stats) = pool.install(|| run(fake_work(1_000), 0..100_000, DivideSettings::new(100, 1_000, *spawn, CountWith::None)));
+            assert!(stats.handed_off > 0);
+            assert!(stats.threads_used > 1);
+            assert!(stats.threads_used <= 4);
+        }
+    }
+
+    #[test]
+    fn test_merge_sort() {
+        let v = crate::sort::test_data::vec_usize_shuffled(5_000);
+        let (sorted, stats) = run(merge_sort(), v, DivideSettings::new(16, 500, Spawn::CrossbeamScope, CountWith::None));
+        assert_eq!(crate::sort::test_data::vec_usize_ordered(5_000), sorted);
+        assert!(stats.peak_threads > 1);
+    }
+}
@@ -1,4 +1,6 @@
 pub mod between_threads;
+pub mod divide;
+pub mod schedule;
 
 
 
@@ -0,0 +1,169 @@
+#![allow(dead_code)]
+
+use super::divide::*;
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+pub fn main() {
+    try_busy_work();
+    // try_merge_sort();
+}
+
+// Runs the same divide-and-conquer workload under each way of scheduling the halves and compares the
+// time each one takes and how many threads it needs, across problem sizes and min_thread_size values.
+// Each combination runs several times and the time is the median. The outputs of every run have to
+// match, so a strategy that loses or mixes up pieces shows up as a panic rather than a fast time.
+//
+// The strategies are the Spawn policies in coord::divide: Inline is the sequential baseline,
+// ThreadSpawn starts a thread per split, CrossbeamScope a scoped thread per split, RayonJoin and
+// RayonScope leave it to rayon's workers, and Pool hands pieces to a fixed number of threads through
+// a work queue.
+
+#[derive(Clone, Debug)]
+pub struct CompareOptions {
+    pub sizes: Vec<usize>,
+    pub min_thread_sizes: Vec<usize>,
+    pub min_split_size: usize,
+    pub strategies: Vec<Spawn>,
+    pub runs: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct StrategyRow {
+    pub spawn: Spawn,
+    pub size: usize,
+    pub min_thread_size: usize,
+    pub elapsed: Duration,
+    pub threads_created: usize,
+    pub threads_used: usize,
+    pub peak_threads: usize,
+    pub handed_off: usize,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        CompareOptions {
+            sizes: vec![100_000, 1_000_000],
+            min_thread_sizes: vec![1_000, 10_000, 100_000],
+            min_split_size: 100,
+            strategies: strategies(4),
+            runs: 5,
+        }
+    }
+}
+
+pub fn strategies(pool_threads: usize) -> Vec<Spawn> {
+    vec![Spawn::Inline, Spawn::ThreadSpawn, Spawn::RayonJoin, Spawn::RayonScope, Spawn::CrossbeamScope, Spawn::Pool(pool_threads)]
+}
+
+// make_divide is called for each run since run() takes the Divide by value, and make_problem makes a
+// problem of the given size. The thread counts are from the last run of each combination. With fewer
+// CPUs than threads the times mostly show what each strategy costs rather than how much it speeds the
+// work up.
+pub fn compare<D, F, P>(make_divide: F, make_problem: P, options: &CompareOptions) -> Vec<StrategyRow>
+    where
+        D: Divide,
+        D::Output: PartialEq + Debug,
+        F: Fn() -> D,
+        P: Fn(usize) -> D::Problem,
+{
+    let mut rows = vec![];
+    for &size in options.sizes.iter() {
+        let mut expected = None;
+        for &min_thread_size in options.min_thread_sizes.iter() {
+            for &spawn in options.strategies.iter() {
+                let settings = DivideSettings::new(options.min_split_size, min_thread_size, spawn, CountWith::None);
+                let mut times = vec![];
+                let mut last_stats = None;
+                for _ in 0..options.runs.max(1) {
+                    let (output, stats) = run(make_divide(), make_problem(size), settings);
+                    match expected.as_ref() {
+                        Some(expected) => assert_eq!(expected, &output, "{} gave a different output for size = {}, min_thread_size = {}.", spawn, size, min_thread_size),
+                        None => expected = Some(output),
+                    }
+                    times.push(stats.elapsed);
+                    last_stats = Some(stats);
+                }
+                times.sort();
+                let stats = last_stats.unwrap();
+                rows.push(StrategyRow {
+                    spawn,
+                    size,
+                    min_thread_size,
+                    elapsed: times[times.len() / 2],
+                    threads_created: stats.threads_created,
+                    threads_used: stats.threads_used,
+                    peak_threads: stats.peak_threads,
+                    handed_off: stats.handed_off,
+                });
+            }
+        }
+    }
+    rows
+}
+
+pub fn print_table(rows: &[StrategyRow]) {
+    println!("\n{:>10} {:>10} {:<18} {:>12} {:>8} {:>5} {:>5} {:>10}", "size", "min thread", "strategy", "elapsed", "created", "used", "peak", "handed off");
+    for row in rows.iter() {
+        println!("{:>10} {:>10} {:<18} {:>12?} {:>8} {:>5} {:>5} {:>10}",
+            row.size, row.min_thread_size, row.spawn.to_string(), row.elapsed, row.threads_created, row.threads_used, row.peak_threads, row.handed_off);
+    }
+}
+
+fn try_busy_work() {
+    let rounds_per_item = 20;
+    let options = CompareOptions::default();
+    println!("\nbusy_work: rounds_per_item = {}; min_split_size = {}; runs = {}", rounds_per_item, options.min_split_size, options.runs);
+    let rows = compare(|| busy_work(rounds_per_item), |size| 0..size, &options);
+    print_table(&rows);
+}
+
+fn try_merge_sort() {
+    let options = CompareOptions { min_split_size: 32, ..CompareOptions::default() };
+    println!("\nmerge_sort: min_split_size = {}; runs = {}", options.min_split_size, options.runs);
+    let rows = compare(merge_sort::<usize>, crate::sort::test_data::vec_usize_shuffled, &options);
+    print_table(&rows);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        let options = CompareOptions {
+            sizes: vec![1_000, 4_000],
+            min_thread_sizes: vec![500, 2_000],
+            min_split_size: 50,
+            strategies: strategies(2),
+            runs: 2,
+        };
+        // On a pool of its own so rayon has more than one worker however many CPUs there are, with
+        // leaves that sleep so the other workers are free to steal.
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let rows = pool.install(|| compare(|| fake_work(1_000), |size| 0..size, &options));
+        assert_eq!(2 * 2 * 6, rows.len());
+        for row in rows.iter() {
+            match row.spawn {
+                Spawn::Inline => assert_eq!((0, 1, 1), (row.threads_created, row.threads_used, row.peak_threads)),
+                Spawn::ThreadSpawn | Spawn::CrossbeamScope => assert_eq!(row.handed_off, row.threads_created),
+                Spawn::RayonJoin | Spawn::RayonScope => {
+                    assert_eq!(0, row.threads_created);
+                    assert!(row.threads_used <= 4);
+                    if (row.size, row.min_thread_size) == (4_000, 500) {
+                        assert!(row.handed_off > 0);
+                        assert!(row.threads_used > 1);
+                        assert!(row.peak_threads > 1);
+                    }
+                },
+                Spawn::Pool(threads) => {
+                    assert_eq!(threads, row.threads_created);
+                    // The pool threads and the calling thread.
+                    assert!(row.threads_used <= threads + 1);
+                },
+            }
+            assert!(row.peak_threads <= row.threads_used);
+        }
+    }
+}
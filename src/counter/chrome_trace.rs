@@ -0,0 +1,97 @@
+#![allow(dead_code)]
+
+use super::{Counter, CounterItem};
+use crate::sort::SliceCounterItem;
+use crate::sort::quicksort_ptr;
+use crate::sort::test_data;
+use crate::vis::chrome_trace::ChromeTrace;
+
+use std::fmt::Debug;
+use std::time::Instant;
+
+const FILE_TRACE: &str = "counter_trace.json";
+
+pub fn main() {
+    try_export_parallel_quicksort();
+}
+
+// Exports a Counter's call tree as a Chrome trace for Perfetto or chrome://tracing. See
+// vis::chrome_trace for the format.
+
+impl <T: Debug + Clone> Counter<T> {
+
+    // A Counter doesn't know which OS thread ran each call, only whether a call started a new thread.
+    // Each call that did goes on the first track that's free by the time it starts, so tracks are
+    // reused the way pool threads would be, and every other call goes on its parent's track. Calls
+    // that were never ended are shown with no duration.
+    pub fn chrome_trace(&self, pid: usize, process_name: &str, frame_label: &dyn Fn(&CounterItem<T>) -> String) -> ChromeTrace {
+        let mut trace = ChromeTrace::new();
+        trace.process_name(pid, process_name);
+        let first_start = match self.first_start() {
+            Some(first_start) => first_start,
+            None => return trace,
+        };
+        let nanos_since = |instant: Instant| instant.duration_since(first_start).as_nanos();
+
+        let tracks = self.tracks();
+        let track_count = tracks.iter().max().map_or(0, |track| track + 1);
+        for track in 0..track_count {
+            trace.thread_name(pid, track, &format!("track {}", track));
+        }
+        for (index, item) in self.items.iter().enumerate() {
+            let args = serde_json::json!({
+                "index": index,
+                "size": item.size,
+                "is_new_thread": item.is_new_thread,
+                "data": item.data.as_ref().map(|data| format!("{:?}", data)),
+            });
+            trace.complete(&frame_label(item), "call", pid, tracks[index], nanos_since(item.start), self.elapsed_nanos(index), args);
+        }
+        trace
+    }
+
+    // The track for each item, assigned as described for chrome_trace().
+    fn tracks(&self) -> Vec<usize> {
+        let mut tracks = vec![0; self.items.len()];
+        // When each track's current new-thread call ends.
+        let mut track_ends: Vec<Instant> = vec![];
+        let mut order: Vec<usize> = (0..self.items.len()).collect();
+        order.sort_by_key(|index| self.items[*index].start);
+        for index in order {
+            let item = &self.items[index];
+            let inherited_track = item.parent_index.filter(|_| !item.is_new_thread).map(|parent_index| tracks[parent_index]);
+            tracks[index] = match inherited_track {
+                Some(track) => track,
+                None => {
+                    let end = item.end.unwrap_or(item.start);
+                    match track_ends.iter().position(|track_end| *track_end <= item.start) {
+                        Some(track) => {
+                            track_ends[track] = end;
+                            track
+                        },
+                        None => {
+                            track_ends.push(end);
+                            track_ends.len() - 1
+                        },
+                    }
+                },
+            };
+        }
+        tracks
+    }
+}
+
+impl Counter<SliceCounterItem> {
+
+    pub fn chrome_trace_by_range(&self, pid: usize, process_name: &str) -> ChromeTrace {
+        self.chrome_trace(pid, process_name, &|item| item.data.as_ref().map_or("?".to_string(), |data| data.label_range()))
+    }
+}
+
+fn try_export_parallel_quicksort() {
+    let size = 1_000_000;
+    let mut v = test_data::vec_usize_shuffled(size);
+    let counter = quicksort_ptr::quicksort_parallel_ptr(&mut v[..], 0.0, 50_000, true, 7).unwrap();
+    let label = format!("quicksort_parallel_ptr: count = {}", util::format::format_count(size));
+    counter.chrome_trace_by_range(0, &label).save(FILE_TRACE).unwrap();
+}
@@ -0,0 +1,201 @@
+#![allow(dead_code)]
+
+use super::Counter;
+use crate::sort::SliceCounterItem;
+use crate::sort::quicksort_ptr;
+use crate::sort::test_data;
+
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub fn main() {
+    try_diff_thread_min_size();
+}
+
+// Identifies the same call in two runs: the slice range it covered and its method. If the same range
+// and method were called more than once in a run, occurrence tells them apart in the order they
+// were started.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CallKey {
+    pub start_index: usize,
+    pub end_index: usize,
+    pub method: String,
+    pub occurrence: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallDiff {
+    pub key: CallKey,
+    pub first_nanos: u64,
+    pub second_nanos: u64,
+    pub first_is_new_thread: bool,
+    pub second_is_new_thread: bool,
+}
+
+// A call found in both runs whose children differ, meaning the range was split differently.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitChange {
+    pub key: CallKey,
+    pub first_children: Vec<CallKey>,
+    pub second_children: Vec<CallKey>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CounterDiff {
+    pub first_label: String,
+    pub second_label: String,
+    pub first_elapsed_nanos: u64,
+    pub second_elapsed_nanos: u64,
+    // Sorted with the biggest increase in time first.
+    pub matched: Vec<CallDiff>,
+    pub only_in_first: Vec<CallKey>,
+    pub only_in_second: Vec<CallKey>,
+    pub split_changes: Vec<SplitChange>,
+}
+
+impl CallDiff {
+    pub fn change_nanos(&self) -> i64 {
+        self.second_nanos as i64 - self.first_nanos as i64
+    }
+}
+
+impl Counter<SliceCounterItem> {
+
+    // The key of each item, in item order.
+    pub fn call_keys(&self) -> Vec<CallKey> {
+        let mut occurrences: BTreeMap<(usize, usize, String), usize> = BTreeMap::new();
+        self.items.iter()
+            .map(|item| {
+                let (start_index, end_index, method) = item.data.as_ref()
+                    .map_or((0, 0, "?".to_string()), |data| (data.start_index, data.end_index, data.label_method()));
+                let occurrence = occurrences.entry((start_index, end_index, method.clone())).or_insert(0);
+                *occurrence += 1;
+                CallKey { start_index, end_index, method, occurrence: *occurrence - 1 }
+            })
+            .collect()
+    }
+
+    // Lines up the calls of two runs, which may be two runs of the same sort or runs with different
+    // settings. Time changes are only reported for calls that appear in both.
+    pub fn diff(&self, other: &Counter<SliceCounterItem>, first_label: &str, second_label: &str) -> CounterDiff {
+        let first_keys = self.call_keys();
+        let second_keys = other.call_keys();
+        let first_map: BTreeMap<&CallKey, usize> = first_keys.iter().enumerate().map(|(index, key)| (key, index)).collect();
+        let second_map: BTreeMap<&CallKey, usize> = second_keys.iter().enumerate().map(|(index, key)| (key, index)).collect();
+        let first_children = self.child_indexes();
+        let second_children = other.child_indexes();
+
+        let mut matched = vec![];
+        let mut only_in_first = vec![];
+        let mut split_changes = vec![];
+        for (key, first_index) in first_map.iter() {
+            match second_map.get(key) {
+                Some(second_index) => {
+                    matched.push(CallDiff {
+                        key: (*key).clone(),
+                        first_nanos: self.elapsed_nanos(*first_index) as u64,
+                        second_nanos: other.elapsed_nanos(*second_index) as u64,
+                        first_is_new_thread: self.items[*first_index].is_new_thread,
+                        second_is_new_thread: other.items[*second_index].is_new_thread,
+                    });
+                    let first_child_keys: BTreeSet<&CallKey> = first_children[*first_index].iter().map(|index| &first_keys[*index]).collect();
+                    let second_child_keys: BTreeSet<&CallKey> = second_children[*second_index].iter().map(|index| &second_keys[*index]).collect();
+                    if first_child_keys != second_child_keys {
+                        split_changes.push(SplitChange {
+                            key: (*key).clone(),
+                            first_children: first_child_keys.into_iter().cloned().collect(),
+                            second_children: second_child_keys.into_iter().cloned().collect(),
+                        });
+                    }
+                },
+                None => only_in_first.push((*key).clone()),
+            }
+        }
+        let only_in_second = second_map.keys()
+            .filter(|key| !first_map.contains_key(*key))
+            .map(|key| (*key).clone())
+            .collect();
+        matched.sort_by_key(|call_diff| -call_diff.change_nanos());
+
+        CounterDiff {
+            first_label: first_label.to_string(),
+            second_label: second_label.to_string(),
+            first_elapsed_nanos: self.stats_by_method().elapsed_nanos,
+            second_elapsed_nanos: other.stats_by_method().elapsed_nanos,
+            matched,
+            only_in_first,
+            only_in_second,
+            split_changes,
+        }
+    }
+}
+
+impl CounterDiff {
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    // Shows the limit biggest increases and decreases and the first limit split changes.
+    pub fn describe(&self, limit: usize) -> String {
+        let mut s = String::new();
+        s.push_str(&format!("\"{}\" vs. \"{}\": elapsed {} ns -> {} ns\n", self.first_label, self.second_label,
+                            util::format::format_count(self.first_elapsed_nanos), util::format::format_count(self.second_elapsed_nanos)));
+        s.push_str(&format!("matched calls = {}; only in first = {}; only in second = {}; split changes = {}\n",
+                            self.matched.len(), self.only_in_first.len(), self.only_in_second.len(), self.split_changes.len()));
+        s.push_str("Biggest increases:\n");
+        for call_diff in self.matched.iter().take(limit).filter(|x| x.change_nanos() > 0) {
+            s.push_str(&format!("\t{}\n", call_diff.describe()));
+        }
+        s.push_str("Biggest decreases:\n");
+        for call_diff in self.matched.iter().rev().take(limit).filter(|x| x.change_nanos() < 0) {
+            s.push_str(&format!("\t{}\n", call_diff.describe()));
+        }
+        s.push_str("Split changes:\n");
+        for split_change in self.split_changes.iter().take(limit) {
+            s.push_str(&format!("\t{}: {} -> {}\n", split_change.key.describe(),
+                                describe_keys(&split_change.first_children), describe_keys(&split_change.second_children)));
+        }
+        s
+    }
+}
+
+impl CallDiff {
+    pub fn describe(&self) -> String {
+        let thread_change = if self.first_is_new_thread != self.second_is_new_thread {
+            format!(" (new thread: {} -> {})", self.first_is_new_thread, self.second_is_new_thread)
+        } else {
+            "".to_string()
+        };
+        format!("{}: {} ns -> {} ns ({:+} ns){}", self.key.describe(), util::format::format_count(self.first_nanos),
+                util::format::format_count(self.second_nanos), self.change_nanos(), thread_change)
+    }
+}
+
+impl CallKey {
+    pub fn describe(&self) -> String {
+        let occurrence = if self.occurrence > 0 { format!(" #{}", self.occurrence + 1) } else { "".to_string() };
+        format!("{} [{}..{}){}", self.method, self.start_index, self.end_index, occurrence)
+    }
+}
+
+fn describe_keys(keys: &Vec<CallKey>) -> String {
+    if keys.is_empty() {
+        "(none)".to_string()
+    } else {
+        keys.iter().map(|key| key.describe()).collect::<Vec<_>>().join(", ")
+    }
+}
+
+// Runs the same data with two thread sizes. The pivots are random so a fair share of the splits will
+// differ even between two runs with the same settings.
+fn try_diff_thread_min_size() {
+    let size = 100_000;
+    let v = test_data::vec_usize_shuffled(size);
+    let mut v1 = v.clone();
+    let first = quicksort_ptr::quicksort_parallel_ptr(&mut v1[..], 0.0, 10_000, true, 7).unwrap();
+    let mut v2 = v.clone();
+    let second = quicksort_ptr::quicksort_parallel_ptr(&mut v2[..], 0.0, 50_000, true, 7).unwrap();
+    let diff = first.diff(&second, "thread_min_size = 10,000", "thread_min_size = 50,000");
+    println!("{}", diff.describe(10));
+}
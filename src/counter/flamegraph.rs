@@ -0,0 +1,240 @@
+#![allow(dead_code)]
+
+use super::{Counter, CounterItem};
+use crate::sort::SliceCounterItem;
+use crate::sort::quicksort_ptr;
+use crate::sort::test_data;
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+
+const FLAMEGRAPH_WIDTH: f64 = 1200.0;
+const FRAME_HEIGHT: f64 = 16.0;
+const TITLE_HEIGHT: f64 = 24.0;
+const MIN_LABEL_WIDTH: f64 = 30.0;
+const FONT_WIDTH: f64 = 6.5;
+
+pub fn main() {
+    try_export_parallel_quicksort();
+}
+
+// Exports for viewing a Counter's call tree as a flamegraph. All times are in nanoseconds.
+
+impl <T: Debug + Clone> Counter<T> {
+
+    // One line per call in the folded format read by inferno and flamegraph.pl:
+    //   root frame;child frame;grandchild frame <self time>
+    // frame_label turns an item into a frame name. Semicolons would be taken as frame separators so
+    // they're replaced with commas.
+    pub fn collapsed_stacks(&self, frame_label: &dyn Fn(&CounterItem<T>) -> String) -> String {
+        let child_indexes = self.child_indexes();
+        let mut lines = vec![];
+        for root_index in self.root_indexes() {
+            self.collapsed_stacks_internal(&mut lines, "", root_index, &child_indexes, frame_label);
+        }
+        lines.join("\n")
+    }
+
+    fn collapsed_stacks_internal(&self, lines: &mut Vec<String>, prefix: &str, index: usize, child_indexes: &Vec<Vec<usize>>, frame_label: &dyn Fn(&CounterItem<T>) -> String) {
+        let frame = frame_label(&self.items[index]).replace(';', ",");
+        let stack = if prefix.is_empty() { frame } else { format!("{};{}", prefix, frame) };
+        let self_nanos = self.self_nanos(index, child_indexes);
+        if self_nanos > 0 {
+            lines.push(format!("{} {}", stack, self_nanos));
+        }
+        for child_index in child_indexes[index].iter() {
+            self.collapsed_stacks_internal(lines, &stack, *child_index, child_indexes, frame_label);
+        }
+    }
+
+    // A self-contained SVG flamegraph with the outermost call at the bottom. As with flamegraph.pl,
+    // calls with the same stack of frame names are merged and siblings are sorted by name, so the x
+    // axis shows the share of total work rather than the order in time.
+    pub fn flamegraph_svg(&self, title: &str, frame_label: &dyn Fn(&CounterItem<T>) -> String) -> String {
+        flamegraph_svg_from_collapsed(title, &self.collapsed_stacks(frame_label))
+    }
+}
+
+impl Counter<SliceCounterItem> {
+
+    // Frames named by method and range such as "quicksort [0..1000)", so each call gets its own frame.
+    pub fn collapsed_stacks_by_range(&self) -> String {
+        self.collapsed_stacks(&|item| item.data.as_ref().map_or("?".to_string(), |data| data.label_range()))
+    }
+
+    // Frames named by method only, so all quicksort calls at the same depth are merged.
+    pub fn collapsed_stacks_by_method(&self) -> String {
+        self.collapsed_stacks(&|item| item.data.as_ref().map_or("?".to_string(), |data| data.label_method()))
+    }
+
+    pub fn flamegraph_svg_by_range(&self, title: &str) -> String {
+        flamegraph_svg_from_collapsed(title, &self.collapsed_stacks_by_range())
+    }
+
+    pub fn flamegraph_svg_by_method(&self, title: &str) -> String {
+        flamegraph_svg_from_collapsed(title, &self.collapsed_stacks_by_method())
+    }
+
+    // An icicle chart laid out by position in the slice rather than by time: each call is drawn over
+    // the indexes it covered, one row per depth with the outermost call at the top. The color goes
+    // from yellow to red with the call's elapsed time per item, and calls that started a new thread
+    // have a blue outline.
+    pub fn icicle_svg(&self, title: &str) -> String {
+        let child_indexes = self.child_indexes();
+        let depths = self.depths();
+        let max_depth = depths.iter().max().map_or(0, |x| *x);
+        let range_start = self.items.iter().filter_map(|item| item.data.as_ref()).map(|data| data.start_index).min().unwrap_or(0);
+        let range_end = self.items.iter().filter_map(|item| item.data.as_ref()).map(|data| data.end_index).max().unwrap_or(1);
+        let x_scale = FLAMEGRAPH_WIDTH / (range_end - range_start).max(1) as f64;
+        let nanos_per_item: Vec<f64> = (0..self.items.len())
+            .map(|index| self.elapsed_nanos(index) as f64 / self.items[index].size.max(1) as f64)
+            .collect();
+        let max_nanos_per_item = nanos_per_item.iter().cloned().fold(0.0, f64::max);
+
+        let height = TITLE_HEIGHT + ((max_depth + 1) as f64 * FRAME_HEIGHT);
+        let mut s = svg_header(title, height);
+        for (index, item) in self.items.iter().enumerate() {
+            let data = match item.data.as_ref() {
+                Some(data) => data,
+                None => continue,
+            };
+            let x = (data.start_index - range_start) as f64 * x_scale;
+            let width = (data.end_index - data.start_index) as f64 * x_scale;
+            let y = TITLE_HEIGHT + (depths[index] as f64 * FRAME_HEIGHT);
+            let heat = if max_nanos_per_item > 0.0 { nanos_per_item[index] / max_nanos_per_item } else { 0.0 };
+            let fill = format!("rgb(255,{},{})", (230.0 * (1.0 - heat)) as u8, (120.0 * (1.0 - heat)) as u8);
+            let stroke = if item.is_new_thread { "stroke=\"rgb(0,80,220)\" stroke-width=\"1.5\"" } else { "stroke=\"white\" stroke-width=\"0.5\"" };
+            let tooltip = format!("{} ({} children; self {} ns)", format!("{:?}", item).trim(), child_indexes[index].len(), self.self_nanos(index, &child_indexes));
+            s.push_str(&svg_frame(x, y, width, &fill, stroke, &tooltip, &data.label_range()));
+        }
+        s.push_str("</svg>\n");
+        s
+    }
+
+    // Depth of each item below its root, where the root is zero.
+    fn depths(&self) -> Vec<usize> {
+        let mut depths = vec![0; self.items.len()];
+        for (index, item) in self.items.iter().enumerate() {
+            // Parents are always started, and so given an index, before their children.
+            if let Some(parent_index) = item.parent_index {
+                depths[index] = depths[parent_index] + 1;
+            }
+        }
+        depths
+    }
+}
+
+// The tree of merged frames built from collapsed stacks.
+#[derive(Default)]
+struct Frame {
+    self_value: u128,
+    children: BTreeMap<String, Frame>,
+}
+
+impl Frame {
+    fn total_value(&self) -> u128 {
+        self.self_value + self.children.values().map(|child| child.total_value()).sum::<u128>()
+    }
+
+    fn max_depth(&self) -> usize {
+        self.children.values().map(|child| child.max_depth() + 1).max().unwrap_or(0)
+    }
+}
+
+// Builds the flamegraph from text in the folded format so that stacks produced elsewhere can be drawn
+// the same way. Lines that don't end in a number are skipped.
+pub fn flamegraph_svg_from_collapsed(title: &str, collapsed: &str) -> String {
+    let mut root = Frame::default();
+    for line in collapsed.lines() {
+        let split_at = match line.rfind(' ') {
+            Some(split_at) => split_at,
+            None => continue,
+        };
+        let value: u128 = match line[split_at + 1..].trim().parse() {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let mut frame = &mut root;
+        for name in line[..split_at].split(';') {
+            frame = frame.children.entry(name.to_string()).or_default();
+        }
+        frame.self_value += value;
+    }
+
+    let total_value = root.total_value();
+    let max_depth = root.max_depth();
+    let height = TITLE_HEIGHT + (max_depth as f64 * FRAME_HEIGHT);
+    let x_scale = if total_value > 0 { FLAMEGRAPH_WIDTH / total_value as f64 } else { 0.0 };
+    let mut s = svg_header(title, height);
+    let mut x = 0.0;
+    for (name, child) in root.children.iter() {
+        add_flamegraph_frames(&mut s, name, child, x, 1, max_depth, x_scale, total_value);
+        x += child.total_value() as f64 * x_scale;
+    }
+    s.push_str("</svg>\n");
+    s
+}
+
+fn add_flamegraph_frames(s: &mut String, name: &str, frame: &Frame, x: f64, depth: usize, max_depth: usize, x_scale: f64, total_value: u128) {
+    let value = frame.total_value();
+    let width = value as f64 * x_scale;
+    let y = TITLE_HEIGHT + ((max_depth - depth) as f64 * FRAME_HEIGHT);
+    let percent = if total_value > 0 { 100.0 * value as f64 / total_value as f64 } else { 0.0 };
+    let tooltip = format!("{} ({} ns, {:.2}%)", name, util::format::format_count(value), percent);
+    s.push_str(&svg_frame(x, y, width, &flame_color(name), "stroke=\"white\" stroke-width=\"0.5\"", &tooltip, name));
+    let mut child_x = x;
+    for (child_name, child) in frame.children.iter() {
+        add_flamegraph_frames(s, child_name, child, child_x, depth + 1, max_depth, x_scale, total_value);
+        child_x += child.total_value() as f64 * x_scale;
+    }
+}
+
+fn svg_header(title: &str, height: f64) -> String {
+    format!(concat!(
+        "<?xml version=\"1.0\" standalone=\"no\"?>\n",
+        "<svg version=\"1.1\" width=\"{width}\" height=\"{height}\" xmlns=\"http://www.w3.org/2000/svg\" font-family=\"Verdana\" font-size=\"11\">\n",
+        "<rect x=\"0\" y=\"0\" width=\"{width}\" height=\"{height}\" fill=\"rgb(250,250,240)\"/>\n",
+        "<text x=\"{center}\" y=\"16\" text-anchor=\"middle\" font-size=\"14\">{title}</text>\n"),
+        width = FLAMEGRAPH_WIDTH, height = height, center = FLAMEGRAPH_WIDTH / 2.0, title = xml_escape(title))
+}
+
+fn svg_frame(x: f64, y: f64, width: f64, fill: &str, stroke: &str, tooltip: &str, label: &str) -> String {
+    let mut s = format!("<g><title>{}</title><rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"{}\" {}/>",
+                        xml_escape(tooltip), x, y, width, FRAME_HEIGHT - 1.0, fill, stroke);
+    if width >= MIN_LABEL_WIDTH {
+        // Cut the label to what fits in the frame.
+        let max_chars = ((width - 6.0) / FONT_WIDTH) as usize;
+        let label = if label.chars().count() > max_chars {
+            format!("{}..", label.chars().take(max_chars.saturating_sub(2)).collect::<String>())
+        } else {
+            label.to_string()
+        };
+        s.push_str(&format!("<text x=\"{:.2}\" y=\"{:.2}\">{}</text>", x + 3.0, y + FRAME_HEIGHT - 4.0, xml_escape(&label)));
+    }
+    s.push_str("</g>\n");
+    s
+}
+
+// The usual flamegraph palette of reds, oranges and yellows, picked from the name so that the same
+// frame gets the same color in every chart.
+fn flame_color(name: &str) -> String {
+    let hash = name.bytes().fold(5381u32, |hash, byte| hash.wrapping_mul(33) ^ byte as u32);
+    let v1 = (hash % 1000) as f64 / 1000.0;
+    let v2 = ((hash / 1000) % 1000) as f64 / 1000.0;
+    format!("rgb({},{},{})", 205 + (50.0 * v2) as u8, (230.0 * v1) as u8, (55.0 * v2) as u8)
+}
+
+pub fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn try_export_parallel_quicksort() {
+    let size = 1_000_000;
+    let mut v = test_data::vec_usize_shuffled(size);
+    let counter = quicksort_ptr::quicksort_parallel_ptr(&mut v[..], 0.0, 50_000, true, 7).unwrap();
+    let label = format!("quicksort_parallel_ptr: count = {}", util::format::format_count(size));
+    fs::write("counter_collapsed.txt", counter.collapsed_stacks_by_range()).unwrap();
+    fs::write("counter_flamegraph.svg", counter.flamegraph_svg_by_method(&label)).unwrap();
+    fs::write("counter_icicle.svg", counter.icicle_svg(&label)).unwrap();
+}
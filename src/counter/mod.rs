@@ -0,0 +1,308 @@
+pub mod chrome_trace;
+pub mod diff;
+pub mod flamegraph;
+pub mod persist;
+pub mod stats;
+
+use std::fmt::{self, Debug};
+use std::sync::Mutex;
+use std::time;
+use util::format;
+use itertools::Itertools;
+use std::cmp::Ordering;
+use std::time::Instant;
+use std::sync::atomic::{self, AtomicUsize};
+use crossbeam::queue::SegQueue;
+
+pub fn main() {
+
+}
+
+#[derive(Clone)]
+// #[derive(Debug)]
+pub struct Counter<T: Debug + Clone> {
+    // pub items: Mutex<Vec<CounterItem<T>>>,
+    pub items: Vec<CounterItem<T>>,
+    // pub child_sort_function: Box<dyn FnMut((usize, &CounterItem<T>), (usize, &CounterItem<T>)) -> Option<Ordering>>,
+}
+
+#[derive(Clone)]
+pub struct CounterItem<T: Debug + Clone> {
+    pub start: time::Instant,
+    pub end: Option<time::Instant>,
+    pub size: usize,
+    pub is_new_thread: bool,
+    pub data: Option<T>,
+    pub parent_index: Option<usize>,
+}
+
+impl <T: Debug + Clone> Counter<T> {
+
+    pub fn new() -> Self {
+        Counter {
+            items: vec![],
+            // child_sort_function: |((a_index, _), (b_index, _))| a_index.partial_cmp(b_index),
+        }
+    }
+
+    pub fn start(&mut self, size: usize, data: Option<T>, is_new_thread: bool, parent_index: Option<usize>) -> usize {
+        // let items = self.items.get_mut().unwrap();
+        self.items.push(CounterItem {
+            start: time::Instant::now(),
+            end: None,
+            size,
+            is_new_thread,
+            data,
+            parent_index,
+        });
+        self.items.len() - 1
+    }
+
+    pub fn end(&mut self, index: usize) {
+        // let items = self.items.get_mut().unwrap();
+        self.items.get_mut(index).unwrap().end = Some(time::Instant::now());
+    }
+
+    // The indexes of the items that have no parent. Normally there's just the one for the outermost
+    // call.
+    pub fn root_indexes(&self) -> Vec<usize> {
+        self.items.iter()
+            .enumerate()
+            .filter(|(_index, item)| item.parent_index.is_none())
+            .map(|(index, _item)| index)
+            .collect()
+    }
+
+    // For each item, the indexes of its children in the order they were started. Build this once
+    // rather than calling filter() for each item when walking the whole tree.
+    pub fn child_indexes(&self) -> Vec<Vec<usize>> {
+        let mut child_indexes = vec![vec![]; self.items.len()];
+        for (index, item) in self.items.iter().enumerate() {
+            if let Some(parent_index) = item.parent_index {
+                child_indexes[parent_index].push(index);
+            }
+        }
+        child_indexes
+    }
+
+    // Zero for an item that was never ended.
+    pub fn elapsed_nanos(&self, index: usize) -> u128 {
+        let item = &self.items[index];
+        item.end.map_or(0, |end| end.duration_since(item.start).as_nanos())
+    }
+
+    // Time spent in the item itself rather than in its children, that is, its elapsed time less the
+    // time covered by any of its children. Children that ran in parallel overlap, so the overlapping
+    // part is only taken off once. Zero for an item that was never ended.
+    pub fn self_nanos(&self, index: usize, child_indexes: &Vec<Vec<usize>>) -> u128 {
+        let item = &self.items[index];
+        let (start, end) = match item.end {
+            Some(end) => (item.start, end),
+            None => return 0,
+        };
+        let mut child_spans: Vec<(Instant, Instant)> = child_indexes[index].iter()
+            .filter_map(|child_index| {
+                let child = &self.items[*child_index];
+                child.end.map(|child_end| (child.start.max(start), child_end.min(end)))
+            })
+            .filter(|(child_start, child_end)| child_start < child_end)
+            .collect();
+        child_spans.sort();
+        let mut covered = 0;
+        let mut covered_to = start;
+        for (child_start, child_end) in child_spans {
+            let child_start = child_start.max(covered_to);
+            if child_end > child_start {
+                covered += child_end.duration_since(child_start).as_nanos();
+                covered_to = child_end;
+            }
+        }
+        end.duration_since(start).as_nanos() - covered
+    }
+
+    // Self time of the item plus all of its descendants, that is, the total time spent by all threads
+    // on this part of the tree. For a call whose children ran in parallel this is more than its
+    // elapsed time.
+    pub fn work_nanos(&self, index: usize, child_indexes: &Vec<Vec<usize>>) -> u128 {
+        self.self_nanos(index, child_indexes)
+            + child_indexes[index].iter().map(|child_index| self.work_nanos(*child_index, child_indexes)).sum::<u128>()
+    }
+
+    // FnMut(&T, &T) -> Ordering
+    pub fn describe_deep(&self, max_depth: Option<usize>, child_sort_function: &dyn Fn(&(usize, &CounterItem<T>), &(usize, &CounterItem<T>)) -> Ordering) -> String {
+        let mut s = "".to_string();
+        self.describe_deep_internal(&mut s, 0, 0, max_depth, child_sort_function);
+        s
+    }
+
+    fn describe_deep_internal(&self, s: &mut String, index: usize, depth: usize, max_depth: Option<usize>, child_sort_function: &dyn Fn(&(usize, &CounterItem<T>), &(usize, &CounterItem<T>)) -> Ordering) {
+        let item = self.items.get(index).unwrap();
+        let line = format::format_indent_space(depth, &format!("[{}] {}", index, format!("{:?}", item)));
+        s.push_str(&format!("\n{}", line));
+        if max_depth.is_none() || max_depth.unwrap() > depth {
+            let mut child_items: Vec<(usize, &CounterItem<T>)> = self.items.iter()
+                .enumerate()
+                .filter(|(_index, item)| item.parent_index == Some(index))
+                .collect();
+            child_items.sort_unstable_by(child_sort_function);
+            for (index, _item) in child_items {
+                self.describe_deep_internal(s, index, depth + 1, max_depth, child_sort_function);
+            }
+            /*
+            self.items.iter()
+                .enumerate()
+                .filter(|(_, item)| item.parent_index == Some(index))
+                .sorted_by(child_sort_function)
+                .for_each(|(index, _)| {
+                    self.describe_deep_internal(s, index, depth + 1, max_depth, child_sort_function);
+                })
+                */
+        }
+    }
+
+}
+
+// A call-tree recorder with the same start()/end()/parent_index semantics as Counter but usable from
+// many threads at once without a lock. start() takes the next index from an atomic and both calls
+// append an event to a lock-free queue, so recording a call never waits on another thread. The
+// events are put back together into an ordinary Counter by into_counter() once the work is done.
+pub struct ConcurrentCounter<T: Debug + Clone> {
+    next_index: AtomicUsize,
+    events: SegQueue<CounterEvent<T>>,
+}
+
+enum CounterEvent<T: Debug + Clone> {
+    Start {
+        index: usize,
+        item: CounterItem<T>,
+    },
+    End {
+        index: usize,
+        end: time::Instant,
+    },
+}
+
+impl <T: Debug + Clone> ConcurrentCounter<T> {
+
+    pub fn new() -> Self {
+        ConcurrentCounter {
+            next_index: AtomicUsize::new(0),
+            events: SegQueue::new(),
+        }
+    }
+
+    pub fn start(&self, size: usize, data: Option<T>, is_new_thread: bool, parent_index: Option<usize>) -> usize {
+        let start = time::Instant::now();
+        let index = self.next_index.fetch_add(1, atomic::Ordering::Relaxed);
+        self.events.push(CounterEvent::Start {
+            index,
+            item: CounterItem {
+                start,
+                end: None,
+                size,
+                is_new_thread,
+                data,
+                parent_index,
+            },
+        });
+        index
+    }
+
+    pub fn end(&self, index: usize) {
+        self.events.push(CounterEvent::End { index, end: time::Instant::now() });
+    }
+
+    pub fn len(&self) -> usize {
+        self.next_index.load(atomic::Ordering::Relaxed)
+    }
+
+    // Must only be called after every thread that was recording has finished, which the Arc or
+    // borrow the threads were using normally guarantees.
+    pub fn into_counter(self) -> Counter<T> {
+        let len = self.next_index.load(atomic::Ordering::Acquire);
+        let mut items: Vec<Option<CounterItem<T>>> = vec![None; len];
+        let mut ends = vec![];
+        while let Ok(event) = self.events.pop() {
+            match event {
+                CounterEvent::Start { index, item } => items[index] = Some(item),
+                CounterEvent::End { index, end } => ends.push((index, end)),
+            }
+        }
+        for (index, end) in ends {
+            items[index].as_mut().unwrap().end = Some(end);
+        }
+        Counter {
+            items: items.into_iter().map(|item| item.unwrap()).collect(),
+        }
+    }
+}
+
+impl <T: Debug + Clone> Debug for Counter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let child_sort_function//: dyn Fn(&(usize, &CounterItem<T>), &(usize, &CounterItem<T>)) -> Ordering
+            = |a: &(usize, &CounterItem<T>), b: &(usize, &CounterItem<T>)| a.0.cmp(&b.0);
+        // let child_sort_function// : dyn Fn(&(usize, &CounterItem<T>), &(usize, &CounterItem<T>)) -> Option<Ordering>
+        //     = |a, b| a.0.partial_cmp(&b.0);
+        write!(f, "{}", self.describe_deep(None, &child_sort_function))
+    }
+}
+
+impl <T: Debug + Clone> Debug for CounterItem<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let is_new_thread = if self.is_new_thread { " T".to_string() } else { "  ".to_string() };
+        let size = format!(" size = {}", util::format::format_count(self.size));
+        let elapsed = format!("; {}", describe_elapsed(self.start, self.end, Some(self.size), Some("item")));
+        let data = self.data.as_ref().map_or("".to_string(), |x| format!("; data = {:?}", &x));
+        let line = format!("{}{}{}{}", is_new_thread, size, elapsed, data);
+        write!(f, "{}", line)
+    }
+}
+
+fn describe_elapsed(start: Instant, end: Option<Instant>, count: Option<usize>, item_label: Option<&str>) -> String {
+    match end {
+        Some(end) => {
+            let microseconds =  end.duration_since(start).as_micros();
+            let mut s = format!("elapsed = {} µs", util::format::format_count(microseconds));
+            if let Some(count) = count {
+                if count > 0 {
+                    let nanoseconds =  end.duration_since(start).as_nanos();
+                    let nano_per_item = nanoseconds / count as u128;
+                    s.push_str(&format!(" ({} ns/{})", nano_per_item, item_label.unwrap_or("item")));
+                }
+            }
+            s
+        },
+        None => " {no end time}".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_self_nanos_with_overlapping_children() {
+        let base = Instant::now();
+        let at = |nanos: u64| base + Duration::from_nanos(nanos);
+        let item = |start: u64, end: u64, parent_index: Option<usize>| CounterItem::<usize> {
+            start: at(start),
+            end: Some(at(end)),
+            size: 1,
+            is_new_thread: parent_index.is_some(),
+            data: None,
+            parent_index,
+        };
+        // Two children that ran in parallel from 10 to 60 and from 30 to 80 cover 10 to 80 between
+        // them.
+        let counter = Counter { items: vec![item(0, 100, None), item(10, 60, Some(0)), item(30, 80, Some(0))] };
+        let child_indexes = counter.child_indexes();
+        assert_eq!(30, counter.self_nanos(0, &child_indexes));
+        assert_eq!(50, counter.self_nanos(1, &child_indexes));
+        assert_eq!(30 + 50 + 50, counter.work_nanos(0, &child_indexes));
+        let stats = counter.stats(&|_| None);
+        assert_eq!(130, stats.work_nanos);
+        assert_eq!(30, stats.by_depth[0].self_nanos);
+        assert_eq!(100, stats.by_depth[1].self_nanos);
+    }
+}
@@ -0,0 +1,169 @@
+#![allow(dead_code)]
+
+use super::{Counter, CounterItem};
+use crate::sort::SliceCounterItem;
+use crate::sort::quicksort_ptr;
+use crate::sort::test_data;
+
+use serde::{de, Serialize, Serializer, Deserialize, Deserializer};
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub fn main() {
+    try_save_and_load();
+}
+
+// Instants can't be saved, so a saved Counter holds each start and end as nanoseconds since the
+// earliest start in the Counter. Loading turns them back into Instants counted from the moment of
+// loading, which keeps every duration and ordering the same as in the original run.
+#[derive(Serialize, Deserialize)]
+pub struct SavedCounter<T> {
+    pub items: Vec<SavedCounterItem<T>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedCounterItem<T> {
+    pub start_nanos: u64,
+    pub end_nanos: Option<u64>,
+    pub size: usize,
+    pub is_new_thread: bool,
+    pub data: Option<T>,
+    pub parent_index: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SavedCounterError {
+    // Counter::start() only hands out the index of an item already added, so a parent always comes
+    // before its children. Anything else would panic later when the tree is walked.
+    BadParentIndex { index: usize, parent_index: usize },
+}
+
+impl <T: Debug + Clone> Counter<T> {
+
+    pub fn first_start(&self) -> Option<Instant> {
+        self.items.iter().map(|item| item.start).min()
+    }
+
+    pub fn to_saved(&self) -> SavedCounter<T> {
+        let first_start = self.first_start().unwrap_or_else(Instant::now);
+        let nanos_since = |instant: Instant| instant.duration_since(first_start).as_nanos() as u64;
+        SavedCounter {
+            items: self.items.iter()
+                .map(|item| SavedCounterItem {
+                    start_nanos: nanos_since(item.start),
+                    end_nanos: item.end.map(nanos_since),
+                    size: item.size,
+                    is_new_thread: item.is_new_thread,
+                    data: item.data.clone(),
+                    parent_index: item.parent_index,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn from_saved(saved: SavedCounter<T>) -> Result<Self, SavedCounterError> {
+        for (index, item) in saved.items.iter().enumerate() {
+            if let Some(parent_index) = item.parent_index {
+                if parent_index >= index {
+                    return Err(SavedCounterError::BadParentIndex { index, parent_index });
+                }
+            }
+        }
+        let first_start = Instant::now();
+        let instant_at = |nanos: u64| first_start + Duration::from_nanos(nanos);
+        Ok(Counter {
+            items: saved.items.into_iter()
+                .map(|item| CounterItem {
+                    start: instant_at(item.start_nanos),
+                    end: item.end_nanos.map(instant_at),
+                    size: item.size,
+                    is_new_thread: item.is_new_thread,
+                    data: item.data,
+                    parent_index: item.parent_index,
+                })
+                .collect(),
+        })
+    }
+}
+
+impl <T: Debug + Clone + Serialize> Serialize for Counter<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_saved().serialize(serializer)
+    }
+}
+
+impl <'de, T: Debug + Clone + Deserialize<'de>> Deserialize<'de> for Counter<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SavedCounter::deserialize(deserializer).and_then(|saved| Counter::from_saved(saved).map_err(de::Error::custom))
+    }
+}
+
+impl <T: Debug + Clone + Serialize + for<'de> Deserialize<'de>> Counter<T> {
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    pub fn load_json<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Self::from_json(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Display for SavedCounterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SavedCounterError::BadParentIndex { index, parent_index } =>
+                write!(f, "item {} has parent_index {} which doesn't come before it", index, parent_index),
+        }
+    }
+}
+
+impl Error for SavedCounterError {}
+
+fn try_save_and_load() {
+    let size = 100_000;
+    let mut v = test_data::vec_usize_shuffled(size);
+    let counter = quicksort_ptr::quicksort_parallel_ptr(&mut v[..], 0.0, 10_000, true, 7).unwrap();
+    let file_name = "counter_run.json";
+    counter.save_json(file_name).unwrap();
+    let loaded: Counter<SliceCounterItem> = Counter::load_json(file_name).unwrap();
+    assert_eq!(counter.items.len(), loaded.items.len());
+    for index in 0..counter.items.len() {
+        assert_eq!(counter.elapsed_nanos(index), loaded.elapsed_nanos(index));
+    }
+    loaded.dbg(Some(3));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved_item(parent_index: Option<usize>) -> SavedCounterItem<usize> {
+        SavedCounterItem { start_nanos: 0, end_nanos: Some(10), size: 1, is_new_thread: false, data: None, parent_index }
+    }
+
+    #[test]
+    fn test_bad_parent_index() {
+        let saved = SavedCounter { items: vec![saved_item(None), saved_item(Some(0)), saved_item(Some(1))] };
+        assert!(Counter::from_saved(saved).is_ok());
+        for parent_index in [1, 2, 5].iter() {
+            let saved = SavedCounter { items: vec![saved_item(None), saved_item(Some(*parent_index))] };
+            assert_eq!(Some(SavedCounterError::BadParentIndex { index: 1, parent_index: *parent_index }), Counter::from_saved(saved).err());
+        }
+        let json = serde_json::to_string(&SavedCounter { items: vec![saved_item(Some(3))] }).unwrap();
+        assert!(Counter::<usize>::from_json(&json).is_err());
+    }
+}
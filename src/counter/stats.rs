@@ -0,0 +1,204 @@
+#![allow(dead_code)]
+
+use super::{Counter, CounterItem};
+use crate::sort::SliceCounterItem;
+use crate::sort::quicksort_ptr;
+use crate::sort::test_data;
+
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+pub fn main() {
+    try_stats_parallel_quicksort();
+}
+
+// Summary of a whole Counter tree. All times are in nanoseconds. "Work" is the sum of the self times
+// of all calls, i.e. the time that would be needed on one thread. The critical path is the longest
+// chain of calls that had to run one after another, given that children started in a new thread run
+// alongside their siblings. Work divided by the critical path is the most speedup that any number of
+// threads could have got from this particular split.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CounterStats {
+    pub call_count: usize,
+    pub new_thread_count: usize,
+    pub unfinished_count: usize,
+    pub elapsed_nanos: u64,
+    pub work_nanos: u64,
+    pub critical_path_nanos: u64,
+    pub parallelism: f64,
+    pub by_depth: Vec<GroupStats>,
+    pub by_group: BTreeMap<String, GroupStats>,
+    pub nanos_per_item_histogram: Vec<HistogramBucket>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GroupStats {
+    pub call_count: usize,
+    pub new_thread_count: usize,
+    pub item_count: usize,
+    pub total_nanos: u64,
+    pub self_nanos: u64,
+}
+
+// Calls whose elapsed time per item was in from_nanos..to_nanos. The buckets double in width so a
+// handful of them covers everything from a cached bubble sort to a thread spawn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    pub from_nanos: u64,
+    pub to_nanos: u64,
+    pub call_count: usize,
+}
+
+impl <T: Debug + Clone> Counter<T> {
+
+    // group_key decides which entry of by_group each call is added to. Calls for which it returns
+    // None are left out of by_group but still counted everywhere else.
+    pub fn stats(&self, group_key: &dyn Fn(&CounterItem<T>) -> Option<String>) -> CounterStats {
+        let child_indexes = self.child_indexes();
+        let root_indexes = self.root_indexes();
+
+        let mut by_depth: Vec<GroupStats> = vec![];
+        let mut by_group: BTreeMap<String, GroupStats> = BTreeMap::new();
+        let mut histogram_counts: Vec<usize> = vec![];
+        let mut depths = vec![0; self.items.len()];
+        for (index, item) in self.items.iter().enumerate() {
+            if let Some(parent_index) = item.parent_index {
+                depths[index] = depths[parent_index] + 1;
+            }
+            let elapsed_nanos = self.elapsed_nanos(index) as u64;
+            let self_nanos = self.self_nanos(index, &child_indexes) as u64;
+            if by_depth.len() <= depths[index] {
+                by_depth.resize(depths[index] + 1, GroupStats::default());
+            }
+            by_depth[depths[index]].add(item, elapsed_nanos, self_nanos);
+            if let Some(key) = group_key(item) {
+                by_group.entry(key).or_default().add(item, elapsed_nanos, self_nanos);
+            }
+            if item.end.is_some() && item.size > 0 {
+                let bucket = log2_bucket(elapsed_nanos / item.size as u64);
+                if histogram_counts.len() <= bucket {
+                    histogram_counts.resize(bucket + 1, 0);
+                }
+                histogram_counts[bucket] += 1;
+            }
+        }
+
+        let elapsed_nanos = {
+            let start = root_indexes.iter().map(|index| self.items[*index].start).min();
+            let end = root_indexes.iter().filter_map(|index| self.items[*index].end).max();
+            match (start, end) {
+                (Some(start), Some(end)) if end > start => end.duration_since(start).as_nanos() as u64,
+                _ => 0,
+            }
+        };
+        let work_nanos: u64 = root_indexes.iter().map(|index| self.work_nanos(*index, &child_indexes) as u64).sum();
+        let critical_path_nanos = root_indexes.iter().map(|index| self.critical_path_nanos(*index, &child_indexes)).max().unwrap_or(0) as u64;
+
+        CounterStats {
+            call_count: self.items.len(),
+            new_thread_count: self.items.iter().filter(|item| item.is_new_thread).count(),
+            unfinished_count: self.items.iter().filter(|item| item.end.is_none()).count(),
+            elapsed_nanos,
+            work_nanos,
+            critical_path_nanos,
+            parallelism: if critical_path_nanos > 0 { work_nanos as f64 / critical_path_nanos as f64 } else { 0.0 },
+            by_depth,
+            by_group,
+            nanos_per_item_histogram: histogram_counts.iter()
+                .enumerate()
+                .map(|(bucket, call_count)| HistogramBucket {
+                    from_nanos: if bucket == 0 { 0 } else { 1 << bucket },
+                    to_nanos: 1 << (bucket + 1),
+                    call_count: *call_count,
+                })
+                .collect(),
+        }
+    }
+
+    // Children that stayed in the parent's thread ran one after another, while each child that was
+    // started in a new thread ran alongside all of them.
+    pub fn critical_path_nanos(&self, index: usize, child_indexes: &Vec<Vec<usize>>) -> u128 {
+        let mut same_thread_nanos = 0;
+        let mut new_thread_nanos = 0;
+        for child_index in child_indexes[index].iter() {
+            let child_nanos = self.critical_path_nanos(*child_index, child_indexes);
+            if self.items[*child_index].is_new_thread {
+                new_thread_nanos = new_thread_nanos.max(child_nanos);
+            } else {
+                same_thread_nanos += child_nanos;
+            }
+        }
+        self.self_nanos(index, child_indexes) + same_thread_nanos.max(new_thread_nanos)
+    }
+}
+
+impl Counter<SliceCounterItem> {
+    // Grouped by SliceCounterItem::method, e.g. "quicksort" and "bubble sort".
+    pub fn stats_by_method(&self) -> CounterStats {
+        self.stats(&|item| item.data.as_ref().map(|data| data.label_method()))
+    }
+}
+
+impl GroupStats {
+    fn add<T: Debug + Clone>(&mut self, item: &CounterItem<T>, elapsed_nanos: u64, self_nanos: u64) {
+        self.call_count += 1;
+        if item.is_new_thread {
+            self.new_thread_count += 1;
+        }
+        self.item_count += item.size;
+        self.total_nanos += elapsed_nanos;
+        self.self_nanos += self_nanos;
+    }
+}
+
+impl CounterStats {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut s = String::new();
+        s.push_str(&format!("calls = {}; new threads = {}; unfinished = {}\n", fc(self.call_count), fc(self.new_thread_count), fc(self.unfinished_count)));
+        s.push_str(&format!("elapsed = {} ns; work = {} ns; critical path = {} ns; parallelism = {:.2}\n",
+                            fc(self.elapsed_nanos), fc(self.work_nanos), fc(self.critical_path_nanos), self.parallelism));
+        s.push_str("By depth:\n");
+        for (depth, group) in self.by_depth.iter().enumerate() {
+            s.push_str(&format!("\t{:>3}: {}\n", depth, group.describe()));
+        }
+        s.push_str("By group:\n");
+        for (key, group) in self.by_group.iter() {
+            s.push_str(&format!("\t{}: {}\n", key, group.describe()));
+        }
+        s.push_str("ns/item:\n");
+        for bucket in self.nanos_per_item_histogram.iter() {
+            s.push_str(&format!("\t{:>7}..{:>7}: {}\n", fc(bucket.from_nanos), fc(bucket.to_nanos), fc(bucket.call_count)));
+        }
+        s
+    }
+}
+
+impl GroupStats {
+    pub fn describe(&self) -> String {
+        format!("calls = {}; new threads = {}; items = {}; total = {} ns; self = {} ns",
+                fc(self.call_count), fc(self.new_thread_count), fc(self.item_count), fc(self.total_nanos), fc(self.self_nanos))
+    }
+}
+
+fn fc<T: num_format::ToFormattedStr>(n: T) -> String {
+    util::format::format_count(n)
+}
+
+// 0 and 1 go in bucket 0, 2 and 3 in bucket 1, 4 through 7 in bucket 2 and so on.
+fn log2_bucket(n: u64) -> usize {
+    if n <= 1 { 0 } else { 63 - n.leading_zeros() as usize }
+}
+
+fn try_stats_parallel_quicksort() {
+    let size = 1_000_000;
+    let mut v = test_data::vec_usize_shuffled(size);
+    let counter = quicksort_ptr::quicksort_parallel_ptr(&mut v[..], 0.0, 50_000, true, 7).unwrap();
+    let stats = counter.stats_by_method();
+    println!("{}", stats.describe());
+    println!("{}", stats.to_json());
+}
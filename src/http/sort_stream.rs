@@ -0,0 +1,75 @@
+#![allow(dead_code)]
+
+use rocket::http::ContentType;
+use rocket::response::{content, Stream};
+use std::time::Duration;
+
+use crate::http::http_server;
+use crate::vis::live::{self, LiveData, LiveFormat, LiveOptions, LiveReader, LiveSort};
+
+// Runs sorts on request and streams what they do as it happens. Open http://localhost:8000/sort for
+// a page that picks a sort, starts it and draws the array as the messages arrive.
+//
+// /sort/stream sends the messages from vis::live as Server-Sent Events and /sort/json sends the same
+// messages as JSON lines over a chunked response. Both take:
+//   sort      bubble_sort, insertion_sort, merge_sort, merge_sort_loop or quicksort_rayon
+//   data      shuffled (the default), ordered or reversed
+//   size      the number of items, at most MAX_SIZE
+//   delay_ms  how long the sort waits after each message
+//   compares  true to include the compares
+// A sort or data that isn't recognized gives a 404.
+
+const PAGE: &str = include_str!("sort_stream.html");
+const MAX_SIZE: usize = 2_000;
+const MAX_DELAY_MS: u64 = 1_000;
+// Rocket fills a chunk before it writes it, so a chunk much bigger than one message would hold the
+// messages back until enough of them had come in.
+const CHUNK_SIZE: u64 = 64;
+
+pub fn main() {
+    start_server();
+}
+
+pub fn start_server() {
+    http_server::start(routes![sort_page, sort_stream, sort_json]).unwrap();
+}
+
+#[get("/sort")]
+fn sort_page() -> content::Html<String> {
+    let sort_options: String = LiveSort::all().iter()
+        .map(|sort| format!("<option value=\"{}\">{}</option>", sort.name(), sort.name()))
+        .collect();
+    content::Html(PAGE
+        .replace("{{sort_options}}", &sort_options)
+        .replace("{{max_size}}", &MAX_SIZE.to_string()))
+}
+
+#[get("/sort/stream?<sort>&<data>&<size>&<delay_ms>&<compares>")]
+fn sort_stream(sort: String, data: Option<String>, size: Option<usize>, delay_ms: Option<u64>, compares: Option<bool>) -> Option<content::Content<Stream<LiveReader>>> {
+    let options = live_options(&sort, data, size, delay_ms, compares)?;
+    let reader = live::start(&options).into_reader(LiveFormat::EventStream);
+    Some(content::Content(ContentType::new("text", "event-stream"), Stream::chunked(reader, CHUNK_SIZE)))
+}
+
+#[get("/sort/json?<sort>&<data>&<size>&<delay_ms>&<compares>")]
+fn sort_json(sort: String, data: Option<String>, size: Option<usize>, delay_ms: Option<u64>, compares: Option<bool>) -> Option<content::Content<Stream<LiveReader>>> {
+    let options = live_options(&sort, data, size, delay_ms, compares)?;
+    let reader = live::start(&options).into_reader(LiveFormat::JsonLines);
+    Some(content::Content(ContentType::new("application", "x-ndjson"), Stream::chunked(reader, CHUNK_SIZE)))
+}
+
+fn live_options(sort: &str, data: Option<String>, size: Option<usize>, delay_ms: Option<u64>, compares: Option<bool>) -> Option<LiveOptions> {
+    let default = LiveOptions::default();
+    let data = match data {
+        Some(data) => LiveData::from_name(&data)?,
+        None => default.data,
+    };
+    Some(LiveOptions {
+        sort: LiveSort::from_name(sort)?,
+        data,
+        size: size.unwrap_or(default.size).max(1).min(MAX_SIZE),
+        delay: delay_ms.map(|delay_ms| Duration::from_millis(delay_ms.min(MAX_DELAY_MS))).unwrap_or(default.delay),
+        include_compares: compares.unwrap_or(default.include_compares),
+        ..default
+    })
+}
@@ -53,6 +53,7 @@ pub mod http;
 pub mod map;
 // pub mod polygon_map;
 pub mod range;
+pub mod search;
 pub mod sort;
 pub mod vis;
 
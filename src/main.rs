@@ -5,21 +5,49 @@ fn main() {
     println!("\nStart: {}\n", label);
 
     // coord::between_threads::main();
+    // coord::divide::main();
+    // coord::schedule::main();
     // counter::main();
+    // counter::chrome_trace::main();
+    // counter::flamegraph::main();
+    // counter::stats::main();
+    // counter::persist::main();
+    // counter::diff::main();
+    // http::sort_stream::main();
     map::polygon_map::main();
+    // range::coverage::main();
+    // range::interval_set::main();
+    // search::binary_search::main();
+    // search::exponential_search::main();
+    // search::galloping_search::main();
+    // search::interpolation_search::main();
     // sort::bubble_sort::main();
+    // sort::cancel::main();
     // sort::insertion_sort::main();
     // sort::merge_sort::main();
+    // sort::merge_sort_linked_list::main();
     // sort::merge_sort_loop::main();
     // sort::quicksort::main();
     // sort::quicksort_crossbeam::main();
     // sort::quicksort_rayon::main();
     // sort::quicksort_safe::main();
     // sort::sort_vis::main();
+    // sort::sorted_iter::main();
     // sort::test_data::main();
+    // sort::thread_limit::main();
+    // vis::analysis::main();
     // vis::bubble_sort::main();
+    // vis::chrome_trace::main();
+    // vis::gen_html::main();
     // vis::gen_js::main();
+    // vis::live::main();
+    // vis::logical_clock::main();
+    // vis::persist::main();
     // vis::quicksort::main();
+    // vis::raster::main();
+    // vis::replay::main();
+    // vis::terminal::main();
+    // vis::traced_slice::main();
 
     println!("\nEnd: {}\n", label);
 }
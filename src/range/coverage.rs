@@ -0,0 +1,199 @@
+#![allow(dead_code)]
+
+use super::IntervalSet;
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::ops::Range;
+use std::sync::{Mutex, MutexGuard};
+
+pub fn main() {
+    try_coverage();
+}
+
+// Checks while a divide-and-conquer algorithm is running that each index of full_range is finished
+// exactly once. Any thread can report into the same tracker:
+//   - finish_range() when a range is done for good, such as a piece that was bubble sorted or a
+//     single item. This fails if any part of the range was already finished.
+//   - finish_call() when a call that split its range has seen all of its children return. This fails
+//     if any part of the call's range has not been finished yet.
+// Call keys are the same strings RangeCall uses, so an error names the call that caused it. Errors are
+// returned from the report and also kept so they can be looked at after the run. If panic_on_error
+// is true the reporting thread panics instead, which stops a sort at the first mistake. The error is
+// still kept and the lock released first, so the tracker can be looked at after catching the panic.
+pub struct CoverageTracker {
+    pub full_range: Range<usize>,
+    pub panic_on_error: bool,
+    state: Mutex<CoverageState>,
+}
+
+struct CoverageState {
+    // Not coalesced so that each finished range keeps its own start, which is the key into call_keys.
+    finished: IntervalSet<usize>,
+    call_keys: BTreeMap<usize, String>,
+    errors: Vec<CoverageError>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CoverageError {
+    DoubleCoverage { call_key: String, range: Range<usize>, previous_call_key: String, overlap: Range<usize> },
+    Gap { call_key: String, range: Range<usize>, gaps: Vec<Range<usize>> },
+    OutOfRange { call_key: String, range: Range<usize>, full_range: Range<usize> },
+}
+
+impl CoverageTracker {
+
+    pub fn new(full_range: Range<usize>, panic_on_error: bool) -> Self {
+        CoverageTracker {
+            full_range,
+            panic_on_error,
+            state: Mutex::new(CoverageState {
+                finished: IntervalSet::new(false, None),
+                call_keys: BTreeMap::new(),
+                errors: vec![],
+            }),
+        }
+    }
+
+    pub fn finish_range(&self, range: Range<usize>, call_key: &str) -> Result<(), CoverageError> {
+        if range.start >= range.end {
+            return Ok(());
+        }
+        let mut state = self.state.lock().unwrap();
+        if range.start < self.full_range.start || range.end > self.full_range.end {
+            let error = CoverageError::OutOfRange { call_key: call_key.to_string(), range, full_range: self.full_range.clone() };
+            return self.fail(state, error);
+        }
+        let previous = state.finished.overlapping(&range).next();
+        if let Some(previous) = previous {
+            let error = CoverageError::DoubleCoverage {
+                call_key: call_key.to_string(),
+                overlap: range.start.max(previous.start)..range.end.min(previous.end),
+                range,
+                previous_call_key: state.call_keys[&previous.start].clone(),
+            };
+            return self.fail(state, error);
+        }
+        state.call_keys.insert(range.start, call_key.to_string());
+        state.finished.insert(range).unwrap();
+        Ok(())
+    }
+
+    pub fn finish_call(&self, range: Range<usize>, call_key: &str) -> Result<(), CoverageError> {
+        if range.start >= range.end {
+            return Ok(());
+        }
+        let state = self.state.lock().unwrap();
+        let gaps: Vec<Range<usize>> = state.finished.complement(&range).iter().collect();
+        if !gaps.is_empty() {
+            let error = CoverageError::Gap { call_key: call_key.to_string(), range, gaps };
+            return self.fail(state, error);
+        }
+        Ok(())
+    }
+
+    // Call once the algorithm has returned. Checks that the whole of full_range was finished and
+    // returns every error found during the run.
+    pub fn finish(&self) -> Result<(), Vec<CoverageError>> {
+        // Any error from this check is also added to the list.
+        let _ = self.finish_call(self.full_range.clone(), "(end)");
+        let errors = self.errors();
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    pub fn errors(&self) -> Vec<CoverageError> {
+        self.state.lock().unwrap().errors.clone()
+    }
+
+    pub fn finished_range_count(&self) -> usize {
+        self.state.lock().unwrap().finished.len()
+    }
+
+    // Takes the guard so it can be dropped before panicking, which would otherwise poison the mutex.
+    fn fail(&self, mut state: MutexGuard<CoverageState>, error: CoverageError) -> Result<(), CoverageError> {
+        state.errors.push(error.clone());
+        drop(state);
+        if self.panic_on_error {
+            panic!("{}", error);
+        }
+        Err(error)
+    }
+}
+
+impl Display for CoverageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoverageError::DoubleCoverage { call_key, range, previous_call_key, overlap } =>
+                write!(f, "call \"{}\" finished {:?} but {:?} was already finished by call \"{}\"", call_key, range, overlap, previous_call_key),
+            CoverageError::Gap { call_key, range, gaps } =>
+                write!(f, "call \"{}\" returned from {:?} with {:?} not finished", call_key, range, gaps),
+            CoverageError::OutOfRange { call_key, range, full_range } =>
+                write!(f, "call \"{}\" finished {:?} which is outside {:?}", call_key, range, full_range),
+        }
+    }
+}
+
+impl Error for CoverageError {}
+
+fn try_coverage() {
+    let tracker = CoverageTracker::new(0..100, false);
+    dbg!(tracker.finish_range(0..40, "00"));
+    dbg!(tracker.finish_range(30..50, "01"));
+    dbg!(tracker.finish_call(0..60, "0"));
+    dbg!(tracker.finish_range(90..110, "11"));
+    dbg!(tracker.finish());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::between_threads;
+    use crate::sort::{quicksort_rayon, test_data};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_double_coverage() {
+        let tracker = CoverageTracker::new(0..100, false);
+        tracker.finish_range(0..40, "00").unwrap();
+        assert_eq!(Err(CoverageError::DoubleCoverage { call_key: "01".to_string(), range: 30..50, previous_call_key: "00".to_string(), overlap: 30..40 }),
+                   tracker.finish_range(30..50, "01"));
+        assert_eq!(1, tracker.errors().len());
+    }
+
+    #[test]
+    fn test_gap() {
+        let tracker = CoverageTracker::new(0..100, false);
+        tracker.finish_range(0..40, "00").unwrap();
+        tracker.finish_range(50..60, "01").unwrap();
+        assert_eq!(Err(CoverageError::Gap { call_key: "0".to_string(), range: 0..60, gaps: vec![40..50] }),
+                   tracker.finish_call(0..60, "0"));
+        tracker.finish_range(60..100, "1").unwrap();
+        assert_eq!(2, tracker.finish().unwrap_err().len());
+    }
+
+    #[test]
+    fn test_panic_leaves_tracker_usable() {
+        let tracker = CoverageTracker::new(0..100, true);
+        tracker.finish_range(0..40, "00").unwrap();
+        assert!(std::panic::catch_unwind(|| tracker.finish_range(30..50, "01")).is_err());
+        assert_eq!(1, tracker.errors().len());
+        assert_eq!(1, tracker.finished_range_count());
+    }
+
+    #[test]
+    fn test_quicksort_rayon_coverage() {
+        let mut v = test_data::vec_usize_shuffled(100_000);
+        let tracker = CoverageTracker::new(0..v.len(), true);
+        quicksort_rayon::quicksort_rayon_coverage(&mut v, 7, 1_000, &tracker);
+        assert!(v.is_sorted());
+        assert_eq!(Ok(()), tracker.finish());
+    }
+
+    #[test]
+    fn test_divide_parallel_coverage() {
+        let tracker = Arc::new(CoverageTracker::new(0..100_000, true));
+        between_threads::divide_parallel_coverage(0, 100_000, 10, 1_000, 0, Arc::clone(&tracker));
+        assert_eq!(Ok(()), tracker.finish());
+    }
+}
@@ -0,0 +1,390 @@
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+use std::ops::Range;
+
+pub fn main() {
+    try_interval_set();
+}
+
+// A set of half-open intervals over any ordered index, kept as a map from each interval's start to its
+// end. The intervals never overlap, so the one that might contain a point is always the one with the
+// greatest start at or before that point, and every lookup is a single range query on the map.
+//
+// If coalesce is true, intervals that touch such as 0..5 and 5..9 are joined into 0..9 when added.
+// Otherwise they're kept apart, which is how to record which pieces a range was built from. The
+// results of union(), intersection(), difference() and complement() are always coalesced.
+//
+// If bounds is set, insert() rejects anything outside them, and gaps() and complement_in_bounds()
+// work within them.
+#[derive(Clone, PartialEq, Eq)]
+pub struct IntervalSet<I: Ord + Copy> {
+    pub coalesce: bool,
+    pub bounds: Option<Range<I>>,
+    intervals: BTreeMap<I, I>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IntervalError<I> {
+    // The range's start is not before its end.
+    Empty(Range<I>),
+    OutOfBounds { range: Range<I>, bounds: Range<I> },
+    Overlap { range: Range<I>, existing: Range<I> },
+    // The range to remove is not entirely inside one interval of the set.
+    NotCovered(Range<I>),
+}
+
+impl <I: Ord + Copy> IntervalSet<I> {
+
+    pub fn new(coalesce: bool, bounds: Option<Range<I>>) -> Self {
+        IntervalSet {
+            coalesce,
+            bounds,
+            intervals: BTreeMap::new(),
+        }
+    }
+
+    pub fn from_ranges<T: IntoIterator<Item = Range<I>>>(ranges: T) -> Self {
+        let mut set = Self::new(true, None);
+        for range in ranges {
+            set.insert_merging(range);
+        }
+        set
+    }
+
+    pub fn len(&self) -> usize {
+        self.intervals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.intervals.clear();
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = Range<I>> + '_ {
+        self.intervals.iter().map(|(start, end)| *start..*end)
+    }
+
+    pub fn first(&self) -> Option<Range<I>> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<Range<I>> {
+        self.iter().next_back()
+    }
+
+    // From the start of the first interval to the end of the last.
+    pub fn span(&self) -> Option<Range<I>> {
+        match (self.first(), self.last()) {
+            (Some(first), Some(last)) => Some(first.start..last.end),
+            _ => None,
+        }
+    }
+
+    // Adds a range that must not overlap anything already in the set.
+    pub fn insert(&mut self, range: Range<I>) -> Result<(), IntervalError<I>> {
+        self.check_range(&range)?;
+        if let Some(bounds) = &self.bounds {
+            if range.start < bounds.start || range.end > bounds.end {
+                return Err(IntervalError::OutOfBounds { range, bounds: bounds.clone() });
+            }
+        }
+        // Only the last interval starting before the new range ends can reach into it.
+        if let Some(existing) = self.last_starting_before(range.end) {
+            if existing.end > range.start {
+                return Err(IntervalError::Overlap { range, existing });
+            }
+        }
+        self.insert_unchecked(range);
+        Ok(())
+    }
+
+    // Adds a range, absorbing any intervals it overlaps or touches. Ignores bounds and coalesce.
+    pub fn insert_merging(&mut self, range: Range<I>) {
+        if range.start >= range.end {
+            return;
+        }
+        let mut start = range.start;
+        let mut end = range.end;
+        let absorbed: Vec<Range<I>> = self.intervals.range(..=range.end)
+            .rev()
+            .map(|(start, end)| *start..*end)
+            .take_while(|existing| existing.end >= range.start)
+            .collect();
+        for existing in absorbed {
+            self.intervals.remove(&existing.start);
+            start = start.min(existing.start);
+            end = end.max(existing.end);
+        }
+        self.intervals.insert(start, end);
+    }
+
+    // Takes out a range that must lie entirely inside one interval of the set, leaving whatever is
+    // left of that interval on either side.
+    pub fn remove(&mut self, range: Range<I>) -> Result<(), IntervalError<I>> {
+        self.check_range(&range)?;
+        match self.get_containing(range.start) {
+            Some(existing) if existing.end >= range.end => {
+                self.intervals.remove(&existing.start);
+                if existing.start < range.start {
+                    self.intervals.insert(existing.start, range.start);
+                }
+                if range.end < existing.end {
+                    self.intervals.insert(range.end, existing.end);
+                }
+                Ok(())
+            },
+            _ => Err(IntervalError::NotCovered(range)),
+        }
+    }
+
+    // Takes out whatever part of the range is in the set, which may be nothing.
+    pub fn remove_overlapping(&mut self, range: Range<I>) {
+        for existing in self.overlapping(&range).collect::<Vec<_>>() {
+            self.intervals.remove(&existing.start);
+            if existing.start < range.start {
+                self.intervals.insert(existing.start, range.start);
+            }
+            if range.end < existing.end {
+                self.intervals.insert(range.end, existing.end);
+            }
+        }
+    }
+
+    pub fn contains_point(&self, point: I) -> bool {
+        self.get_containing(point).is_some()
+    }
+
+    // The interval that the point falls in, if any.
+    pub fn get_containing(&self, point: I) -> Option<Range<I>> {
+        self.intervals.range(..=point)
+            .next_back()
+            .filter(|(_start, end)| **end > point)
+            .map(|(start, end)| *start..*end)
+    }
+
+    // True if the whole range is inside a single interval.
+    pub fn contains_range(&self, range: &Range<I>) -> bool {
+        range.start < range.end && self.get_containing(range.start).map_or(false, |existing| existing.end >= range.end)
+    }
+
+    pub fn overlaps(&self, range: &Range<I>) -> bool {
+        self.overlapping(range).next().is_some()
+    }
+
+    // The intervals that share at least one point with the range, in order.
+    pub fn overlapping<'a>(&'a self, range: &Range<I>) -> impl Iterator<Item = Range<I>> + 'a {
+        let (start, end) = (range.start, range.end);
+        // An interval starting before the range can still reach into it, but only the last such one.
+        let first_start = self.intervals.range(..start)
+            .next_back()
+            .filter(|(_start, existing_end)| **existing_end > start)
+            .map_or(start, |(existing_start, _end)| *existing_start);
+        self.intervals.range(first_start..)
+            .map(|(start, end)| *start..*end)
+            .take_while(move |existing| start < end && existing.start < end)
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut set = self.coalesced();
+        for range in other.iter() {
+            set.insert_merging(range);
+        }
+        set
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut set = Self::new(true, self.bounds.clone());
+        for range in self.iter() {
+            for other_range in other.overlapping(&range) {
+                set.insert_merging(range.start.max(other_range.start)..range.end.min(other_range.end));
+            }
+        }
+        set
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut set = self.coalesced();
+        for range in other.iter() {
+            set.remove_overlapping(range);
+        }
+        set
+    }
+
+    // Everything within the given range that is not in the set.
+    pub fn complement(&self, within: &Range<I>) -> Self {
+        let mut set = Self::new(true, self.bounds.clone());
+        set.insert_merging(within.clone());
+        for range in self.overlapping(within) {
+            set.remove_overlapping(range);
+        }
+        set
+    }
+
+    pub fn complement_in_bounds(&self) -> Option<Self> {
+        self.bounds.as_ref().map(|bounds| self.complement(bounds))
+    }
+
+    // The parts of the bounds not covered by the set. Without bounds, the parts between the first and
+    // last intervals.
+    pub fn gaps(&self) -> Vec<Range<I>> {
+        match self.bounds.clone().or_else(|| self.span()) {
+            Some(within) => self.complement(&within).iter().collect(),
+            None => vec![],
+        }
+    }
+
+    // True if the intervals cover the bounds, or the span if there are no bounds, with no gaps.
+    pub fn is_complete(&self) -> bool {
+        self.gaps().is_empty() && (self.bounds.is_none() || !self.is_empty())
+    }
+
+    fn coalesced(&self) -> Self {
+        let mut set = Self::new(true, self.bounds.clone());
+        for range in self.iter() {
+            set.insert_merging(range);
+        }
+        set
+    }
+
+    fn check_range(&self, range: &Range<I>) -> Result<(), IntervalError<I>> {
+        if range.start < range.end {
+            Ok(())
+        } else {
+            Err(IntervalError::Empty(range.clone()))
+        }
+    }
+
+    fn last_starting_before(&self, point: I) -> Option<Range<I>> {
+        self.intervals.range(..point).next_back().map(|(start, end)| *start..*end)
+    }
+
+    // Assumes the range doesn't overlap anything in the set.
+    fn insert_unchecked(&mut self, range: Range<I>) {
+        let mut start = range.start;
+        let mut end = range.end;
+        if self.coalesce {
+            if let Some(before) = self.last_starting_before(start) {
+                if before.end == start {
+                    self.intervals.remove(&before.start);
+                    start = before.start;
+                }
+            }
+            if let Some(after_end) = self.intervals.remove(&end) {
+                end = after_end;
+            }
+        }
+        self.intervals.insert(start, end);
+    }
+}
+
+impl <I: Ord + Copy> Default for IntervalSet<I> {
+    fn default() -> Self {
+        Self::new(true, None)
+    }
+}
+
+impl <I: Ord + Copy + Debug> Debug for IntervalSet<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl <I: Debug> Display for IntervalError<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntervalError::Empty(range) => write!(f, "empty range {:?}", range),
+            IntervalError::OutOfBounds { range, bounds } => write!(f, "range {:?} is outside the bounds {:?}", range, bounds),
+            IntervalError::Overlap { range, existing } => write!(f, "range {:?} overlaps {:?}", range, existing),
+            IntervalError::NotCovered(range) => write!(f, "range {:?} is not inside a single interval", range),
+        }
+    }
+}
+
+impl <I: Debug> Error for IntervalError<I> {}
+
+fn try_interval_set() {
+    let mut set = IntervalSet::new(true, Some(0..100));
+    set.insert(10..20).unwrap();
+    set.insert(20..30).unwrap();
+    set.insert(50..60).unwrap();
+    dbg!(&set, set.gaps());
+    dbg!(set.insert(25..35), set.insert(90..110), set.remove(28..52));
+    set.remove(12..15).unwrap();
+    dbg!(&set, set.overlapping(&(14..55)).collect::<Vec<_>>());
+    let other = IntervalSet::from_ranges(vec![0..11, 29..51]);
+    dbg!(set.union(&other), set.intersection(&other), set.difference(&other), set.complement_in_bounds());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_coalesce() {
+        let mut set = IntervalSet::new(true, None);
+        set.insert(5..10).unwrap();
+        set.insert(0..5).unwrap();
+        set.insert(10..12).unwrap();
+        assert_eq!(vec![0..12], set.iter().collect::<Vec<_>>());
+
+        let mut set = IntervalSet::new(false, None);
+        set.insert(5..10).unwrap();
+        set.insert(0..5).unwrap();
+        assert_eq!(vec![0..5, 5..10], set.iter().collect::<Vec<_>>());
+        assert!(set.gaps().is_empty());
+    }
+
+    #[test]
+    fn test_insert_errors() {
+        let mut set = IntervalSet::new(true, Some(0..100));
+        set.insert(10..20).unwrap();
+        assert_eq!(Err(IntervalError::Overlap { range: 15..25, existing: 10..20 }), set.insert(15..25));
+        assert_eq!(Err(IntervalError::Overlap { range: 0..11, existing: 10..20 }), set.insert(0..11));
+        assert_eq!(Err(IntervalError::OutOfBounds { range: 90..101, bounds: 0..100 }), set.insert(90..101));
+        assert_eq!(Err(IntervalError::Empty(30..30)), set.insert(30..30));
+        assert_eq!(vec![10..20], set.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut set = IntervalSet::new(true, None);
+        set.insert(0..10).unwrap();
+        set.remove(3..5).unwrap();
+        assert_eq!(vec![0..3, 5..10], set.iter().collect::<Vec<_>>());
+        assert_eq!(Err(IntervalError::NotCovered(2..6)), set.remove(2..6));
+        set.remove_overlapping(2..6);
+        assert_eq!(vec![0..2, 6..10], set.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_queries() {
+        let set = IntervalSet::from_ranges(vec![0..5, 10..15, 20..25]);
+        assert!(set.contains_point(0));
+        assert!(!set.contains_point(5));
+        assert_eq!(Some(10..15), set.get_containing(14));
+        assert!(set.contains_range(&(11..15)));
+        assert!(!set.contains_range(&(4..11)));
+        assert_eq!(vec![0..5, 10..15], set.overlapping(&(4..11)).collect::<Vec<_>>());
+        assert!(!set.overlaps(&(5..10)));
+        assert_eq!(vec![5..10, 15..20], set.gaps());
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = IntervalSet::from_ranges(vec![0..10, 20..30]);
+        let b = IntervalSet::from_ranges(vec![5..25]);
+        assert_eq!(vec![0..30], a.union(&b).iter().collect::<Vec<_>>());
+        assert_eq!(vec![5..10, 20..25], a.intersection(&b).iter().collect::<Vec<_>>());
+        assert_eq!(vec![0..5, 25..30], a.difference(&b).iter().collect::<Vec<_>>());
+        assert_eq!(vec![10..20, 30..40], a.complement(&(0..40)).iter().collect::<Vec<_>>());
+        let chars = IntervalSet::from_ranges(vec!['a'..'f', 'x'..'z']);
+        assert!(chars.contains_point('c'));
+        assert_eq!(vec!['f'..'x'], chars.gaps());
+    }
+}
@@ -1,3 +1,9 @@
+pub mod coverage;
+pub use coverage::{CoverageTracker, CoverageError};
+
+pub mod interval_set;
+pub use interval_set::{IntervalSet, IntervalError};
+
 use std::fmt::Display;
 use std::ops::{Range, AddAssign, Sub};
 use std::usize;
@@ -6,6 +12,8 @@ use util::format;
 use itertools::Itertools;
 use num_format::ToFormattedStr;
 
+// Superseded by IntervalSet, which works with any index type, returns errors instead of panicking and
+// answers queries without scanning every range.
 #[derive(Clone)]
 pub struct RangeSet
 {
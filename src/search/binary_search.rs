@@ -0,0 +1,138 @@
+#![allow(dead_code)]
+
+use super::Probes;
+use crate::counter::Counter;
+use crate::sort::SliceCounterItem;
+use crate::sort::test_data::*;
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+pub fn main() {
+    try_bounds();
+    try_counted();
+}
+
+// The comparators here follow slice::binary_search_by(): f(item) says how the item compares to the
+// value being searched for.
+
+// Index of the first item that is not Less than the target, or s.len() if there is none.
+pub fn lower_bound<T: Ord + Debug> (s: &[T], x: &T) -> usize {
+    lower_bound_by(s, |item| item.cmp(x))
+}
+
+pub fn lower_bound_by<T, F> (s: &[T], f: F) -> usize
+    where F: FnMut(&T) -> Ordering
+{
+    lower_bound_in(s, 0, s.len(), f, &mut Probes::none())
+}
+
+pub fn lower_bound_by_counted<T, F> (s: &[T], f: F) -> (usize, Counter<SliceCounterItem>)
+    where F: FnMut(&T) -> Ordering
+{
+    let mut counter = Counter::new();
+    let index = lower_bound_in(s, 0, s.len(), f, &mut Probes::new(&mut counter, "lower bound"));
+    (index, counter)
+}
+
+// Index of the first item that is Greater than the target, or s.len() if there is none.
+pub fn upper_bound<T: Ord + Debug> (s: &[T], x: &T) -> usize {
+    upper_bound_by(s, |item| item.cmp(x))
+}
+
+pub fn upper_bound_by<T, F> (s: &[T], f: F) -> usize
+    where F: FnMut(&T) -> Ordering
+{
+    upper_bound_in(s, 0, s.len(), f, &mut Probes::none())
+}
+
+pub fn upper_bound_by_counted<T, F> (s: &[T], f: F) -> (usize, Counter<SliceCounterItem>)
+    where F: FnMut(&T) -> Ordering
+{
+    let mut counter = Counter::new();
+    let index = upper_bound_in(s, 0, s.len(), f, &mut Probes::new(&mut counter, "upper bound"));
+    (index, counter)
+}
+
+// Same contract as slice::binary_search_by(): Ok with the index of a matching item, or Err with the
+// index where the target could be inserted. Unlike the standard version, a match is always the first
+// of a run of equal items.
+pub fn binary_search_by<T, F> (s: &[T], mut f: F) -> Result<usize, usize>
+    where F: FnMut(&T) -> Ordering
+{
+    let index = lower_bound_in(s, 0, s.len(), &mut f, &mut Probes::none());
+    found_at(s, index, f)
+}
+
+pub fn binary_search_by_counted<T, F> (s: &[T], mut f: F) -> (Result<usize, usize>, Counter<SliceCounterItem>)
+    where F: FnMut(&T) -> Ordering
+{
+    let mut counter = Counter::new();
+    let index = lower_bound_in(s, 0, s.len(), &mut f, &mut Probes::new(&mut counter, "binary search"));
+    (found_at(s, index, f), counter)
+}
+
+// The shared loops. They search only s[from..to] but return absolute indexes so that the other
+// searches can use them to finish off a range they've narrowed down.
+pub(super) fn lower_bound_in<T, F> (s: &[T], mut from: usize, mut to: usize, mut f: F, probes: &mut Probes) -> usize
+    where F: FnMut(&T) -> Ordering
+{
+    debug_assert!(from <= to && to <= s.len());
+    while from < to {
+        let mid = from + (to - from) / 2;
+        probes.probe(from, to);
+        if f(&s[mid]) == Ordering::Less {
+            from = mid + 1;
+        } else {
+            to = mid;
+        }
+    }
+    from
+}
+
+pub(super) fn upper_bound_in<T, F> (s: &[T], mut from: usize, mut to: usize, mut f: F, probes: &mut Probes) -> usize
+    where F: FnMut(&T) -> Ordering
+{
+    debug_assert!(from <= to && to <= s.len());
+    while from < to {
+        let mid = from + (to - from) / 2;
+        probes.probe(from, to);
+        if f(&s[mid]) == Ordering::Greater {
+            to = mid;
+        } else {
+            from = mid + 1;
+        }
+    }
+    from
+}
+
+// Turns a lower bound into the Result returned by the binary_search_by() style functions.
+pub(super) fn found_at<T, F> (s: &[T], index: usize, mut f: F) -> Result<usize, usize>
+    where F: FnMut(&T) -> Ordering
+{
+    if index < s.len() && f(&s[index]) == Ordering::Equal {
+        Ok(index)
+    } else {
+        Err(index)
+    }
+}
+
+fn try_bounds() {
+    let v = vec![1, 2, 2, 2, 3, 5, 8];
+    for x in 0..=9 {
+        let lower = lower_bound(&v, &x);
+        let upper = upper_bound(&v, &x);
+        println!("x = {}: lower = {}, upper = {}, binary_search = {:?}", x, lower, upper, binary_search_by(&v, |item| item.cmp(&x)));
+        assert!(v[..lower].iter().all(|item| *item < x));
+        assert!(v[lower..upper].iter().all(|item| *item == x));
+        assert!(v[upper..].iter().all(|item| *item > x));
+    }
+}
+
+fn try_counted() {
+    let size = 1_000;
+    let v = vec_usize_ordered(size);
+    let (index, counter) = lower_bound_by_counted(&v, |item| item.cmp(&700));
+    dbg!(index, counter.items.len());
+    counter.dbg(None);
+}
@@ -0,0 +1,64 @@
+#![allow(dead_code)]
+
+use super::Probes;
+use super::binary_search::{found_at, lower_bound_in};
+use crate::counter::Counter;
+use crate::sort::SliceCounterItem;
+use crate::sort::test_data::*;
+
+use std::cmp::{self, Ordering};
+
+pub fn main() {
+    try_exponential_search();
+}
+
+// Checks indexes 1, 2, 4, 8, ... until it passes the target, then binary searches the last gap. This
+// costs O(log i) comparisons where i is the answer, so it beats a plain binary search when the target
+// is likely to be near the start of a long slice. Returns the same thing as binary_search::binary_search_by().
+pub fn exponential_search_by<T, F> (s: &[T], mut f: F) -> Result<usize, usize>
+    where F: FnMut(&T) -> Ordering
+{
+    let index = exponential_lower_bound(s, &mut f, &mut Probes::none());
+    found_at(s, index, f)
+}
+
+pub fn exponential_search_by_counted<T, F> (s: &[T], mut f: F) -> (Result<usize, usize>, Counter<SliceCounterItem>)
+    where F: FnMut(&T) -> Ordering
+{
+    let mut counter = Counter::new();
+    let index = exponential_lower_bound(s, &mut f, &mut Probes::new(&mut counter, "exponential search"));
+    (found_at(s, index, f), counter)
+}
+
+fn exponential_lower_bound<T, F> (s: &[T], mut f: F, probes: &mut Probes) -> usize
+    where F: FnMut(&T) -> Ordering
+{
+    let s_len = s.len();
+    if s_len == 0 {
+        return 0;
+    }
+    probes.probe(0, s_len);
+    if f(&s[0]) != Ordering::Less {
+        return 0;
+    }
+    // Invariant: s[bound / 2] is Less than the target.
+    let mut bound = 1;
+    while bound < s_len {
+        probes.probe(bound / 2, s_len);
+        if f(&s[bound]) != Ordering::Less {
+            break;
+        }
+        bound *= 2;
+    }
+    lower_bound_in(s, bound / 2 + 1, cmp::min(bound + 1, s_len), f, probes)
+}
+
+fn try_exponential_search() {
+    let size = 1_000;
+    let v = vec_usize_ordered(size);
+    for x in [0, 1, 2, 3, 17, 500, 999, 1_000, 1_001].iter() {
+        let (result, counter) = exponential_search_by_counted(&v, |item| item.cmp(x));
+        assert_eq!(v.binary_search(x), result);
+        println!("x = {}: {:?} after {} probes", x, result, counter.items.len());
+    }
+}
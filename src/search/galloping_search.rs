@@ -0,0 +1,97 @@
+#![allow(dead_code)]
+
+use super::Probes;
+use super::binary_search::{found_at, lower_bound_in};
+use crate::counter::Counter;
+use crate::sort::SliceCounterItem;
+use crate::sort::test_data::*;
+
+use std::cmp::Ordering;
+
+pub fn main() {
+    try_galloping_search();
+}
+
+// Like exponential search but starting from a hint instead of the front of the slice, galloping
+// right or left from the hint as needed. This is the search TimSort uses while merging, where the next
+// insertion point is usually close to the last one. The cost is O(log d) comparisons where d is the
+// distance from the hint to the answer. Returns the same thing as binary_search::binary_search_by().
+pub fn galloping_search_by<T, F> (s: &[T], hint: usize, mut f: F) -> Result<usize, usize>
+    where F: FnMut(&T) -> Ordering
+{
+    let index = galloping_lower_bound(s, hint, &mut f, &mut Probes::none());
+    found_at(s, index, f)
+}
+
+pub fn galloping_search_by_counted<T, F> (s: &[T], hint: usize, mut f: F) -> (Result<usize, usize>, Counter<SliceCounterItem>)
+    where F: FnMut(&T) -> Ordering
+{
+    let mut counter = Counter::new();
+    let index = galloping_lower_bound(s, hint, &mut f, &mut Probes::new(&mut counter, "galloping search"));
+    (found_at(s, index, f), counter)
+}
+
+// The lower bound of the target, found by galloping out from the hint.
+pub fn galloping_lower_bound_by<T, F> (s: &[T], hint: usize, f: F) -> usize
+    where F: FnMut(&T) -> Ordering
+{
+    galloping_lower_bound(s, hint, f, &mut Probes::none())
+}
+
+fn galloping_lower_bound<T, F> (s: &[T], hint: usize, mut f: F, probes: &mut Probes) -> usize
+    where F: FnMut(&T) -> Ordering
+{
+    let s_len = s.len();
+    if s_len == 0 {
+        return 0;
+    }
+    let hint = hint.min(s_len - 1);
+    probes.probe(0, s_len);
+    if f(&s[hint]) == Ordering::Less {
+        // The answer is to the right of the hint. Each step doubles the distance from the hint while
+        // s[last_less] stays Less than the target.
+        let mut last_less = hint;
+        let mut step = 1;
+        loop {
+            let next = hint + step;
+            if next >= s_len {
+                return lower_bound_in(s, last_less + 1, s_len, f, probes);
+            }
+            probes.probe(last_less + 1, s_len);
+            if f(&s[next]) != Ordering::Less {
+                return lower_bound_in(s, last_less + 1, next, f, probes);
+            }
+            last_less = next;
+            step *= 2;
+        }
+    } else {
+        // The answer is at or to the left of the hint. Now s[first_not_less] is never Less than the
+        // target.
+        let mut first_not_less = hint;
+        let mut step = 1;
+        loop {
+            if step > hint {
+                return lower_bound_in(s, 0, first_not_less, f, probes);
+            }
+            let next = hint - step;
+            probes.probe(0, first_not_less + 1);
+            if f(&s[next]) == Ordering::Less {
+                return lower_bound_in(s, next + 1, first_not_less, f, probes);
+            }
+            first_not_less = next;
+            step *= 2;
+        }
+    }
+}
+
+fn try_galloping_search() {
+    let size = 1_000;
+    let v = vec_usize_ordered(size);
+    for hint in [0, 10, 500, 999, 5_000].iter() {
+        for x in [0, 1, 2, 11, 12, 30, 500, 999, 1_000, 1_001].iter() {
+            let (result, counter) = galloping_search_by_counted(&v, *hint, |item| item.cmp(x));
+            assert_eq!(v.binary_search(x), result);
+            println!("hint = {}, x = {}: {:?} after {} probes", hint, x, result, counter.items.len());
+        }
+    }
+}
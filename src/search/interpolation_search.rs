@@ -0,0 +1,100 @@
+#![allow(dead_code)]
+
+use super::Probes;
+use super::binary_search::{found_at, lower_bound_in};
+use crate::counter::Counter;
+use crate::sort::SliceCounterItem;
+use crate::sort::test_data::*;
+
+use std::cmp::Ordering;
+
+pub fn main() {
+    try_interpolation_search();
+}
+
+// Interpolation needs to know how far apart two keys are, not just their order, so instead of a
+// comparator it takes a function that maps each item to a numeric key.
+pub trait InterpolationKey: Ord + Copy {
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! impl_interpolation_key {
+    ($($t:ty),*) => {
+        $(
+            impl InterpolationKey for $t {
+                fn to_f64(self) -> f64 { self as f64 }
+            }
+        )*
+    }
+}
+
+impl_interpolation_key!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+// Guesses the position of the target from where its key falls between the keys at the ends of the
+// range. On evenly distributed keys such as vec_usize_ordered() this takes O(log log n) probes. A bad
+// guess can only shrink the range by one, so after max_probes guesses it falls back to a binary
+// search to keep the worst case at O(log n). Returns the same thing as binary_search::binary_search_by().
+pub fn interpolation_search_by_key<T, K, F> (s: &[T], key: K, mut f: F) -> Result<usize, usize>
+    where K: InterpolationKey, F: FnMut(&T) -> K
+{
+    let index = interpolation_lower_bound(s, key, &mut f, &mut Probes::none());
+    found_at(s, index, |item| f(item).cmp(&key))
+}
+
+pub fn interpolation_search_by_key_counted<T, K, F> (s: &[T], key: K, mut f: F) -> (Result<usize, usize>, Counter<SliceCounterItem>)
+    where K: InterpolationKey, F: FnMut(&T) -> K
+{
+    let mut counter = Counter::new();
+    let index = interpolation_lower_bound(s, key, &mut f, &mut Probes::new(&mut counter, "interpolation search"));
+    (found_at(s, index, |item| f(item).cmp(&key)), counter)
+}
+
+pub fn interpolation_search<K: InterpolationKey> (s: &[K], key: K) -> Result<usize, usize> {
+    interpolation_search_by_key(s, key, |item| *item)
+}
+
+fn interpolation_lower_bound<T, K, F> (s: &[T], key: K, mut f: F, probes: &mut Probes) -> usize
+    where K: InterpolationKey, F: FnMut(&T) -> K
+{
+    let mut from = 0;
+    let mut to = s.len();
+    let max_probes = 2 * (usize::max_value().count_ones() - s.len().leading_zeros()) as usize;
+    let mut probe_count = 0;
+    // Invariant: everything before from is Less than the key and everything from to on is not.
+    while from < to && probe_count < max_probes {
+        let low_key = f(&s[from]);
+        if low_key >= key {
+            return from;
+        }
+        let high_key = f(&s[to - 1]);
+        if high_key < key {
+            return to;
+        }
+        // Now s[from] < key <= s[to - 1] so the range has at least two items and the keys differ.
+        let fraction = (key.to_f64() - low_key.to_f64()) / (high_key.to_f64() - low_key.to_f64());
+        let guess = from + 1 + ((to - from - 2) as f64 * fraction) as usize;
+        let guess = guess.min(to - 1);
+        probes.probe(from, to);
+        probe_count += 1;
+        if f(&s[guess]) < key {
+            from = guess + 1;
+        } else {
+            to = guess;
+        }
+    }
+    lower_bound_in(s, from, to, |item| f(item).cmp(&key), probes)
+}
+
+fn try_interpolation_search() {
+    let size = 1_000_000;
+    let v = vec_usize_ordered(size);
+    for x in [0, 1, 2, 17, 500_000, 999_999, 1_000_000, 1_000_001].iter() {
+        let (result, counter) = interpolation_search_by_key_counted(&v, *x, |item| *item);
+        assert_eq!(v.binary_search(x), result);
+        println!("x = {}: {:?} after {} probes", x, result, counter.items.len());
+    }
+    let v: Vec<usize> = (0..1_000).map(|x| x * x).collect();
+    for x in [0, 1, 2, 250_000, 998_001, 998_002].iter() {
+        assert_eq!(v.binary_search(x), interpolation_search(&v, *x));
+    }
+}
@@ -0,0 +1,67 @@
+pub mod binary_search;
+pub use binary_search::*;
+
+pub mod exponential_search;
+pub use exponential_search::*;
+
+pub mod galloping_search;
+pub use galloping_search::*;
+
+pub mod interpolation_search;
+pub use interpolation_search::*;
+
+use crate::counter::Counter;
+use crate::sort::SliceCounterItem;
+
+// Instrumentation shared by the searches. Each probe (one call to the comparator) is recorded as a
+// CounterItem whose data is the range still being searched, and whose parent is the previous probe,
+// so Counter::dbg() shows the range narrowing one level per probe just as it shows the splits of a
+// sort. Without a counter the calls do nothing.
+pub struct Probes<'a> {
+    counter: Option<&'a mut Counter<SliceCounterItem>>,
+    method: &'static str,
+    parent_index: Option<usize>,
+    probe_count: usize,
+}
+
+impl <'a> Probes<'a> {
+    pub fn none() -> Self {
+        Self {
+            counter: None,
+            method: "",
+            parent_index: None,
+            probe_count: 0,
+        }
+    }
+
+    pub fn new(counter: &'a mut Counter<SliceCounterItem>, method: &'static str) -> Self {
+        Self {
+            counter: Some(counter),
+            method,
+            parent_index: None,
+            probe_count: 0,
+        }
+    }
+
+    // Records a probe at some index while the answer is known to be in start_index..end_index.
+    pub fn probe(&mut self, start_index: usize, end_index: usize) {
+        self.probe_count += 1;
+        if let Some(counter) = self.counter.as_mut() {
+            let index = counter.start(
+                end_index - start_index,
+                Some(SliceCounterItem {
+                    start_index,
+                    end_index,
+                    method: Some(self.method.to_string()),
+                }),
+                false,
+                self.parent_index);
+            counter.end(index);
+            self.parent_index = Some(index);
+        }
+    }
+
+    pub fn probe_count(&self) -> usize {
+        self.probe_count
+    }
+}
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
 use super::test_data::*;
+use super::sort_slice::SortSlice;
 
 use std::cmp::Ordering;
 use std::fmt::Debug;
@@ -14,16 +15,22 @@ pub fn main() {
 }
 
 #[inline]
-pub fn bubble_sort<T: PartialOrd + Debug> (v: &mut [T]) {
+pub fn bubble_sort<T: PartialOrd + Debug> (mut v: &mut [T]) {
+    bubble_sort_on(&mut v);
+}
+
+// bubble_sort() for anything that implements SortSlice, such as a TracedSlice.
+#[inline]
+pub fn bubble_sort_on<S: SortSlice> (s: &mut S) {
     let mut did_swap = true;
-    for i in (1..v.len()).rev() {
+    for i in (1..s.len()).rev() {
         if !did_swap {
             return;
         }
         did_swap = false;
         for j in 0..i {
-            if v[j] > v[j + 1] {
-                v.swap(j, j + 1);
+            if s.less(j + 1, j) {
+                s.swap(j, j + 1);
                 did_swap = true;
             }
         }
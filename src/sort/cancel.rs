@@ -0,0 +1,182 @@
+#![allow(dead_code)]
+
+use std::fmt::{self, Display};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+pub fn main() {
+    try_cancel_token();
+}
+
+// Shared between a caller and a running sort. The sort checks it before each split and gives up
+// once it's cancelled or past its deadline. Clones share the same flag.
+//
+// The cancellable sorts are quicksort_rayon, quicksort_crossbeam, merge_sort_loop and
+// quicksort_parallel_ptr. The recording sorts in vis and the traced_slice sorts don't take a token
+// since they're for watching a whole run afterward, and a recording that stopped partway would leave
+// the animation with calls that never end.
+#[derive(Clone, Debug)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Cancelled {
+    Requested,
+    DeadlineExpired,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: None,
+        }
+    }
+
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: Some(deadline),
+        }
+    }
+
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.check().is_err()
+    }
+
+    // Called by the sorts at each split. A cancel() wins over an expired deadline if both apply.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.cancelled.load(Ordering::Acquire) {
+            return Err(Cancelled::Requested);
+        }
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                return Err(Cancelled::DeadlineExpired);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cancelled::Requested => write!(f, "sort cancelled"),
+            Cancelled::DeadlineExpired => write!(f, "sort deadline expired"),
+        }
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+fn try_cancel_token() {
+    let token = CancelToken::new();
+    dbg!(token.check());
+    token.clone().cancel();
+    dbg!(token.check());
+    let token = CancelToken::with_timeout(Duration::from_millis(0));
+    dbg!(token.check());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sort::{merge_sort_loop, quicksort_crossbeam, quicksort_ptr, quicksort_rayon};
+    use crate::sort::test_data::vec_usize_shuffled;
+    use std::cmp::Ordering as CmpOrdering;
+    use std::sync::Mutex;
+    use std::sync::atomic::AtomicUsize;
+
+    lazy_static! {
+        static ref TRIP_TOKEN: Mutex<CancelToken> = Mutex::new(CancelToken::new());
+    }
+    static COMPARISONS: AtomicUsize = AtomicUsize::new(0);
+    static TRIP_AT: AtomicUsize = AtomicUsize::new(0);
+
+    // A value that cancels TRIP_TOKEN after a set number of comparisons, so that a sort is cancelled
+    // partway through no matter how fast the machine is.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    struct Tripwire(usize);
+
+    impl PartialOrd for Tripwire {
+        fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Tripwire {
+        fn cmp(&self, other: &Self) -> CmpOrdering {
+            if COMPARISONS.fetch_add(1, Ordering::SeqCst) + 1 == TRIP_AT.load(Ordering::SeqCst) {
+                TRIP_TOKEN.lock().unwrap().cancel();
+            }
+            self.0.cmp(&other.0)
+        }
+    }
+
+    fn arm_tripwire(trip_at: usize) -> CancelToken {
+        let token = CancelToken::new();
+        *TRIP_TOKEN.lock().unwrap() = token.clone();
+        COMPARISONS.store(0, Ordering::SeqCst);
+        TRIP_AT.store(trip_at, Ordering::SeqCst);
+        token
+    }
+
+    fn assert_permutation(original: &[usize], v: &[Tripwire]) {
+        let mut original = original.to_vec();
+        original.sort_unstable();
+        let mut v: Vec<usize> = v.iter().map(|x| x.0).collect();
+        v.sort_unstable();
+        assert_eq!(original, v, "The slice should still hold exactly the original values.");
+    }
+
+    #[test]
+    fn test_cancel_mid_sort() {
+        let size = 20_000;
+        let trip_at = 5_000;
+        let original = vec_usize_shuffled(size);
+        let sorts: Vec<(&str, fn(&mut [Tripwire], &CancelToken) -> Result<(), Cancelled>)> = vec![
+            ("quicksort_rayon", |s, token| quicksort_rayon::quicksort_rayon_cancellable(s, 10, 1_000, token)),
+            ("quicksort_crossbeam", |s, token| quicksort_crossbeam::quicksort_crossbeam_cancellable(s, 10, 1_000, 4, token)),
+            ("merge_sort_loop", |s, token| merge_sort_loop::merge_sort_loop_cancellable(s, 10, 8, token)),
+            ("quicksort_parallel_ptr", |s, token| quicksort_ptr::quicksort_parallel_ptr_cancellable(s, 0.0, 1_000, 10, 4, token)),
+        ];
+        for (label, sort_func) in sorts {
+            let token = arm_tripwire(trip_at);
+            let mut v: Vec<Tripwire> = original.iter().map(|x| Tripwire(*x)).collect();
+            assert_eq!(Err(Cancelled::Requested), sort_func(&mut v, &token), "{}", label);
+            assert!(!v.is_sorted(), "{} should have stopped before finishing.", label);
+            assert_permutation(&original, &v);
+        }
+    }
+
+    #[test]
+    fn test_deadline_expired() {
+        let original = vec_usize_shuffled(1_000);
+        let mut v = original.clone();
+        let token = CancelToken::with_timeout(Duration::from_millis(0));
+        assert_eq!(Err(Cancelled::DeadlineExpired), quicksort_rayon::quicksort_rayon_cancellable(&mut v, 10, 100, &token));
+        assert_eq!(original, v);
+    }
+
+    #[test]
+    fn test_not_cancelled() {
+        let mut v = vec_usize_shuffled(10_000);
+        let token = CancelToken::with_timeout(Duration::from_secs(60));
+        assert_eq!(Ok(()), quicksort_crossbeam::quicksort_crossbeam_cancellable(&mut v, 10, 1_000, 4, &token));
+        assert!(v.is_sorted());
+        let mut v = vec_usize_shuffled(10_000);
+        assert_eq!(Ok(()), quicksort_ptr::quicksort_parallel_ptr_cancellable(&mut v, 0.0, 1_000, 10, 4, &token));
+        assert!(v.is_sorted());
+    }
+}
@@ -12,6 +12,7 @@ pub fn main() {
     // try_large_vector();
     // try_insertion_sort_small();
     try_insertion_sort_ptr();
+    // try_insertion_sort_on();
 }
 
 pub fn insertion_sort<T: Ord + Debug> (v: &mut Vec<T>) {
@@ -143,6 +144,18 @@ pub fn insertion_sort_ptr<T: Ord + Debug> (v: &mut Vec<T>) {
     *v = sorted;
 }
 
+// An in-place insertion sort that only swaps neighbors, written against SortSlice so that it can be
+// traced. Each item is swapped down until the one before it is no bigger.
+pub fn insertion_sort_on<S: super::sort_slice::SortSlice> (s: &mut S) {
+    for i in 1..s.len() {
+        let mut j = i;
+        while j > 0 && s.less(j, j - 1) {
+            s.swap(j, j - 1);
+            j -= 1;
+        }
+    }
+}
+
 fn try_small_vectors() {
     for i in 1..=5 {
         let mut v = vec_usize_shuffled(i);
@@ -179,6 +192,14 @@ fn try_insertion_sort_small() {
 
 }
 
+fn try_insertion_sort_on() {
+    for i in 0..=20 {
+        let mut v = vec_usize_shuffled(i);
+        insertion_sort_on(&mut &mut v[..]);
+        assert!(&v.is_sorted());
+    }
+}
+
 fn try_insertion_sort_ptr() {
     // let mut v = vec![2, 1, 3];
     // dbg!(&v);
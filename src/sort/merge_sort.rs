@@ -2,12 +2,14 @@
 
 use super::*;
 use super::test_data::*;
+use super::sort_slice::SortSlice;
 
 use std::cmp::Ordering;
 use std::fmt::Debug;
 use std::mem;
 use itertools::Itertools;
 use std::convert::TryInto;
+use std::iter::Peekable;
 
 // use super::Sortable;
 
@@ -25,6 +27,8 @@ pub fn main() {
     // try_merge_sort_merge_in_place();
     // try_all_merges();
     // try_all_merge_sorts();
+    // try_merge_iter();
+    // try_merge_sort_on();
 }
 
 pub fn merge_sort<T: PartialOrd + Debug> (v: &mut Vec<T>) {
@@ -147,6 +151,51 @@ pub fn merge_sort_merge_in_place<T: Ord + Debug> (s: &mut [T]) {
     merge_in_place(s, mid);
 }
 
+// A top-down merge sort on a SortSlice, merging with a buffer that holds a copy of the low half. The
+// slice is split and joined again around the recursive calls so that with a TracedSlice each half is
+// its own call. Returns the slice for the caller's join().
+pub fn merge_sort_on<S: super::sort_slice::SortSlice> (s: S) -> S
+    where S::Item: Clone
+{
+    let s_len = s.len();
+    if s_len <= 1 {
+        return s;
+    }
+    let mid = s_len / 2;
+    let (lo, hi) = s.split_at(mid);
+    let lo = merge_sort_on(lo);
+    let hi = merge_sort_on(hi);
+    let mut s = unsafe { S::join(lo, hi) };
+    merge_with_buffer_on(&mut s, mid);
+    s
+}
+
+// Merges the sorted ranges ..mid and mid.. by copying ..mid to a buffer and then filling the slice
+// from the front. An item taken from the high half is swapped into place since whatever it replaces
+// has already been copied to the buffer or moved. Stable since ties are taken from the buffer.
+pub fn merge_with_buffer_on<S: super::sort_slice::SortSlice> (s: &mut S, mid: usize)
+    where S::Item: Clone
+{
+    let s_len = s.len();
+    let mut buffer = Vec::with_capacity(mid);
+    for i in 0..mid {
+        s.copy_to_buffer(i, &mut buffer);
+    }
+    let mut buffer_index = 0;
+    let mut hi_index = mid;
+    let mut to_index = 0;
+    while buffer_index < mid {
+        if hi_index < s_len && s.less_than_buffer(hi_index, &buffer, buffer_index) {
+            s.swap(to_index, hi_index);
+            hi_index += 1;
+        } else {
+            s.copy_from_buffer(to_index, &buffer, buffer_index);
+            buffer_index += 1;
+        }
+        to_index += 1;
+    }
+}
+
 pub fn merge_sort_test_only_no_merge<T: PartialOrd + Debug> (v: &mut Vec<T>) {
     const CROSSOVER_POINT: usize = 10;
     let v_len = v.len();
@@ -356,6 +405,53 @@ pub fn merge_reverse<T: PartialOrd + Debug> (v1: &mut Vec<T>, v2: &mut Vec<T>) -
     }
 }
 
+// Lazy version of merge() for iterators that are already sorted. Each call to next() takes one item
+// from whichever side is lower. Unlike merge(), ties go to the first iterator so the merge is stable.
+pub struct MergeIter<T, A, B>
+    where A: Iterator<Item = T>, B: Iterator<Item = T>
+{
+    a: Peekable<A>,
+    b: Peekable<B>,
+}
+
+pub fn merge_iter<T, A, B> (a: A, b: B) -> MergeIter<T, A, B>
+    where T: PartialOrd, A: Iterator<Item = T>, B: Iterator<Item = T>
+{
+    MergeIter {
+        a: a.peekable(),
+        b: b.peekable(),
+    }
+}
+
+impl <T, A, B> Iterator for MergeIter<T, A, B>
+    where T: PartialOrd, A: Iterator<Item = T>, B: Iterator<Item = T>
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let take_second = match (self.a.peek(), self.b.peek()) {
+            (Some(a_next), Some(b_next)) => b_next.partial_cmp(a_next) == Some(Ordering::Less),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        if take_second {
+            self.b.next()
+        } else {
+            self.a.next()
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a_low, a_high) = self.a.size_hint();
+        let (b_low, b_high) = self.b.size_hint();
+        let high = match (a_high, b_high) {
+            (Some(a_high), Some(b_high)) => a_high.checked_add(b_high),
+            _ => None,
+        };
+        (a_low.saturating_add(b_low), high)
+    }
+}
+
 #[inline]
 pub fn merge_in_place<T: Ord + Debug> (s: &mut [T], mid: usize) {
     let s_len = s.len();
@@ -380,30 +476,38 @@ pub fn merge_in_place<T: Ord + Debug> (s: &mut [T], mid: usize) {
     //debug_assert!(s.is_sorted())
 }
 
+// The merge is done by merge_in_place_track_start_on() so that merge_sort_loop() and a traced run of
+// it use the same code.
 #[inline]
 pub fn merge_in_place_track_start<T: Ord + Debug> (s: &mut [T], mid: usize) {
     let s_len = s.len();
-    //debug_assert!(s_len >= 3);
-    //debug_assert!(mid > 0);
-    //debug_assert!(mid < s_len);
-    //debug_assert!(s[..mid].is_sorted());
-    //debug_assert!(s[mid..].is_sorted());
+    let mut s = s;
+    merge_in_place_track_start_on(&mut s, 0, mid, s_len);
+}
+
+// merge_in_place_track_start() over the range from..to of a SortSlice. The binary search finds the
+// first item in the low run that's bigger than the next high item, so equal items keep their order.
+pub fn merge_in_place_track_start_on<S: SortSlice> (s: &mut S, from: usize, mid: usize, to: usize) {
     let mut next_second_index = mid;
-    let mut search_start_index = 0;
-    while next_second_index < s_len {
-        let insertion_index = match s[search_start_index..next_second_index].binary_search(&s[next_second_index]) {
-            Ok(index) => index,
-            Err(index) => index,
-        } + search_start_index;
+    let mut search_start_index = from;
+    while next_second_index < to {
+        let mut low = search_start_index;
+        let mut high = next_second_index;
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if s.less(next_second_index, middle) {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+        let insertion_index = low;
         if insertion_index < next_second_index {
-            s[insertion_index..next_second_index + 1].rotate_right(1);
+            s.rotate_right_one(insertion_index, next_second_index + 1);
         }
         search_start_index = insertion_index + 1;
         next_second_index += 1;
-        //debug_assert!(s[..next_second_index].is_sorted());
-        //debug_assert!(s[next_second_index..].is_sorted());
     }
-    //debug_assert!(s.is_sorted())
 }
 
 /*
@@ -626,6 +730,21 @@ fn try_all_merge_sorts() {
     assert!(&v.is_sorted());
 }
 
+fn try_merge_sort_on() {
+    for size in 0..=100 {
+        let mut v = vec_usize_shuffled(size);
+        merge_sort_on(&mut v[..]);
+        assert!(v.is_sorted());
+    }
+}
+
+fn try_merge_iter() {
+    dbg!(merge_iter(vec![2].into_iter(), vec![1].into_iter()).collect::<Vec<_>>());
+    dbg!(merge_iter(vec![3].into_iter(), vec![1, 2].into_iter()).collect::<Vec<_>>());
+    dbg!(merge_iter(vec![1, 5, 6, 7, 11].into_iter(), vec![2, 3, 4, 8, 9, 10].into_iter()).collect::<Vec<_>>());
+    dbg!(merge_iter(Vec::<usize>::new().into_iter(), vec![1, 2].into_iter()).collect::<Vec<_>>());
+}
+
 fn try_merge() {
     dbg!(merge(&mut vec![2], &mut vec![1]));
     dbg!(merge(&mut vec![3], &mut vec![1, 2]));
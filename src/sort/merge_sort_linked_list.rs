@@ -0,0 +1,75 @@
+#![allow(dead_code)]
+
+use super::test_data::*;
+
+use std::cmp::Ordering;
+use std::collections::LinkedList;
+use std::fmt::Debug;
+use std::mem;
+
+pub fn main() {
+    try_merge_linked_list();
+    try_small_lists();
+    // try_large_list();
+}
+
+// Merge sort that works on the list's own nodes. split_off() and append() only relink nodes, so no
+// values are moved or copied. The split walks half the list, which is the same O(n) per level as the
+// merge itself.
+pub fn merge_sort_linked_list<T: PartialOrd + Debug> (list: &mut LinkedList<T>) {
+    let list_len = list.len();
+    if list_len <= 1 {
+        return;
+    }
+    let mut second = list.split_off(list_len / 2);
+    merge_sort_linked_list(list);
+    merge_sort_linked_list(&mut second);
+    let first = mem::replace(list, LinkedList::new());
+    *list = merge_linked_list(first, second);
+}
+
+// Merges two sorted lists by moving the front node of one list or the other onto the end of the
+// result. Ties go to the first list so the sort is stable.
+pub fn merge_linked_list<T: PartialOrd + Debug> (mut first: LinkedList<T>, mut second: LinkedList<T>) -> LinkedList<T> {
+    let mut merged = LinkedList::new();
+    loop {
+        let take_second = match (first.front(), second.front()) {
+            (Some(first_next), Some(second_next)) => second_next.partial_cmp(first_next) == Some(Ordering::Less),
+            _ => break,
+        };
+        let source = if take_second { &mut second } else { &mut first };
+        // split_off(1) is constant time since it only has to step past the front node.
+        let rest = source.split_off(1);
+        merged.append(source);
+        *source = rest;
+    }
+    // At most one of these still has nodes, and they're all higher than anything in merged.
+    merged.append(&mut first);
+    merged.append(&mut second);
+    merged
+}
+
+fn try_merge_linked_list() {
+    let first: LinkedList<usize> = vec![1, 5, 6, 7, 11].into_iter().collect();
+    let second: LinkedList<usize> = vec![2, 3, 4, 8, 9, 10].into_iter().collect();
+    dbg!(merge_linked_list(first, second));
+}
+
+fn try_small_lists() {
+    for size in 1..=100 {
+        let mut list: LinkedList<usize> = vec_usize_shuffled(size).into_iter().collect();
+        merge_sort_linked_list(&mut list);
+        assert_eq!(size, list.len());
+        assert!(list.iter().is_sorted());
+    }
+}
+
+fn try_large_list() {
+    let size = 1_000_000;
+    let mut list: LinkedList<usize> = vec_usize_shuffled(size).into_iter().collect();
+    util::format::print_elapsed(true, "merge_sort_linked_list", "", || {
+        merge_sort_linked_list(&mut list);
+    });
+    assert_eq!(size, list.len());
+    assert!(list.iter().is_sorted());
+}
@@ -5,19 +5,31 @@ use std::fmt::Debug;
 use crate::sort::test_data;
 use crate::sort::bubble_sort;
 use crate::sort::merge_sort;
+use crate::sort::thread_limit;
+use crate::sort::cancel::{CancelToken, Cancelled};
+use crate::sort::sort_slice::SortSlice;
 use std::cmp::min;
 
 pub fn main() {
     // try_sort_specific_case();
     try_sort_small();
+    // try_sort_in_pool();
+    // try_sort_on();
 }
 
+// The sort itself is merge_sort_loop_on() so that a run traced with vis::traced_slice is this sort.
 pub fn merge_sort_loop<T> (s: &mut [T], min_split_size: u8, max_threads: u8)
     where T: Ord + Send + Debug
+{
+    merge_sort_loop_on(s, min_split_size, max_threads);
+}
+
+pub fn merge_sort_loop_vec<T> (s: &mut Vec<T>, min_split_size: u8, max_threads: u8)
+    where T: Ord + Send + Debug
 {
     let s_len = s.len();
     match s_len {
-        1 => {},
+        1 => {}
         2 => {
             if s[0] > s[1] {
                 s.swap(0, 1);
@@ -25,26 +37,19 @@ pub fn merge_sort_loop<T> (s: &mut [T], min_split_size: u8, max_threads: u8)
             //ebug_assert!(s.is_sorted());
         },
         _ => {
-            // if s_len < min_split_size as usize {
-            //    bubble_sort::bubble_sort(s);
-            //    //ebug_assert!(s.is_sorted());
-            //    return;
-            //}
             if max_threads >= 2 {
                 let mid = s_len / 2;
                 //bg!(&s, &mid);
-                {
-                    let (lo, hi) = s.split_at_mut(mid);
-                    //bg!(&lo, &hi);
-                    //bg!("rayon::join() -> Start");
-                    rayon::join(
-                        || merge_sort_loop(lo, min_split_size, max_threads / 2),
-                        || merge_sort_loop(hi, min_split_size, max_threads / 2)
-                    );
-                    //bg!("rayon::join() -> Done");
-                    //bg!(&lo, &hi);
-                }
-                merge_sort::merge_in_place_track_start(s, mid);
+                let mut hi = s.split_off(mid);
+                //bg!(&lo, &hi);
+                //bg!("rayon::join() -> Start");
+                rayon::join(
+                    || merge_sort_loop(s, min_split_size, max_threads / 2),
+                    || merge_sort_loop(&mut hi, min_split_size, max_threads / 2)
+                );
+                //bg!("rayon::join() -> Done");
+                //bg!(&lo, &hi);
+                *s = merge_sort::merge_from_end(s, &mut hi);
                 //dbg!(&s);
             } else {
                 let mut subslice_len = 2;
@@ -74,36 +79,38 @@ pub fn merge_sort_loop<T> (s: &mut [T], min_split_size: u8, max_threads: u8)
 
 }
 
-pub fn merge_sort_loop_vec<T> (s: &mut Vec<T>, min_split_size: u8, max_threads: u8)
+// Checks the token before each split, before each merge and before each pass of the bottom-up loop.
+// Once it's cancelled the remaining merges are skipped. merge_in_place_track_start() only rotates
+// within the slice so the slice always holds the original values.
+pub fn merge_sort_loop_cancellable<T> (s: &mut [T], min_split_size: u8, max_threads: u8, token: &CancelToken) -> Result<(), Cancelled>
     where T: Ord + Send + Debug
 {
+    token.check()?;
     let s_len = s.len();
     match s_len {
-        1 => {}
+        0 | 1 => {},
         2 => {
             if s[0] > s[1] {
                 s.swap(0, 1);
             }
-            //ebug_assert!(s.is_sorted());
         },
         _ => {
             if max_threads >= 2 {
                 let mid = s_len / 2;
-                //bg!(&s, &mid);
-                let mut hi = s.split_off(mid);
-                //bg!(&lo, &hi);
-                //bg!("rayon::join() -> Start");
-                rayon::join(
-                    || merge_sort_loop(s, min_split_size, max_threads / 2),
-                    || merge_sort_loop(&mut hi, min_split_size, max_threads / 2)
-                );
-                //bg!("rayon::join() -> Done");
-                //bg!(&lo, &hi);
-                *s = merge_sort::merge_from_end(s, &mut hi);
-                //dbg!(&s);
+                {
+                    let (lo, hi) = s.split_at_mut(mid);
+                    let (lo_result, hi_result) = rayon::join(
+                        || merge_sort_loop_cancellable(lo, min_split_size, max_threads / 2, token),
+                        || merge_sort_loop_cancellable(hi, min_split_size, max_threads / 2, token)
+                    );
+                    lo_result.and(hi_result)?;
+                }
+                token.check()?;
+                merge_sort::merge_in_place_track_start(s, mid);
             } else {
                 let mut subslice_len = 2;
                 while subslice_len < s_len {
+                    token.check()?;
                     if subslice_len == 2 {
                         for i in (0..s_len).step_by(2) {
                             if i + 1 < s_len {
@@ -118,15 +125,75 @@ pub fn merge_sort_loop_vec<T> (s: &mut Vec<T>, min_split_size: u8, max_threads:
                         let end_index = min(i + next_subslice_len, s_len);
                         let merge_slice = &mut s[i..end_index];
                         merge_sort::merge_in_place_track_start(merge_slice, subslice_len);
-                        //ebug_assert!(merge_slice.is_sorted());
                     }
                     subslice_len = next_subslice_len;
                 }
             }
-            //ebug_assert!(s.is_sorted());
         }
     }
+    Ok(())
+}
 
+// The same sorts confined to a caller-supplied pool. max_threads still limits how far the work is
+// split while the pool limits how many of those pieces can run at once.
+pub fn merge_sort_loop_in_pool<T> (s: &mut [T], min_split_size: u8, max_threads: u8, pool: &rayon::ThreadPool)
+    where T: Ord + Send + Debug
+{
+    pool.install(|| merge_sort_loop(s, min_split_size, max_threads));
+}
+
+pub fn merge_sort_loop_vec_in_pool<T> (s: &mut Vec<T>, min_split_size: u8, max_threads: u8, pool: &rayon::ThreadPool)
+    where T: Ord + Send + Debug
+{
+    pool.install(|| merge_sort_loop_vec(s, min_split_size, max_threads));
+}
+
+// merge_sort_loop() written against SortSlice so that it can be traced. The halves given to
+// rayon::join() are split off and joined again before the merge, and the slice is returned so that
+// the caller can do the same.
+pub fn merge_sort_loop_on<S> (s: S, min_split_size: u8, max_threads: u8) -> S
+    where S: SortSlice + Send
+{
+    let mut s = s;
+    let s_len = s.len();
+    if s_len < 2 {
+        return s;
+    }
+    if s_len == 2 {
+        if s.less(1, 0) {
+            s.swap(0, 1);
+        }
+        return s;
+    }
+    if max_threads >= 2 {
+        let mid = s_len / 2;
+        let (lo, hi) = s.split_at(mid);
+        let (lo, hi) = rayon::join(
+            || merge_sort_loop_on(lo, min_split_size, max_threads / 2),
+            || merge_sort_loop_on(hi, min_split_size, max_threads / 2)
+        );
+        s = unsafe { S::join(lo, hi) };
+        merge_sort::merge_in_place_track_start_on(&mut s, 0, mid, s_len);
+    } else {
+        let mut subslice_len = 2;
+        while subslice_len < s_len {
+            if subslice_len == 2 {
+                for i in (0..s_len).step_by(2) {
+                    if i + 1 < s_len && s.less(i + 1, i) {
+                        s.swap(i, i + 1);
+                    }
+                }
+            }
+            let next_subslice_len = subslice_len * 2;
+            for i in (0..s_len - 1).step_by(next_subslice_len) {
+                let end_index = min(i + next_subslice_len, s_len);
+                let mid = min(i + subslice_len, end_index);
+                merge_sort::merge_in_place_track_start_on(&mut s, i, mid, end_index);
+            }
+            subslice_len = next_subslice_len;
+        }
+    }
+    s
 }
 
 fn try_sort_specific_case() {
@@ -150,3 +217,27 @@ fn try_sort_small() {
     }
 }
 
+fn try_sort_on() {
+    for size in 0..=100 {
+        let mut v = test_data::vec_usize_shuffled(size);
+        merge_sort_loop_on(&mut v[..], 5, 8);
+        assert!(v.is_sorted());
+        let mut v = test_data::vec_usize_shuffled(size);
+        merge_sort_loop_on(&mut v[..], 5, 1);
+        assert!(v.is_sorted());
+    }
+}
+
+fn try_sort_in_pool() {
+    let min_split_size = 5;
+    let max_threads = 8;
+    let pool = thread_limit::thread_pool(2);
+    for size in 1..=100 {
+        let mut v = test_data::vec_usize_shuffled(size);
+        merge_sort_loop_in_pool(&mut v, min_split_size, max_threads, &pool);
+        assert!(v.is_sorted());
+        let mut v = test_data::vec_usize_shuffled(size);
+        merge_sort_loop_vec_in_pool(&mut v, min_split_size, max_threads, &pool);
+        assert!(v.is_sorted());
+    }
+}
@@ -1,6 +1,8 @@
 pub mod bubble_sort;
 pub use bubble_sort::*;
 
+pub mod cancel;
+
 pub mod insertion_sort;
 pub use insertion_sort::*;
 
@@ -8,6 +10,9 @@ pub mod merge_sort;
 pub use merge_sort::*;
 // pub use merge_sort::{merge, merge_sort, merge_sort_with_bubble, merge_sort_test_only_no_merge};
 
+pub mod merge_sort_linked_list;
+pub use merge_sort_linked_list::*;
+
 pub mod merge_sort_loop;
 
 pub mod quicksort_crossbeam;
@@ -21,23 +26,45 @@ pub mod quicksort_safe;
 
 // pub mod sort_vis;
 
+pub mod sort_slice;
+
+pub mod sorted_iter;
+pub use sorted_iter::*;
+
 pub mod test_data;
 
+pub mod thread_limit;
+
 use std::fmt::{self, Debug};
 
 use crate::*;
 use counter::{Counter, CounterItem};
+use serde::{Serialize, Deserialize};
 
 // pub trait <T: PartialOrd + Debug> Sortable<T>;
 // pub trait Sortable: PartialOrd + Debug {}
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SliceCounterItem {
     pub start_index: usize,
     pub end_index: usize,
     pub method: Option<String>,
 }
 
+impl SliceCounterItem {
+    pub fn method_or_default(&self) -> &str {
+        self.method.as_ref().map_or("call", |x| x.as_str())
+    }
+
+    pub fn label_method(&self) -> String {
+        self.method_or_default().to_string()
+    }
+
+    pub fn label_range(&self) -> String {
+        format!("{} [{}..{})", self.method_or_default(), self.start_index, self.end_index)
+    }
+}
+
 impl Debug for SliceCounterItem {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let method = self.method.as_ref().map_or("".to_string(), |x| format!("; method = {}", x));
@@ -2,9 +2,12 @@ use rayon::prelude::*;
 use std::fmt::Debug;
 use crate::sort::test_data;
 use crate::sort::bubble_sort;
+use crate::sort::thread_limit::ThreadLimit;
+use crate::sort::cancel::{CancelToken, Cancelled};
 
 pub fn main() {
     try_sort_small();
+    // try_sort_limited();
 }
 
 pub fn quicksort_crossbeam_minimal<T>(s: &mut [T])
@@ -44,6 +47,85 @@ pub fn quicksort_crossbeam<T>(s: &mut [T], min_split_size: u8, min_thread_size:
     }
 }
 
+// Same as quicksort_crossbeam() but never has more than max_threads extra threads running. Only the
+// lower half gets a new thread, and only if a permit is available. The upper half always stays in
+// the current thread.
+pub fn quicksort_crossbeam_limited<T>(s: &mut [T], min_split_size: u8, min_thread_size: u32, max_threads: usize)
+    where T: Ord + Send + Debug
+{
+    let thread_limit = ThreadLimit::new(max_threads);
+    quicksort_crossbeam_limited_internal(s, min_split_size, min_thread_size, &thread_limit);
+}
+
+fn quicksort_crossbeam_limited_internal<T>(s: &mut [T], min_split_size: u8, min_thread_size: u32, thread_limit: &ThreadLimit)
+    where T: Ord + Send + Debug
+{
+    let s_len = s.len();
+    if s_len > 1 {
+        if s_len < min_split_size as usize {
+            bubble_sort::bubble_sort(s);
+        } else {
+            let mid = partition(s);
+            let (lo, hi) = s.split_at_mut(mid);
+            let min_thread_size_usize = min_thread_size as usize;
+            if (lo.len() >= min_thread_size_usize || hi.len() >= min_thread_size_usize) && thread_limit.try_acquire() {
+                let _ = crossbeam::scope(|scope| {
+                    scope.spawn(move |_| {
+                        quicksort_crossbeam_limited_internal(lo, min_split_size, min_thread_size, thread_limit);
+                        thread_limit.release();
+                    });
+                    quicksort_crossbeam_limited_internal(hi, min_split_size, min_thread_size, thread_limit);
+                });
+            } else {
+                quicksort_crossbeam_limited_internal(lo, min_split_size, min_thread_size, thread_limit);
+                quicksort_crossbeam_limited_internal(hi, min_split_size, min_thread_size, thread_limit);
+            }
+        }
+    }
+}
+
+// Checks the token before each split and stops splitting once it's cancelled. The slice is left as a
+// permutation of the original values. Threads are capped at max_threads the same way as in
+// quicksort_crossbeam_limited().
+pub fn quicksort_crossbeam_cancellable<T>(s: &mut [T], min_split_size: u8, min_thread_size: u32, max_threads: usize, token: &CancelToken) -> Result<(), Cancelled>
+    where T: Ord + Send + Debug
+{
+    let thread_limit = ThreadLimit::new(max_threads);
+    quicksort_crossbeam_cancellable_internal(s, min_split_size, min_thread_size, &thread_limit, token)
+}
+
+fn quicksort_crossbeam_cancellable_internal<T>(s: &mut [T], min_split_size: u8, min_thread_size: u32, thread_limit: &ThreadLimit, token: &CancelToken) -> Result<(), Cancelled>
+    where T: Ord + Send + Debug
+{
+    token.check()?;
+    let s_len = s.len();
+    if s_len > 1 {
+        if s_len < min_split_size as usize {
+            bubble_sort::bubble_sort(s);
+        } else {
+            let mid = partition(s);
+            let (lo, hi) = s.split_at_mut(mid);
+            let min_thread_size_usize = min_thread_size as usize;
+            if (lo.len() >= min_thread_size_usize || hi.len() >= min_thread_size_usize) && thread_limit.try_acquire() {
+                let (lo_result, hi_result) = crossbeam::scope(|scope| {
+                    let lo_handle = scope.spawn(move |_| {
+                        let result = quicksort_crossbeam_cancellable_internal(lo, min_split_size, min_thread_size, thread_limit, token);
+                        thread_limit.release();
+                        result
+                    });
+                    let hi_result = quicksort_crossbeam_cancellable_internal(hi, min_split_size, min_thread_size, thread_limit, token);
+                    (lo_handle.join().unwrap(), hi_result)
+                }).unwrap();
+                lo_result.and(hi_result)?;
+            } else {
+                quicksort_crossbeam_cancellable_internal(lo, min_split_size, min_thread_size, thread_limit, token)?;
+                quicksort_crossbeam_cancellable_internal(hi, min_split_size, min_thread_size, thread_limit, token)?;
+            }
+        }
+    }
+    Ok(())
+}
+
 #[inline]
 fn partition<T> (s: &mut [T]) -> usize
     where T: Ord + Send + Debug
@@ -74,3 +156,31 @@ fn try_sort_small() {
 
 }
 
+fn try_sort_limited() {
+    let min_split_size = 5;
+    let min_thread_size = 15;
+    let max_threads = 2;
+    for size in 1..= 60{
+        let mut v = test_data::vec_usize_shuffled(size);
+        quicksort_crossbeam_limited(&mut v, min_split_size, min_thread_size, max_threads);
+        assert!(v.is_sorted());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limited_stays_under_cap() {
+        for max_threads in 0..=3 {
+            let thread_limit = ThreadLimit::new(max_threads);
+            let mut v = test_data::vec_usize_shuffled(5_000);
+            quicksort_crossbeam_limited_internal(&mut v, 10, 100, &thread_limit);
+            assert_eq!(test_data::vec_usize_ordered(5_000), v);
+            assert!(thread_limit.peak_live_threads() <= max_threads);
+            assert_eq!(0, thread_limit.live_threads());
+            assert_eq!(max_threads > 0, thread_limit.spawned_threads() > 0);
+        }
+    }
+}
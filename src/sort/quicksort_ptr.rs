@@ -3,7 +3,9 @@
 use crate::*;
 use super::*;
 use super::test_data::*;
-use counter::Counter;
+use counter::{ConcurrentCounter, Counter};
+use super::thread_limit::ThreadLimit;
+use super::cancel::{CancelToken, Cancelled};
 
 use std::cmp;
 use std::cmp::Ordering;
@@ -22,6 +24,7 @@ use std::time::Instant;
 
 pub fn main() {
     try_ptr_parallel();
+    // try_ptr_parallel_limited();
 }
 
 pub fn quicksort_rnd_3_ptr_with_limit<T: PartialOrd + Debug> (s: &mut [T], limit: usize) {
@@ -146,6 +149,50 @@ pub fn quicksort_parallel_ptr<T: 'static + PartialOrd + Send + Debug> (
     fill_counter: bool,
     crossover_point: usize)
     -> Option<Counter<SliceCounterItem>>
+{
+    quicksort_parallel_ptr_with_thread_limit(s, thread_min_fraction, thread_min_size, fill_counter, crossover_point, None, None).0
+}
+
+// Same as quicksort_parallel_ptr() except that no more than max_threads spawned threads are alive at
+// once. When the cap is reached the larger subslice is sorted in the current thread instead.
+pub fn quicksort_parallel_ptr_limited<T: 'static + PartialOrd + Send + Debug> (
+    s: &mut [T],
+    thread_min_fraction: f64,
+    thread_min_size: usize,
+    fill_counter: bool,
+    crossover_point: usize,
+    max_threads: usize)
+    -> Option<Counter<SliceCounterItem>>
+{
+    let thread_limit = Arc::new(ThreadLimit::new(max_threads));
+    quicksort_parallel_ptr_with_thread_limit(s, thread_min_fraction, thread_min_size, fill_counter, crossover_point, Some(thread_limit), None).0
+}
+
+// Same as quicksort_parallel_ptr_limited() except that it checks the token before each split and
+// stops splitting once it's cancelled. Every spawned thread is still joined before this returns so
+// the slice is left as a permutation of the original values.
+pub fn quicksort_parallel_ptr_cancellable<T: 'static + PartialOrd + Send + Debug> (
+    s: &mut [T],
+    thread_min_fraction: f64,
+    thread_min_size: usize,
+    crossover_point: usize,
+    max_threads: usize,
+    token: &CancelToken)
+    -> Result<(), Cancelled>
+{
+    let thread_limit = Arc::new(ThreadLimit::new(max_threads));
+    quicksort_parallel_ptr_with_thread_limit(s, thread_min_fraction, thread_min_size, false, crossover_point, Some(thread_limit), Some(token.clone())).1
+}
+
+fn quicksort_parallel_ptr_with_thread_limit<T: 'static + PartialOrd + Send + Debug> (
+    s: &mut [T],
+    thread_min_fraction: f64,
+    thread_min_size: usize,
+    fill_counter: bool,
+    crossover_point: usize,
+    thread_limit: Option<Arc<ThreadLimit>>,
+    token: Option<CancelToken>)
+    -> (Option<Counter<SliceCounterItem>>, Result<(), Cancelled>)
 {
     // const NEW_THREAD_MIN_FRACTION: f64 = 0.10;
     let len_isize = s.len() as isize;
@@ -159,26 +206,30 @@ pub fn quicksort_parallel_ptr<T: 'static + PartialOrd + Send + Debug> (
         overall_start_index: 0,
     };
     let counter = if fill_counter {
-        Some(Arc::new(Mutex::new(Counter::new())))
+        Some(Arc::new(ConcurrentCounter::new()))
     } else {
         None
     };
     let one_counter = counter.as_ref().map(|x| Arc::clone(&x));
-    quicksort_parallel_ptr_internal(subslice, one_counter, None, false, crossover_point);
-    // counter.map(|arc| arc.into_inner().unwrap().clone())
-    counter.map(|arc| {
-        let m: &Mutex<Counter<SliceCounterItem>> = arc.borrow();
-        m.lock().unwrap().clone()
-    })
+    let result = quicksort_parallel_ptr_internal(subslice, one_counter, None, false, crossover_point, thread_limit, token);
+    // All of the threads have been joined so this is the only remaining reference.
+    (counter.map(|arc| Arc::try_unwrap(arc).ok().unwrap().into_counter()), result)
 }
 
 fn quicksort_parallel_ptr_internal<T: 'static + PartialOrd + Send + Debug> (
     subslice: Subslice<T>,
-    counter: Option<Arc<Mutex<Counter<SliceCounterItem>>>>,
+    counter: Option<Arc<ConcurrentCounter<SliceCounterItem>>>,
     counter_parent_index: Option<usize>,
     is_new_thread: bool,
-    crossover_point: usize)
+    crossover_point: usize,
+    thread_limit: Option<Arc<ThreadLimit>>,
+    token: Option<CancelToken>)
+    -> Result<(), Cancelled>
 {
+    if let Some(token) = token.as_ref() {
+        token.check()?;
+    }
+
     // This is a variation on the pointer implementation that takes a pointer and a length instead
     // of a slice for convenience in sending sections from the original slice to other threads.
     // const CROSSOVER_POINT: isize = 11;
@@ -192,7 +243,7 @@ fn quicksort_parallel_ptr_internal<T: 'static + PartialOrd + Send + Debug> (
     let overall_start_index = subslice.overall_start_index;
 
     let counter_index = counter.as_ref().map(|ct| {
-        ct.lock().unwrap().start(
+        ct.start(
             s_len as usize,
             Some(SliceCounterItem {
                 start_index: overall_start_index,
@@ -208,9 +259,9 @@ fn quicksort_parallel_ptr_internal<T: 'static + PartialOrd + Send + Debug> (
         let s: &mut [T] = unsafe { slice::from_raw_parts_mut(s_ptr, s_len as usize) };
         bubble_sort(s);
         if let Some(ct) = counter {
-            ct.lock().unwrap().end(counter_index.unwrap());
+            ct.end(counter_index.unwrap());
         }
-        return;
+        return Ok(());
     }
 
     if s_len > LIMIT {
@@ -312,31 +363,43 @@ fn quicksort_parallel_ptr_internal<T: 'static + PartialOrd + Send + Debug> (
     let mut handle = None;
     let one_subslice = subslices.remove(0);
     let one_counter = counter.as_ref().map(|x| Arc::clone(&x));
-    if one_subslice.s_len >= thread_min_size {
-        // The largest subslice is long enough to get its own thread.
+    let one_thread_limit = thread_limit.as_ref().map(|x| Arc::clone(&x));
+    let one_token = token.clone();
+    let mut larger_result = Ok(());
+    if one_subslice.s_len >= thread_min_size && thread_limit.as_ref().map_or(true, |x| x.try_acquire()) {
+        // The largest subslice is long enough to get its own thread and we're under the thread cap,
+        // if there is one.
         //bg!("Starting a thread for {?:}", &one_subslice);
         handle = Some(thread::spawn(move || {
-            quicksort_parallel_ptr_internal(one_subslice, one_counter,counter_index, true, crossover_point);
+            let result = quicksort_parallel_ptr_internal(one_subslice, one_counter,counter_index, true, crossover_point, one_thread_limit.as_ref().map(|x| Arc::clone(&x)), one_token);
+            if let Some(one_thread_limit) = one_thread_limit {
+                one_thread_limit.release();
+            }
+            result
         }));
     } else {
-        quicksort_parallel_ptr_internal(one_subslice, one_counter, counter_index, false, crossover_point);
+        larger_result = quicksort_parallel_ptr_internal(one_subslice, one_counter, counter_index, false, crossover_point, one_thread_limit, one_token);
     }
 
-    // Sort the smaller of the subslices.
+    // Sort the smaller of the subslices. If the sort was cancelled this still has to wait for the
+    // thread below since that thread is working on part of the slice.
     let one_subslice = subslices.remove(0);
+    let mut smaller_result = Ok(());
     if one_subslice.s_len > 0 {
         let one_counter = counter.as_ref().map(|x| Arc::clone(&x));
-        quicksort_parallel_ptr_internal(one_subslice, one_counter, counter_index, false, crossover_point);
+        let one_thread_limit = thread_limit.as_ref().map(|x| Arc::clone(&x));
+        smaller_result = quicksort_parallel_ptr_internal(one_subslice, one_counter, counter_index, false, crossover_point, one_thread_limit, token);
     }
 
     if let Some(handle) = handle {
-        handle.join().unwrap();
+        larger_result = handle.join().unwrap();
     }
 
     if let Some(ct) = counter {
-        ct.lock().unwrap().end(counter_index.unwrap());
+        ct.end(counter_index.unwrap());
     }
 
+    larger_result.and(smaller_result)
 }
 
 fn try_ptr_parallel() {
@@ -359,3 +422,17 @@ fn try_ptr_parallel() {
     // assert!(&v.is_sorted());
 
 }
+
+fn try_ptr_parallel_limited() {
+    let size = 100_000;
+    let thread_min_fraction = 0.0;
+    let thread_min_size = 1_000;
+    let max_threads = 4;
+    let mut v = vec_usize_shuffled(size);
+    let start = Instant::now();
+    let counter = quicksort_parallel_ptr_limited(&mut v[..], thread_min_fraction, thread_min_size, true, 7, max_threads);
+    dbg!(start.elapsed());
+    assert!(&v.is_sorted());
+    let new_thread_count = counter.unwrap().items.iter().filter(|item| item.is_new_thread).count();
+    dbg!(new_thread_count);
+}
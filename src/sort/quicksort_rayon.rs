@@ -2,9 +2,18 @@ use rayon::prelude::*;
 use std::fmt::Debug;
 use crate::sort::test_data;
 use crate::sort::bubble_sort;
+use crate::sort::thread_limit;
+use crate::sort::cancel::{CancelToken, Cancelled};
+use crate::sort::SliceCounterItem;
+use crate::sort::sort_slice::SortSlice;
+use crate::counter::{ConcurrentCounter, Counter};
+use crate::range::CoverageTracker;
 
 pub fn main() {
     try_sort_small();
+    // try_sort_in_pool();
+    // try_sort_counted();
+    // try_sort_on();
 }
 
 /*
@@ -16,12 +25,13 @@ pub fn quicksort_safe_parallel<T>(
     ordering: atomic::Ordering)
 */
 
-pub fn quicksort_rayon_minimal<T>(s: &mut [T])
+pub fn quicksort_rayon_minimal<T>(mut s: &mut [T])
     where T: Ord + Send + Debug
 {
     if s.len() > 1 {
-        let mid = partition(s);
+        let mid = partition(&mut s);
         let (lo, hi) = s.split_at_mut(mid);
+        let hi = &mut hi[1..];
         rayon::join(
             || quicksort_rayon_minimal(lo),
             || quicksort_rayon_minimal(hi)
@@ -29,44 +39,223 @@ pub fn quicksort_rayon_minimal<T>(s: &mut [T])
     }
 }
 
+// The sort itself is quicksort_rayon_on() so that a run traced with vis::traced_slice is this sort.
 pub fn quicksort_rayon<T>(s: &mut [T], min_split_size: u8, min_thread_size: u16)
     where T: Ord + Send + Debug
 {
+    quicksort_rayon_on(s, min_split_size, min_thread_size);
+}
+
+// Runs quicksort_rayon() on the given pool rather than the global one. Nested rayon::join() calls
+// stay on whichever pool they were started from, so the whole sort is confined to the pool's threads.
+pub fn quicksort_rayon_in_pool<T>(s: &mut [T], min_split_size: u8, min_thread_size: u16, pool: &rayon::ThreadPool)
+    where T: Ord + Send + Debug
+{
+    pool.install(|| quicksort_rayon(s, min_split_size, min_thread_size));
+}
+
+// Checks the token before each split. Once it's cancelled no more splits are started and the call
+// returns Err as soon as the pieces already running come back. Every step is a swap within the slice
+// so it's left holding the same values, partly sorted.
+pub fn quicksort_rayon_cancellable<T>(mut s: &mut [T], min_split_size: u8, min_thread_size: u16, token: &CancelToken) -> Result<(), Cancelled>
+    where T: Ord + Send + Debug
+{
+    token.check()?;
     let s_len = s.len();
     if s_len > 1 {
         if s_len < min_split_size as usize {
             bubble_sort::bubble_sort(s);
         } else {
-            let mid = partition(s);
+            let mid = partition(&mut s);
+            let (lo, hi) = s.split_at_mut(mid);
+            let hi = &mut hi[1..];
+            let min_thread_size_usize = min_thread_size as usize;
+            if lo.len() < min_thread_size_usize && hi.len() < min_thread_size_usize {
+                quicksort_rayon_cancellable(lo, min_split_size, min_thread_size, token)?;
+                quicksort_rayon_cancellable(hi, min_split_size, min_thread_size, token)?;
+            } else {
+                let (lo_result, hi_result) = rayon::join(
+                    || quicksort_rayon_cancellable(lo, min_split_size, min_thread_size, token),
+                    || quicksort_rayon_cancellable(hi, min_split_size, min_thread_size, token)
+                );
+                lo_result.and(hi_result)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// quicksort_rayon() with every call recorded in a Counter. The pieces may run on any worker so they
+// share a ConcurrentCounter rather than locking a Counter. A call counts as a new thread when it runs
+// on a different rayon worker than its parent, which shows where work was actually stolen.
+pub fn quicksort_rayon_counted<T>(s: &mut [T], min_split_size: u8, min_thread_size: u16) -> Counter<SliceCounterItem>
+    where T: Ord + Send + Debug
+{
+    let counter = ConcurrentCounter::new();
+    quicksort_rayon_counted_internal(s, 0, min_split_size, min_thread_size, &counter, None, rayon::current_thread_index());
+    counter.into_counter()
+}
+
+fn quicksort_rayon_counted_internal<T>(
+    mut s: &mut [T],
+    overall_start_index: usize,
+    min_split_size: u8,
+    min_thread_size: u16,
+    counter: &ConcurrentCounter<SliceCounterItem>,
+    counter_parent_index: Option<usize>,
+    parent_thread_index: Option<usize>)
+    where T: Ord + Send + Debug
+{
+    let s_len = s.len();
+    let thread_index = rayon::current_thread_index();
+    let is_bubble_sort = s_len < min_split_size as usize;
+    let counter_index = counter.start(
+        s_len,
+        Some(SliceCounterItem {
+            start_index: overall_start_index,
+            end_index: overall_start_index + s_len,
+            method: Some(if is_bubble_sort { "bubble sort".to_string() } else { "quicksort".to_string() }),
+        }),
+        thread_index != parent_thread_index,
+        counter_parent_index);
+    if s_len > 1 {
+        if is_bubble_sort {
+            bubble_sort::bubble_sort(s);
+        } else {
+            let mid = partition(&mut s);
             let (lo, hi) = s.split_at_mut(mid);
+            let hi = &mut hi[1..];
+            let hi_start_index = overall_start_index + mid + 1;
             let min_thread_size_usize = min_thread_size as usize;
             if lo.len() < min_thread_size_usize && hi.len() < min_thread_size_usize {
-                quicksort_rayon(lo, min_split_size, min_thread_size);
-                quicksort_rayon(hi, min_split_size, min_thread_size);
+                quicksort_rayon_counted_internal(lo, overall_start_index, min_split_size, min_thread_size, counter, Some(counter_index), thread_index);
+                quicksort_rayon_counted_internal(hi, hi_start_index, min_split_size, min_thread_size, counter, Some(counter_index), thread_index);
             } else {
                 rayon::join(
-                    || quicksort_rayon(lo, min_split_size, min_thread_size),
-                    || quicksort_rayon(hi, min_split_size, min_thread_size)
+                    || quicksort_rayon_counted_internal(lo, overall_start_index, min_split_size, min_thread_size, counter, Some(counter_index), thread_index),
+                    || quicksort_rayon_counted_internal(hi, hi_start_index, min_split_size, min_thread_size, counter, Some(counter_index), thread_index)
                 );
             }
         }
     }
+    counter.end(counter_index);
 }
 
-#[inline]
-fn partition<T> (s: &mut [T]) -> usize
+// quicksort_rayon() reporting into a CoverageTracker as it goes. Each piece that's bubble sorted or
+// down to one item is finished for good, as is each pivot once it's in place, and each call checks
+// that its whole range is finished once both halves return. Keys follow RangeCall: the first call is "0" and its halves add "0" and "1".
+pub fn quicksort_rayon_coverage<T>(s: &mut [T], min_split_size: u8, min_thread_size: u16, tracker: &CoverageTracker)
     where T: Ord + Send + Debug
 {
-    let pivot = if s.len() <= 3 {
-        1
+    quicksort_rayon_coverage_internal(s, 0, min_split_size, min_thread_size, tracker, "0");
+}
+
+fn quicksort_rayon_coverage_internal<T>(mut s: &mut [T], from: usize, min_split_size: u8, min_thread_size: u16, tracker: &CoverageTracker, call_key: &str)
+    where T: Ord + Send + Debug
+{
+    let s_len = s.len();
+    let range = from..from + s_len;
+    if s_len > 1 && s_len >= min_split_size as usize {
+        let mid = partition(&mut s);
+        let _ = tracker.finish_range(from + mid..from + mid + 1, call_key);
+        let (lo, hi) = s.split_at_mut(mid);
+        let hi = &mut hi[1..];
+        let hi_from = from + mid + 1;
+        let lo_key = &format!("{}0", call_key);
+        let hi_key = &format!("{}1", call_key);
+        let min_thread_size_usize = min_thread_size as usize;
+        if lo.len() < min_thread_size_usize && hi.len() < min_thread_size_usize {
+            quicksort_rayon_coverage_internal(lo, from, min_split_size, min_thread_size, tracker, lo_key);
+            quicksort_rayon_coverage_internal(hi, hi_from, min_split_size, min_thread_size, tracker, hi_key);
+        } else {
+            rayon::join(
+                || quicksort_rayon_coverage_internal(lo, from, min_split_size, min_thread_size, tracker, lo_key),
+                || quicksort_rayon_coverage_internal(hi, hi_from, min_split_size, min_thread_size, tracker, hi_key)
+            );
+        }
+        // With panic_on_error set any problem has already panicked, and otherwise it's been recorded.
+        let _ = tracker.finish_call(range, call_key);
     } else {
-        // Median of the first, middle, and last elements.
-        let mut pivots = [0, s.len() / 2, s.len() - 1];
-        pivots.sort_unstable_by_key(|i| &s[*i]);
-        pivots[1]
-    };
-    s.partition_at_index(pivot);
-    pivot
+        if s_len > 1 {
+            bubble_sort::bubble_sort(s);
+        }
+        let _ = tracker.finish_range(range, call_key);
+    }
+}
+
+// quicksort_rayon() written against SortSlice so that the same code can sort a plain slice or be
+// traced with vis::traced_slice::TracedSlice. The partition only swaps, since partition_at_index()
+// only exists for plain slices, and the pivot is split off on its own.
+pub fn quicksort_rayon_on<S>(s: S, min_split_size: u8, min_thread_size: u16)
+    where S: SortSlice + Send
+{
+    let mut s = s;
+    let s_len = s.len();
+    if s_len > 1 {
+        if s_len < min_split_size as usize {
+            bubble_sort::bubble_sort_on(&mut s);
+        } else {
+            let mid = partition(&mut s);
+            let (lo, hi) = s.split_at(mid);
+            let (_pivot, hi) = hi.split_at(1);
+            let min_thread_size_usize = min_thread_size as usize;
+            if lo.len() < min_thread_size_usize && hi.len() < min_thread_size_usize {
+                quicksort_rayon_on(lo, min_split_size, min_thread_size);
+                quicksort_rayon_on(hi, min_split_size, min_thread_size);
+            } else {
+                rayon::join(
+                    || quicksort_rayon_on(lo, min_split_size, min_thread_size),
+                    || quicksort_rayon_on(hi, min_split_size, min_thread_size)
+                );
+            }
+        }
+    }
+}
+
+// Partitions around the median of three, used by every sort in this file. Returns the pivot's final
+// index. Assumes at least two items. Both scans stop at items equal to the pivot and swap them, so
+// runs of equal items are spread over both sides and still split near the middle rather than all
+// going to one side, which would make the sort quadratic with one level of recursion per item.
+fn partition<S: SortSlice>(s: &mut S) -> usize {
+    let last = s.len() - 1;
+    let middle = s.len() / 2;
+    // Order the first, middle and last items so the median of the three is in the middle, then use
+    // it as the pivot by moving it to the end.
+    if s.less(middle, 0) {
+        s.swap(middle, 0);
+    }
+    if middle != last {
+        if s.less(last, middle) {
+            s.swap(last, middle);
+            if s.less(middle, 0) {
+                s.swap(middle, 0);
+            }
+        }
+        s.swap(middle, last);
+    }
+    let mut i = 0;
+    let mut j = last;
+    loop {
+        // Stops at the pivot itself at the latest.
+        while s.less(i, last) {
+            i += 1;
+        }
+        loop {
+            j -= 1;
+            if j == 0 || !s.less(last, j) {
+                break;
+            }
+        }
+        if i >= j {
+            break;
+        }
+        s.swap(i, j);
+        i += 1;
+    }
+    if i != last {
+        s.swap(i, last);
+    }
+    i
 }
 
 fn try_sort_small() {
@@ -83,3 +272,69 @@ fn try_sort_small() {
 
 }
 
+fn try_sort_in_pool() {
+    let min_split_size = 10;
+    let min_thread_size = 20;
+    let pool = thread_limit::thread_pool(2);
+    for size in 1..= 60{
+        let mut v = test_data::vec_usize_shuffled(size);
+        quicksort_rayon_in_pool(&mut v, min_split_size, min_thread_size, &pool);
+        assert!(v.is_sorted());
+    }
+}
+
+fn try_sort_counted() {
+    let min_split_size = 10;
+    let min_thread_size = 1_000;
+    let mut v = test_data::vec_usize_shuffled(100_000);
+    let counter = quicksort_rayon_counted(&mut v, min_split_size, min_thread_size);
+    assert!(v.is_sorted());
+    counter.dbg(Some(4));
+}
+
+fn try_sort_on() {
+    for size in 0..=200 {
+        let mut v = test_data::vec_usize_shuffled(size);
+        quicksort_rayon_on(&mut v[..], 10, 50);
+        assert!(v.is_sorted());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // With all the items equal to the pivot going to one side these would take about n * n / 2
+    // compares and recurse once per item.
+    #[test]
+    fn test_duplicates() {
+        let size = 100_000;
+        let inputs = vec![vec![7; size], (0..size).map(|i| i % 10).collect::<Vec<_>>(), (0..size).map(|i| (size - i) % 3).collect::<Vec<_>>()];
+        for input in inputs.iter() {
+            let mut expected = input.clone();
+            expected.sort();
+
+            let mut v = input.clone();
+            quicksort_rayon_minimal(&mut v);
+            assert_eq!(expected, v);
+
+            let mut v = input.clone();
+            quicksort_rayon(&mut v, 10, 1_000);
+            assert_eq!(expected, v);
+
+            let mut v = input.clone();
+            assert_eq!(Ok(()), quicksort_rayon_cancellable(&mut v, 10, 1_000, &CancelToken::new()));
+            assert_eq!(expected, v);
+
+            let mut v = input.clone();
+            quicksort_rayon_counted(&mut v, 10, 1_000);
+            assert_eq!(expected, v);
+
+            let mut v = input.clone();
+            let tracker = CoverageTracker::new(0..size, true);
+            quicksort_rayon_coverage(&mut v, 10, 1_000, &tracker);
+            assert_eq!(expected, v);
+            assert!(tracker.finish().is_ok());
+        }
+    }
+}
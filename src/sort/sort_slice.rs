@@ -0,0 +1,121 @@
+#![allow(dead_code)]
+
+use std::mem;
+use std::slice;
+
+// The item operations a sort needs, for sorts that are written once and run either on a plain slice
+// or on something that watches each operation such as vis::traced_slice::TracedSlice. Indexes are
+// relative to the start of the slice. For a plain slice every function is a direct slice operation
+// so the generic sorts compile down to the same code as ones written against &mut [T].
+//
+// split_at() takes the slice by value like <[T]>::split_at_mut() so that the two halves can be sent
+// to different threads. Sorts such as merge sort that need the whole range back after sorting the
+// halves put them together again with join().
+pub trait SortSlice: Sized {
+    type Item;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn read(&self, index: usize) -> &Self::Item;
+
+    // True if the item at a sorts before the item at b.
+    fn less(&self, a: usize, b: usize) -> bool;
+
+    fn swap(&mut self, a: usize, b: usize);
+
+    // Puts the value at the index and returns the value that was there.
+    fn write(&mut self, index: usize, value: Self::Item) -> Self::Item;
+
+    // True if the item at index sorts before the one in the buffer slot buffer_index, the buffer being
+    // one filled by copy_to_buffer().
+    fn less_than_buffer(&self, index: usize, buffer: &[Self::Item], buffer_index: usize) -> bool;
+
+    // Moves the last item of from..to to from and shifts the rest up by one, like
+    // <[T]>::rotate_right(1) on that range.
+    fn rotate_right_one(&mut self, from: usize, to: usize);
+
+    // Appends a copy of the item at index to the buffer. The buffer is expected to line up with the
+    // slice, so its first item is the copy of index 0.
+    fn copy_to_buffer(&self, index: usize, buffer: &mut Vec<Self::Item>)
+        where Self::Item: Clone;
+
+    fn copy_from_buffer(&mut self, index: usize, buffer: &[Self::Item], buffer_index: usize)
+        where Self::Item: Clone;
+
+    fn split_at(self, mid: usize) -> (Self, Self);
+
+    // The reverse of split_at().
+    //
+    // Unsafe because lo and hi must be the two halves returned by one call to split_at(). For a plain
+    // slice this is checked only as far as the two halves being next to each other in memory.
+    unsafe fn join(lo: Self, hi: Self) -> Self;
+}
+
+impl <'a, T: PartialOrd> SortSlice for &'a mut [T] {
+    type Item = T;
+
+    #[inline]
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    #[inline]
+    fn read(&self, index: usize) -> &T {
+        &self[index]
+    }
+
+    #[inline]
+    fn less(&self, a: usize, b: usize) -> bool {
+        self[a] < self[b]
+    }
+
+    #[inline]
+    fn swap(&mut self, a: usize, b: usize) {
+        <[T]>::swap(self, a, b);
+    }
+
+    #[inline]
+    fn write(&mut self, index: usize, value: T) -> T {
+        mem::replace(&mut self[index], value)
+    }
+
+    #[inline]
+    fn less_than_buffer(&self, index: usize, buffer: &[T], buffer_index: usize) -> bool {
+        self[index] < buffer[buffer_index]
+    }
+
+    #[inline]
+    fn rotate_right_one(&mut self, from: usize, to: usize) {
+        self[from..to].rotate_right(1);
+    }
+
+    #[inline]
+    fn copy_to_buffer(&self, index: usize, buffer: &mut Vec<T>)
+        where T: Clone
+    {
+        buffer.push(self[index].clone());
+    }
+
+    #[inline]
+    fn copy_from_buffer(&mut self, index: usize, buffer: &[T], buffer_index: usize)
+        where T: Clone
+    {
+        self[index] = buffer[buffer_index].clone();
+    }
+
+    #[inline]
+    fn split_at(self, mid: usize) -> (Self, Self) {
+        self.split_at_mut(mid)
+    }
+
+    #[inline]
+    unsafe fn join(lo: Self, hi: Self) -> Self {
+        let lo_len = <[T]>::len(lo);
+        assert_eq!(lo.as_mut_ptr().add(lo_len), hi.as_mut_ptr(), "join() was given slices that aren't next to each other.");
+        slice::from_raw_parts_mut(lo.as_mut_ptr(), lo_len + <[T]>::len(hi))
+    }
+}
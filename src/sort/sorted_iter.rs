@@ -0,0 +1,126 @@
+#![allow(dead_code)]
+
+use super::merge_sort;
+use super::test_data::*;
+
+use std::fmt::Debug;
+use std::iter;
+
+pub fn main() {
+    try_sorted_iter();
+}
+
+type Run<T> = Box<dyn Iterator<Item = T>>;
+
+// An iterator adaptor that sorts the source iterator the way an external merge sort does, but with
+// the runs kept in memory. The source is read chunk_size items at a time and each chunk is sorted on
+// its own with merge_sort_merge_from_end(), so no more than chunk_size items are ever sorted in one
+// buffer. The sorted chunks are then merged lazily through a balanced tree of merge_sort::MergeIter,
+// one output item per call to next().
+//
+// This isn't a streaming sort and its memory isn't bounded. The smallest item could be the last one
+// in the source, so the first call to next() reads the whole source into runs before it yields
+// anything, all of the items are held until they've been yielded, and it never returns on a source
+// that doesn't end. What chunk_size bounds is the buffer that each sort works in.
+pub struct ChunkMergeIter<I>
+    where I: Iterator, I::Item: PartialOrd + Debug + 'static
+{
+    source: Option<I>,
+    chunk_size: usize,
+    run_count: usize,
+    merged: Option<Run<I::Item>>,
+}
+
+pub fn chunk_merge_iter<I> (source: I, chunk_size: usize) -> ChunkMergeIter<I>
+    where I: Iterator, I::Item: PartialOrd + Debug + 'static
+{
+    assert!(chunk_size > 0);
+    ChunkMergeIter {
+        source: Some(source),
+        chunk_size,
+        run_count: 0,
+        merged: None,
+    }
+}
+
+pub trait SortedInChunks: Iterator + Sized
+    where Self::Item: PartialOrd + Debug + 'static
+{
+    fn sorted_in_chunks(self, chunk_size: usize) -> ChunkMergeIter<Self> {
+        chunk_merge_iter(self, chunk_size)
+    }
+}
+
+impl <I> SortedInChunks for I
+    where I: Iterator, I::Item: PartialOrd + Debug + 'static
+{
+}
+
+impl <I> ChunkMergeIter<I>
+    where I: Iterator, I::Item: PartialOrd + Debug + 'static
+{
+    // The number of sorted chunks read from the source. This is zero until the first call to next().
+    pub fn run_count(&self) -> usize {
+        self.run_count
+    }
+
+    fn build_merged(&mut self) -> Run<I::Item> {
+        let mut source = self.source.take().unwrap();
+        let mut runs: Vec<Run<I::Item>> = vec![];
+        loop {
+            let mut chunk: Vec<I::Item> = source.by_ref().take(self.chunk_size).collect();
+            if chunk.is_empty() {
+                break;
+            }
+            merge_sort::merge_sort_merge_from_end(&mut chunk);
+            runs.push(Box::new(chunk.into_iter()));
+        }
+        self.run_count = runs.len();
+        merge_runs(runs)
+    }
+}
+
+impl <I> Iterator for ChunkMergeIter<I>
+    where I: Iterator, I::Item: PartialOrd + Debug + 'static
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.merged.is_none() {
+            self.merged = Some(self.build_merged());
+        }
+        self.merged.as_mut().unwrap().next()
+    }
+}
+
+// Pairs up the runs level by level so each item passes through about log2(run count) merges.
+fn merge_runs<T> (mut runs: Vec<Run<T>>) -> Run<T>
+    where T: PartialOrd + Debug + 'static
+{
+    while runs.len() > 1 {
+        let mut next_runs: Vec<Run<T>> = Vec::with_capacity((runs.len() + 1) / 2);
+        let mut runs_iter = runs.into_iter();
+        loop {
+            match (runs_iter.next(), runs_iter.next()) {
+                (Some(a), Some(b)) => next_runs.push(Box::new(merge_sort::merge_iter(a, b))),
+                (Some(a), None) => next_runs.push(a),
+                _ => break,
+            }
+        }
+        runs = next_runs;
+    }
+    runs.pop().unwrap_or_else(|| Box::new(iter::empty()))
+}
+
+fn try_sorted_iter() {
+    for size in 1..=100 {
+        for chunk_size in [1, 3, 10, 100].iter() {
+            let v = vec_usize_shuffled(size);
+            let sorted: Vec<usize> = v.into_iter().sorted_in_chunks(*chunk_size).collect();
+            assert_eq!(size, sorted.len());
+            assert!(sorted.is_sorted());
+        }
+    }
+    let mut iter = vec_usize_shuffled(25).into_iter().sorted_in_chunks(10);
+    dbg!(iter.next(), iter.run_count());
+}
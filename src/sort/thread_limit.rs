@@ -0,0 +1,146 @@
+#![allow(dead_code)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub fn main() {
+    try_thread_limit();
+}
+
+// A cap on the number of extra threads that the crossbeam and raw-thread sorts may have running at
+// once. A sort asks for a permit before spawning. If none is available it does the work in the
+// current thread instead, so the cap is never exceeded and the sort never blocks waiting for one.
+#[derive(Debug)]
+pub struct ThreadLimit {
+    max_threads: usize,
+    live_threads: AtomicUsize,
+    peak_live_threads: AtomicUsize,
+    spawned_threads: AtomicUsize,
+}
+
+impl ThreadLimit {
+    pub fn new(max_threads: usize) -> Self {
+        Self {
+            max_threads,
+            live_threads: AtomicUsize::new(0),
+            peak_live_threads: AtomicUsize::new(0),
+            spawned_threads: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(usize::max_value())
+    }
+
+    pub fn try_acquire(&self) -> bool {
+        let mut live = self.live_threads.load(Ordering::Acquire);
+        loop {
+            if live >= self.max_threads {
+                return false;
+            }
+            match self.live_threads.compare_exchange_weak(live, live + 1, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => {
+                    self.spawned_threads.fetch_add(1, Ordering::Relaxed);
+                    self.peak_live_threads.fetch_max(live + 1, Ordering::Relaxed);
+                    return true;
+                },
+                Err(actual) => live = actual,
+            }
+        }
+    }
+
+    pub fn release(&self) {
+        let prev = self.live_threads.fetch_sub(1, Ordering::AcqRel);
+        debug_assert!(prev > 0, "ThreadLimit::release() called without a matching try_acquire().");
+    }
+
+    pub fn max_threads(&self) -> usize {
+        self.max_threads
+    }
+
+    pub fn live_threads(&self) -> usize {
+        self.live_threads.load(Ordering::Acquire)
+    }
+
+    pub fn peak_live_threads(&self) -> usize {
+        self.peak_live_threads.load(Ordering::Relaxed)
+    }
+
+    pub fn spawned_threads(&self) -> usize {
+        self.spawned_threads.load(Ordering::Relaxed)
+    }
+}
+
+// Builds a rayon pool for the *_in_pool() variants of the rayon sorts so that they don't compete
+// with other work on the global pool.
+pub fn thread_pool(num_threads: usize) -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .thread_name(|index| format!("sort-{}", index))
+        .build()
+        .unwrap()
+}
+
+fn try_thread_limit() {
+    let limit = ThreadLimit::new(2);
+    assert!(limit.try_acquire());
+    assert!(limit.try_acquire());
+    assert!(!limit.try_acquire());
+    limit.release();
+    assert!(limit.try_acquire());
+    dbg!(&limit);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sort::{merge_sort_loop, quicksort_crossbeam, quicksort_ptr, quicksort_rayon};
+    use crate::sort::test_data::{vec_usize_ordered, vec_usize_shuffled};
+
+    #[test]
+    fn test_never_exceeds_max_threads() {
+        let limit = ThreadLimit::new(3);
+        crossbeam::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|_| {
+                    for _ in 0..1_000 {
+                        if limit.try_acquire() {
+                            assert!(limit.live_threads() <= limit.max_threads());
+                            limit.release();
+                        }
+                    }
+                });
+            }
+        }).unwrap();
+        assert!(limit.peak_live_threads() <= 3);
+        assert!(limit.spawned_threads() > 0);
+        assert_eq!(0, limit.live_threads());
+    }
+
+    #[test]
+    fn test_limited_and_pool_sorts() {
+        let pool = thread_pool(2);
+        for size in [1, 2, 50, 3_000].iter().cloned() {
+            let expected = vec_usize_ordered(size);
+
+            let mut v = vec_usize_shuffled(size);
+            quicksort_crossbeam::quicksort_crossbeam_limited(&mut v, 10, 100, 2);
+            assert_eq!(expected, v);
+
+            let mut v = vec_usize_shuffled(size);
+            quicksort_ptr::quicksort_parallel_ptr_limited(&mut v, 0.1, 100, false, 10, 2);
+            assert_eq!(expected, v);
+
+            let mut v = vec_usize_shuffled(size);
+            quicksort_rayon::quicksort_rayon_in_pool(&mut v, 10, 100, &pool);
+            assert_eq!(expected, v);
+
+            let mut v = vec_usize_shuffled(size);
+            merge_sort_loop::merge_sort_loop_in_pool(&mut v, 10, 8, &pool);
+            assert_eq!(expected, v);
+
+            let mut v = vec_usize_shuffled(size);
+            merge_sort_loop::merge_sort_loop_vec_in_pool(&mut v, 10, 8, &pool);
+            assert_eq!(expected, v);
+        }
+    }
+}
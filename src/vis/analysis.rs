@@ -0,0 +1,386 @@
+#![allow(dead_code)]
+
+use super::model::*;
+use super::persist::SavedRangeCall;
+use super::quicksort;
+use crate::sort::test_data;
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub fn main() {
+    try_compare_settings();
+}
+
+// Numbers for judging min_split_size and max_threads rather than eyeballing an animation.
+//
+// The per-thread figures come from the ActionList so that the thread numbers match the other views.
+// A thread is busy while it holds at least one range, from a Take to the matching Release, and it's
+// live from its first Take to its last Release. vis::quicksort starts a new thread for each split so
+// these are threads as the sort created them, not the OS threads of a pool.
+//
+// The rest comes from the RangeCall tree. A call's self time is its own span less the time covered by
+// its child calls. The work is the sum of the self times, or roughly what the sort would take on one
+// thread. Child calls on a subrange are independent of each other while every other child call, such
+// as a partition over the whole range, has to finish before them, so the span (the critical path)
+// of a call is its self time, plus the spans of its whole-range child calls, plus the longest span of
+// its subrange child calls.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThreadUsage {
+    pub thread_number: u8,
+    pub start: Duration,
+    pub end: Duration,
+    pub busy: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CriticalPathCall {
+    pub key: String,
+    pub actor: Actor,
+    pub from: usize,
+    pub to: usize,
+    pub thread_number: u8,
+    pub self_time: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct RunAnalysis {
+    pub label: Option<String>,
+    pub wall_time: Duration,
+    pub threads: Vec<ThreadUsage>,
+    // The number of live threads from each time until the next entry.
+    pub live_threads: Vec<(Duration, usize)>,
+    pub work: Duration,
+    pub span: Duration,
+    // The calls on the critical path in the order they ran.
+    pub critical_path: Vec<CriticalPathCall>,
+}
+
+impl ThreadUsage {
+
+    pub fn lifetime(&self) -> Duration {
+        self.end - self.start
+    }
+
+    pub fn idle(&self) -> Duration {
+        self.lifetime() - self.busy
+    }
+
+    pub fn utilization(&self) -> f64 {
+        ratio(self.busy, self.lifetime())
+    }
+}
+
+impl ActionList {
+
+    pub fn thread_usage(&self) -> Vec<ThreadUsage> {
+        let mut actions: Vec<&Action> = self.actions.iter()
+            .filter(|action| match action.action_type {
+                ActionType::Take | ActionType::Release => true,
+                _ => false,
+            })
+            .collect();
+        actions.sort_by_key(|action| action.duration);
+
+        // For each thread, the number of ranges held and when it last went from none to one.
+        let mut held: BTreeMap<u8, (usize, Duration)> = BTreeMap::new();
+        let mut usage: BTreeMap<u8, ThreadUsage> = BTreeMap::new();
+        for action in actions {
+            let thread_usage = usage.entry(action.thread_number).or_insert_with(|| ThreadUsage {
+                thread_number: action.thread_number,
+                start: action.duration,
+                end: action.duration,
+                busy: Duration::from_nanos(0),
+            });
+            thread_usage.end = action.duration;
+            let (count, busy_since) = held.entry(action.thread_number).or_insert((0, action.duration));
+            match action.action_type {
+                ActionType::Take => {
+                    if *count == 0 {
+                        *busy_since = action.duration;
+                    }
+                    *count += 1;
+                },
+                _ => {
+                    if *count == 1 {
+                        thread_usage.busy += action.duration - *busy_since;
+                    }
+                    *count = count.saturating_sub(1);
+                },
+            }
+        }
+        usage.into_iter().map(|(_, thread_usage)| thread_usage).collect()
+    }
+
+    pub fn live_threads(&self) -> Vec<(Duration, usize)> {
+        live_threads(&self.thread_usage())
+    }
+}
+
+impl RunAnalysis {
+
+    pub fn from_range_call(range_call: &RangeCall, label: Option<&str>) -> Self {
+        let threads = ActionList::from_range_call(range_call, label).thread_usage();
+        let live_threads = live_threads(&threads);
+        let saved = range_call.to_saved(range_call.start_instant());
+        let thread_numbers = thread_numbers(&saved);
+        let (span, critical_path) = critical_path(&saved, &thread_numbers, 0);
+        RunAnalysis {
+            label: label.map(|label| label.to_string()),
+            wall_time: Duration::from_nanos(end_nanos(&saved) - saved.start_nanos),
+            threads,
+            live_threads,
+            work: Duration::from_nanos(work_nanos(&saved)),
+            span: Duration::from_nanos(span),
+            critical_path,
+        }
+    }
+
+    pub fn peak_live_threads(&self) -> usize {
+        self.live_threads.iter().map(|(_, count)| *count).max().unwrap_or(0)
+    }
+
+    // The average number of live threads over the wall time.
+    pub fn mean_live_threads(&self) -> f64 {
+        let thread_time: Duration = self.threads.iter().map(|thread_usage| thread_usage.lifetime()).sum();
+        ratio(thread_time, self.wall_time)
+    }
+
+    pub fn busy(&self) -> Duration {
+        self.threads.iter().map(|thread_usage| thread_usage.busy).sum()
+    }
+
+    pub fn idle(&self) -> Duration {
+        self.threads.iter().map(|thread_usage| thread_usage.idle()).sum()
+    }
+
+    // Work over wall time: how many times faster this was than the same work done serially.
+    pub fn speedup(&self) -> f64 {
+        ratio(self.work, self.wall_time)
+    }
+
+    // Work over span: the most speedup that any number of threads could get with these splits.
+    pub fn parallelism(&self) -> f64 {
+        ratio(self.work, self.span)
+    }
+
+    // Speedup per thread at the peak.
+    pub fn efficiency(&self) -> f64 {
+        self.speedup() / self.peak_live_threads().max(1) as f64
+    }
+
+    // How much longer the busiest thread was busy than the average thread, as a fraction of the
+    // average. Zero when the work is spread evenly.
+    pub fn load_imbalance(&self) -> f64 {
+        if self.threads.is_empty() {
+            return 0.0;
+        }
+        let max_busy = self.threads.iter().map(|thread_usage| thread_usage.busy).max().unwrap();
+        let mean_busy = self.busy() / self.threads.len() as u32;
+        ratio(max_busy, mean_busy) - 1.0
+    }
+
+    pub fn report(&self) {
+        if let Some(label) = &self.label {
+            println!("\n{}", label);
+        }
+        println!("wall time = {}", describe(self.wall_time));
+        println!("work = {}; span = {}", describe(self.work), describe(self.span));
+        println!("speedup = {:.2}; parallelism = {:.2}; efficiency = {:.2}", self.speedup(), self.parallelism(), self.efficiency());
+        println!("threads = {}; peak live = {}; mean live = {:.2}", self.threads.len(), self.peak_live_threads(), self.mean_live_threads());
+        println!("busy = {}; idle = {}; load imbalance = {:.2}", describe(self.busy()), describe(self.idle()), self.load_imbalance());
+        for thread_usage in self.threads.iter() {
+            println!("\tthread {:>3}: busy = {:>10}; idle = {:>10}; utilization = {:.2}",
+                     thread_usage.thread_number, describe(thread_usage.busy), describe(thread_usage.idle()), thread_usage.utilization());
+        }
+        println!("critical path:");
+        for call in self.critical_path.iter() {
+            println!("\t{:?} [{}..{}) on thread {}: {}", call.actor, call.from, call.to, call.thread_number, describe(call.self_time));
+        }
+    }
+}
+
+fn live_threads(threads: &[ThreadUsage]) -> Vec<(Duration, usize)> {
+    // Ends sort before starts at the same time so that one thread taking over from another isn't
+    // counted as two.
+    let mut events: Vec<(Duration, bool)> = threads.iter()
+        .flat_map(|thread_usage| vec![(thread_usage.start, true), (thread_usage.end, false)])
+        .collect();
+    events.sort();
+    let mut live_threads: Vec<(Duration, usize)> = vec![];
+    let mut count = 0;
+    for (time, is_start) in events {
+        if is_start { count += 1 } else { count -= 1 }
+        match live_threads.last_mut() {
+            Some((last_time, last_count)) if *last_time == time => *last_count = count,
+            _ => live_threads.push((time, count)),
+        }
+    }
+    live_threads
+}
+
+fn end_nanos(call: &SavedRangeCall) -> u64 {
+    call.end_nanos.unwrap_or(call.start_nanos)
+}
+
+fn is_subrange(call: &SavedRangeCall, child_call: &SavedRangeCall) -> bool {
+    child_call.from >= call.from && child_call.from + child_call.len <= call.from + call.len && child_call.len < call.len
+}
+
+// The call's span less the time covered by any of its child calls.
+fn self_nanos(call: &SavedRangeCall) -> u64 {
+    let (start, end) = (call.start_nanos, end_nanos(call));
+    let mut child_spans: Vec<(u64, u64)> = call.child_calls.iter()
+        .map(|child_call| (child_call.start_nanos.max(start), end_nanos(child_call).min(end)))
+        .filter(|(child_start, child_end)| child_start < child_end)
+        .collect();
+    child_spans.sort();
+    let mut covered = 0;
+    let mut covered_to = start;
+    for (child_start, child_end) in child_spans {
+        let child_start = child_start.max(covered_to);
+        if child_end > child_start {
+            covered += child_end - child_start;
+            covered_to = child_end;
+        }
+    }
+    (end - start) - covered
+}
+
+fn work_nanos(call: &SavedRangeCall) -> u64 {
+    self_nanos(call) + call.child_calls.iter().map(work_nanos).sum::<u64>()
+}
+
+// The same numbering as ActionList::from_range_call(): calls that start a thread are numbered in the
+// order they started and every other call is on its parent's thread.
+fn thread_numbers(call: &SavedRangeCall) -> Vec<(String, u64)> {
+    let mut thread_starters = vec![];
+    add_thread_starters(&mut thread_starters, call);
+    thread_starters.sort_by_key(|(_, start_nanos)| *start_nanos);
+    thread_starters
+}
+
+fn add_thread_starters(thread_starters: &mut Vec<(String, u64)>, call: &SavedRangeCall) {
+    if call.is_new_thread {
+        thread_starters.push((call.key.clone(), call.start_nanos));
+    }
+    for child_call in call.child_calls.iter() {
+        add_thread_starters(thread_starters, child_call);
+    }
+}
+
+// The span of the call and the calls on its critical path.
+fn critical_path(call: &SavedRangeCall, thread_starters: &[(String, u64)], parent_thread_number: u8) -> (u64, Vec<CriticalPathCall>) {
+    let thread_number = if call.is_new_thread {
+        thread_starters.iter().position(|(key, _)| *key == call.key).unwrap() as u8
+    } else {
+        parent_thread_number
+    };
+    let self_nanos = self_nanos(call);
+    let mut span = self_nanos;
+    let mut path = vec![CriticalPathCall {
+        key: call.key.clone(),
+        actor: call.actor,
+        from: call.from,
+        to: call.from + call.len,
+        thread_number,
+        self_time: Duration::from_nanos(self_nanos),
+    }];
+    let mut longest_subrange: Option<(u64, Vec<CriticalPathCall>)> = None;
+    let mut child_calls: Vec<&SavedRangeCall> = call.child_calls.iter().collect();
+    child_calls.sort_by_key(|child_call| child_call.start_nanos);
+    for child_call in child_calls {
+        let (child_span, child_path) = critical_path(child_call, thread_starters, thread_number);
+        if is_subrange(call, child_call) {
+            if longest_subrange.as_ref().map_or(true, |(longest_span, _)| child_span > *longest_span) {
+                longest_subrange = Some((child_span, child_path));
+            }
+        } else {
+            span += child_span;
+            path.extend(child_path);
+        }
+    }
+    if let Some((child_span, child_path)) = longest_subrange {
+        span += child_span;
+        path.extend(child_path);
+    }
+    (span, path)
+}
+
+fn ratio(numerator: Duration, denominator: Duration) -> f64 {
+    if denominator.as_nanos() == 0 {
+        0.0
+    } else {
+        numerator.as_nanos() as f64 / denominator.as_nanos() as f64
+    }
+}
+
+fn describe(duration: Duration) -> String {
+    format!("{:?}", duration)
+}
+
+// The same array sorted with each combination of settings, one line each.
+fn try_compare_settings() {
+    let size = 20_000;
+    let v = test_data::vec_usize_shuffled(size);
+    println!("\nquicksort: count = {}", util::format::format_count(size));
+    println!("{:>9} {:>11} {:>12} {:>12} {:>12} {:>8} {:>11} {:>8} {:>9} {:>10}",
+             "min split", "max threads", "wall time", "work", "span", "speedup", "parallelism", "threads", "peak live", "imbalance");
+    for min_split_size in [20, 100, 500].iter() {
+        for max_threads in [1, 2, 4, 8].iter() {
+            let mut v = v.clone();
+            let range_call = quicksort::quicksort(&mut v, *min_split_size, *max_threads);
+            let analysis = RunAnalysis::from_range_call(&range_call, None);
+            println!("{:>9} {:>11} {:>12} {:>12} {:>12} {:>8.2} {:>11.2} {:>8} {:>9} {:>10.2}",
+                     min_split_size, max_threads, describe(analysis.wall_time), describe(analysis.work), describe(analysis.span),
+                     analysis.speedup(), analysis.parallelism(), analysis.threads.len(), analysis.peak_live_threads(), analysis.load_imbalance());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_fork_join() {
+        // As vis::quicksort records it: a call over 0..100 partitions the whole range, then sorts the
+        // two halves with rayon::join() and finishes after both are done.
+        let start = Instant::now();
+        let at = |micros: u64| start + Duration::from_micros(micros);
+        let mut root = RangeCall::new_at("0", Actor::Quicksort, 0, 100, false, at(0));
+        let mut partition = RangeCall::new_at("00", Actor::Partition, 0, 100, false, at(1));
+        partition.end_at(at(10));
+        let mut low = RangeCall::new_at("00", Actor::Quicksort, 0, 50, true, at(11));
+        low.end_at(at(60));
+        let mut high = RangeCall::new_at("01", Actor::Quicksort, 50, 50, true, at(12));
+        high.end_at(at(40));
+        root.add_child_call(partition);
+        root.add_child_calls((low, high));
+        root.end_at(at(70));
+
+        let analysis = RunAnalysis::from_range_call(&root, None);
+        let micros = Duration::from_micros;
+        assert_eq!(micros(70), analysis.wall_time);
+        // Self times: the root 12 (0..1, 10..11 and 60..70), the partition 9, the low half 49 and the high
+        // half 28.
+        assert_eq!(micros(98), analysis.work);
+        assert_eq!(micros(70), analysis.span);
+        let path: Vec<(Actor, usize, usize)> = analysis.critical_path.iter().map(|call| (call.actor, call.from, call.to)).collect();
+        assert_eq!(vec![(Actor::Quicksort, 0, 100), (Actor::Partition, 0, 100), (Actor::Quicksort, 0, 50)], path);
+
+        // The first thread started gets the same number as the calling thread, as in ActionList.
+        assert_eq!(2, analysis.threads.len());
+        let (main_thread, high_thread) = (&analysis.threads[0], &analysis.threads[1]);
+        assert_eq!((micros(0), micros(70)), (main_thread.start, main_thread.end));
+        // Idle between the partition and the low half, and for the few nanoseconds it takes to let go of
+        // a range and take the next one.
+        assert!(main_thread.idle() >= micros(1) && main_thread.idle() < micros(2));
+        assert_eq!((micros(12), micros(40), micros(28)), (high_thread.start, high_thread.end, high_thread.busy));
+        let live: Vec<(Duration, usize)> = vec![(micros(0), 1), (micros(12), 2), (micros(40), 1), (micros(70), 0)];
+        assert_eq!(live, analysis.live_threads);
+        assert_eq!(2, analysis.peak_live_threads());
+    }
+}
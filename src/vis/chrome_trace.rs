@@ -0,0 +1,262 @@
+#![allow(dead_code)]
+
+use super::model::*;
+use super::quicksort;
+use crate::sort::test_data;
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const FILE_TRACE: &str = "sort_trace.json";
+const FILE_TRACE_WORKERS: &str = "sort_trace_workers.json";
+
+pub fn main() {
+    try_quicksort_trace();
+    // try_quicksort_worker_trace();
+}
+
+// Export to the Trace Event Format read by chrome://tracing and Perfetto (ui.perfetto.dev). Each run
+// is a process and each thread_number a thread track within it. Every call is a complete ("X") slice,
+// so calls made on the same thread nest under their parent, and swaps and the other item actions are
+// thread-scoped instant ("i") events inside the slice of the call that made them.
+//
+// With TraceTracks::Worker the tracks are the rayon workers and OS threads that actually ran the
+// actions instead, so a call whose actions were stolen by another worker shows up on both.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceTracks {
+    ThreadNumber,
+    Worker,
+}
+
+// Times are in microseconds as the format expects, with fractions to keep the nanoseconds.
+#[derive(Clone, Debug, Serialize)]
+pub struct ChromeTraceEvent {
+    pub name: String,
+    pub cat: String,
+    pub ph: String,
+    pub ts: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dur: Option<f64>,
+    pub pid: usize,
+    pub tid: usize,
+    // Scope of an instant event: "t" for the thread.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub s: Option<String>,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub args: Value,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ChromeTrace {
+    #[serde(rename = "traceEvents")]
+    pub trace_events: Vec<ChromeTraceEvent>,
+    #[serde(rename = "displayTimeUnit")]
+    pub display_time_unit: String,
+}
+
+impl ChromeTrace {
+
+    pub fn new() -> Self {
+        ChromeTrace {
+            trace_events: vec![],
+            display_time_unit: "ns".to_string(),
+        }
+    }
+
+    pub fn complete(&mut self, name: &str, cat: &str, pid: usize, tid: usize, start_nanos: u128, duration_nanos: u128, args: Value) {
+        self.trace_events.push(ChromeTraceEvent {
+            name: name.to_string(),
+            cat: cat.to_string(),
+            ph: "X".to_string(),
+            ts: nanos_to_micros(start_nanos),
+            dur: Some(nanos_to_micros(duration_nanos)),
+            pid,
+            tid,
+            s: None,
+            args,
+        });
+    }
+
+    pub fn instant(&mut self, name: &str, cat: &str, pid: usize, tid: usize, nanos: u128, args: Value) {
+        self.trace_events.push(ChromeTraceEvent {
+            name: name.to_string(),
+            cat: cat.to_string(),
+            ph: "i".to_string(),
+            ts: nanos_to_micros(nanos),
+            dur: None,
+            pid,
+            tid,
+            s: Some("t".to_string()),
+            args,
+        });
+    }
+
+    pub fn process_name(&mut self, pid: usize, name: &str) {
+        self.metadata("process_name", pid, 0, name);
+    }
+
+    pub fn thread_name(&mut self, pid: usize, tid: usize, name: &str) {
+        self.metadata("thread_name", pid, tid, name);
+    }
+
+    fn metadata(&mut self, event_name: &str, pid: usize, tid: usize, name: &str) {
+        self.trace_events.push(ChromeTraceEvent {
+            name: event_name.to_string(),
+            cat: "__metadata".to_string(),
+            ph: "M".to_string(),
+            ts: 0.0,
+            dur: None,
+            pid,
+            tid,
+            s: None,
+            args: serde_json::json!({ "name": name }),
+        });
+    }
+
+    // Adds the events from another trace, such as another run with its own pid.
+    pub fn append(&mut self, other: ChromeTrace) {
+        self.trace_events.extend(other.trace_events);
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+}
+
+impl ActionList {
+
+    // A call's slice runs from its first Take to its last Release, on the thread of the first Take.
+    // vis::quicksort gives a partition and the call that follows it on the low side the same key, so
+    // calls are told apart by actor as well as key.
+    pub fn chrome_trace(&self, pid: usize) -> ChromeTrace {
+        self.chrome_trace_with_tracks(pid, TraceTracks::ThreadNumber)
+    }
+
+    pub fn chrome_trace_with_tracks(&self, pid: usize, tracks: TraceTracks) -> ChromeTrace {
+        let mut trace = ChromeTrace::new();
+        trace.process_name(pid, self.label.as_ref().map_or("sort", |label| label.as_str()));
+
+        let mut calls: BTreeMap<(String, String), CallSpan> = BTreeMap::new();
+        let mut track_names: BTreeMap<usize, String> = BTreeMap::new();
+        for action in self.actions.iter() {
+            let (tid, track_name) = track(action, tracks);
+            track_names.entry(tid).or_insert(track_name);
+            let nanos = action.duration.as_nanos();
+            let call_id = (action.call_key.clone(), format!("{:?}", action.actor));
+            match action.action_type {
+                ActionType::Take => {
+                    let span = calls.entry(call_id).or_insert_with(|| CallSpan::new(action, tid, nanos));
+                    span.start_nanos = span.start_nanos.min(nanos);
+                    span.end_nanos = span.end_nanos.max(nanos);
+                },
+                ActionType::Release => {
+                    let span = calls.entry(call_id).or_insert_with(|| CallSpan::new(action, tid, nanos));
+                    span.end_nanos = span.end_nanos.max(nanos);
+                },
+                ActionType::MoveToThread | ActionType::MoveToCall { .. } => {},
+                _ => {
+                    let (name, args) = action_event(&action.action_type);
+                    trace.instant(name, "action", pid, tid, nanos, args);
+                },
+            }
+        }
+
+        for (tid, track_name) in track_names {
+            trace.thread_name(pid, tid, &track_name);
+        }
+        for ((call_key, actor), span) in calls {
+            let args = serde_json::json!({ "call_key": call_key, "from": span.from, "to": span.to });
+            let name = format!("{} [{}..{})", actor, span.from, span.to);
+            trace.complete(&name, "call", pid, span.tid, span.start_nanos, span.end_nanos - span.start_nanos, args);
+        }
+        trace
+    }
+}
+
+// The track (tid) and its name for an action. Workers are numbered from 1 by OS thread so that
+// actions without a worker can go on a track of their own.
+fn track(action: &Action, tracks: TraceTracks) -> (usize, String) {
+    match (tracks, action.worker) {
+        (TraceTracks::ThreadNumber, _) => (action.thread_number as usize, format!("thread {}", action.thread_number)),
+        (TraceTracks::Worker, Some(worker)) => (worker.os_thread + 1, worker.description()),
+        (TraceTracks::Worker, None) => (0, "no worker".to_string()),
+    }
+}
+
+struct CallSpan {
+    tid: usize,
+    from: usize,
+    to: usize,
+    start_nanos: u128,
+    end_nanos: u128,
+}
+
+impl CallSpan {
+    fn new(action: &Action, tid: usize, nanos: u128) -> Self {
+        CallSpan {
+            tid,
+            from: action.from,
+            to: action.to,
+            start_nanos: nanos,
+            end_nanos: nanos,
+        }
+    }
+}
+
+fn action_event(action_type: &ActionType) -> (&'static str, Value) {
+    match *action_type {
+        ActionType::Swap { a, b } => ("swap", serde_json::json!({ "a": a, "b": b })),
+        ActionType::MarkFinal { from, to } => ("mark final", serde_json::json!({ "from": from, "to": to })),
+        ActionType::Compare { a, b } => ("compare", serde_json::json!({ "a": a, "b": b })),
+        ActionType::WriteToBuffer { index, buffer_index } => ("write to buffer", serde_json::json!({ "index": index, "buffer_index": buffer_index })),
+        ActionType::CopyBack { buffer_index, index } => ("copy back", serde_json::json!({ "buffer_index": buffer_index, "index": index })),
+        ActionType::Rotate { from, to } => ("rotate", serde_json::json!({ "from": from, "to": to })),
+        ActionType::MoveToThread | ActionType::MoveToCall { .. } | ActionType::Take | ActionType::Release => unreachable!(),
+    }
+}
+
+// Several runs in one file, each as its own process in the order given.
+pub fn chrome_trace_json(action_lists: &[ActionList]) -> String {
+    let mut trace = ChromeTrace::new();
+    for (run_number, action_list) in action_lists.iter().enumerate() {
+        trace.append(action_list.chrome_trace(run_number));
+    }
+    trace.to_json()
+}
+
+pub fn nanos_to_micros(nanos: u128) -> f64 {
+    nanos as f64 / 1_000.0
+}
+
+// The same run with a track for each rayon worker rather than each thread number.
+fn try_quicksort_worker_trace() {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+    let mut v = test_data::vec_usize_shuffled(2_000);
+    let range_call = quicksort::quicksort_in_pool(&mut v, 20, 8, &pool);
+    let action_list = ActionList::from_range_call(&range_call, Some("quicksort by worker"));
+    action_list.chrome_trace_with_tracks(0, TraceTracks::Worker).save(FILE_TRACE_WORKERS).unwrap();
+    println!("Wrote {}. Open it in ui.perfetto.dev or chrome://tracing.", FILE_TRACE_WORKERS);
+}
+
+fn try_quicksort_trace() {
+    let size = 2_000;
+    let v = test_data::vec_usize_shuffled(size);
+    let mut action_lists = vec![];
+    for max_threads in [1, 4].iter() {
+        let mut v = v.clone();
+        let range_call = quicksort::quicksort(&mut v, 20, *max_threads);
+        let label = format!("quicksort: count = {}; max threads = {}", size, max_threads);
+        action_lists.push(ActionList::from_range_call(&range_call, Some(&label)));
+    }
+    fs::write(FILE_TRACE, chrome_trace_json(&action_lists)).unwrap();
+    println!("Wrote {}. Open it in ui.perfetto.dev or chrome://tracing.", FILE_TRACE);
+}
@@ -0,0 +1,89 @@
+#![allow(dead_code)]
+
+use crate::sort::test_data;
+use super::gen_js::{self, ThreadLayout};
+use super::model::*;
+use super::traced_slice;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// The page with the player. The data from gen_js::js_data() goes in place of {{data}}.
+const HTML_TEMPLATE: &str = include_str!("player.html");
+// Built in rather than read at run time like gen_js does so that the page can be written from anywhere.
+const JS_CONSTANTS: &str = include_str!("../../constants.js");
+const FILE_HTML: &str = "sort_animation.html";
+
+pub fn main() {
+    try_gen_html_multiple();
+    // try_gen_html_traced();
+    // try_gen_html_lanes();
+}
+
+// One HTML file with the player and the data for all of the runs, so it can be opened straight from
+// disk with no other files and no network. Each run gets its own panel with the items as bars, the
+// buffer if the run has one, and a lane for each thread showing which items that thread holds.
+pub fn html(action_lists: &[ActionList], title: &str) -> String {
+    html_with_layout(action_lists, title, ThreadLayout::Flat)
+}
+
+// The page starts in the given layout. It can be changed on the page.
+pub fn html_with_layout(action_lists: &[ActionList], title: &str, thread_layout: ThreadLayout) -> String {
+    let mut data = JS_CONSTANTS.to_string();
+    data.push_str(&gen_js::js_data_with_layout(action_lists, thread_layout));
+    // A label can't be allowed to close the script element early.
+    let data = data.replace("</", "<\\/");
+    HTML_TEMPLATE
+        .replace("{{title}}", &escape_html(title))
+        .replace("{{data}}", &data)
+}
+
+pub fn gen_html<P: AsRef<Path>>(action_lists: &[ActionList], title: &str, path: P) -> io::Result<()> {
+    fs::write(path, html(action_lists, title))
+}
+
+pub fn gen_html_with_layout<P: AsRef<Path>>(action_lists: &[ActionList], title: &str, thread_layout: ThreadLayout, path: P) -> io::Result<()> {
+    fs::write(path, html_with_layout(action_lists, title, thread_layout))
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// The same runs as gen_js::try_gen_multiple_2(): one array sorted with more thread splits each time.
+fn try_gen_html_multiple() {
+    let size = 500;
+    let min_split_size = 20;
+    let mut action_lists = vec![];
+    let v = test_data::vec_usize_shuffled(size);
+    for thread_splits in 0..=3 {
+        action_lists.push(gen_js::make_action_list(size, min_split_size, thread_splits, Some(v.clone())));
+    }
+    gen_html(&action_lists, "Quicksort by max thread splits", FILE_HTML).unwrap();
+    println!("Wrote {}.", FILE_HTML);
+}
+
+fn try_gen_html_traced() {
+    let v = test_data::vec_usize_shuffled(100);
+    let action_lists = vec![
+        traced_slice::trace_quicksort_rayon(&v, 10, 20, Some("quicksort_rayon_on")),
+        traced_slice::trace_merge_sort(&v, Some("merge sort with buffer")),
+        traced_slice::trace_merge_sort_loop(&v, 5, 4, Some("merge_sort_loop; max threads = 4")),
+    ];
+    gen_html(&action_lists, "Traced sorts", FILE_HTML).unwrap();
+    println!("Wrote {}.", FILE_HTML);
+}
+
+// The merge_sort_loop run has several threads at once, which shows the lanes best.
+fn try_gen_html_lanes() {
+    let v = test_data::vec_usize_shuffled(200);
+    let action_lists = vec![
+        traced_slice::trace_merge_sort_loop(&v, 5, 4, Some("merge_sort_loop; max threads = 4")),
+        gen_js::make_action_list(200, 20, 2, Some(v.clone())),
+    ];
+    gen_html_with_layout(&action_lists, "Threads in lanes", ThreadLayout::Horizontal, FILE_HTML).unwrap();
+    println!("Wrote {}.", FILE_HTML);
+}
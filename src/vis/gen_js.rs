@@ -3,11 +3,13 @@
 use crate::sort::test_data;
 use super::quicksort::*;
 use super::model::*;
+use super::traced_slice;
 use std::fs;
 use rand::rngs::ThreadRng;
 use std::collections::BTreeMap;
 use std::ops::Range;
 use std::fmt::Display;
+use std::time::Duration;
 use itertools::Itertools;
 
 const FILE_JS_CONSTANTS: &str = "constants.js";
@@ -16,10 +18,20 @@ const FILE_JS_DATA: &str = "sort_data.js";
 pub fn main() {
     //try_gen_one();
     try_gen_multiple_1();
+    // try_gen_traced();
+    // try_gen_lanes();
 }
 
+// How the player arranges the threads. In the lane layouts each thread that's live at the same time
+// as another gets its own lane, and an item's bar is drawn in the lane of the thread it last moved to,
+// so a MoveToThread shows as bars jumping between lanes. Lanes are reused once their thread is done.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ThreadLayout {
+    // One row of bars, with a strip under it showing which thread holds each item.
+    Flat,
+    // A row of bars for each lane, one above the other.
     Horizontal,
+    // A column for each lane, side by side, with the items running down the page.
     Vertical,
 }
 
@@ -29,6 +41,22 @@ pub struct JSAction {
 }
 
 pub fn gen_js(action_lists: &[ActionList]) {
+    gen_js_with_layout(action_lists, ThreadLayout::Flat);
+}
+
+pub fn gen_js_with_layout(action_lists: &[ActionList], thread_layout: ThreadLayout) {
+    let mut s = fs::read_to_string(FILE_JS_CONSTANTS).unwrap();
+    s.push_str(&js_data_with_layout(action_lists, thread_layout));
+    write_js_file(&s);
+}
+
+// The variables that go after constants.js: run_list, thread_layout, max_item_count, dataset and
+// steps. Kept apart from gen_js() so that other exporters such as gen_html can embed the same data.
+pub fn js_data(action_lists: &[ActionList]) -> String {
+    js_data_with_layout(action_lists, ThreadLayout::Flat)
+}
+
+pub fn js_data_with_layout(action_lists: &[ActionList], thread_layout: ThreadLayout) -> String {
 
     // action_list.sort_unstable_by_key(|x| x.duration);
 
@@ -38,18 +66,15 @@ pub fn gen_js(action_lists: &[ActionList]) {
         .max()
         .unwrap();
 
-    let max_duration_nanos = action_lists
-        .iter()
-        .map(|list| list.actions.iter().map(|action| action.duration).max().unwrap())
-        .max()
-        .unwrap()
-        .as_nanos();
+    let max_duration_nanos = max_duration_nanos(action_lists);
+
+    let lanes: Vec<BTreeMap<u8, usize>> = action_lists.iter().map(thread_lanes).collect();
 
     let mut js_items = vec![];
     let mut js_actions = vec![];
     let mut index_offset = 0;
     for (run_number, action_list) in action_lists.iter().enumerate() {
-        add_js_entries(&mut js_items, &mut js_actions, action_list, run_number, max_duration_nanos, index_offset);
+        add_js_entries(&mut js_items, &mut js_actions, action_list, run_number, max_duration_nanos, index_offset, &lanes[run_number]);
         index_offset += action_list.unsorted_array.as_ref().unwrap().len();
     }
 
@@ -60,15 +85,19 @@ pub fn gen_js(action_lists: &[ActionList]) {
         .collect();
     */
 
-    let mut s = fs::read_to_string(FILE_JS_CONSTANTS).unwrap();
+    let mut s = String::new();
 
     s.push_str("\nvar run_list = [");
-    for action_list in action_lists {
+    for (action_list, lanes) in action_lists.iter().zip(lanes.iter()) {
         let size = action_list.unsorted_array.as_ref().unwrap().len();
-        s.push_str(&format!("\n\t{{ size: {}, call_segments: [{}] }},", size, size));
+        let label = action_list.label.as_ref().map_or("".to_string(), |label| label.replace('\\', "\\\\").replace('"', "\\\""));
+        let lane_count = lanes.values().max().map_or(1, |lane| lane + 1);
+        s.push_str(&format!("\n\t{{ size: {}, call_segments: [{}], label: \"{}\", lane_count: {} }},", size, size, label, lane_count));
     }
     s.push_str("\n];\n");
 
+    s.push_str(&format!("\nvar thread_layout = \"{}\";\n", thread_layout_to_js(thread_layout)));
+
     s.push_str(&format!("\nvar max_item_count = {};\n", max_item_count));
 
     s.push_str(&format!("\nvar dataset = ["));
@@ -83,7 +112,7 @@ pub fn gen_js(action_lists: &[ActionList]) {
     }
     s.push_str(&format!("\n];\n"));
 
-    write_js_file(&s);
+    s
 }
 
 fn add_js_entries(js_items: &mut Vec<String>,
@@ -91,7 +120,8 @@ fn add_js_entries(js_items: &mut Vec<String>,
                   action_list: &ActionList,
                   run_number: usize,
                   max_duration_nanos: u128,
-                  index_offset: usize) {
+                  index_offset: usize,
+                  lanes: &BTreeMap<u8, usize>) {
 
     let v = action_list.unsorted_array.as_ref().unwrap();
     let max_value = v.iter().map(|x| *x).max().unwrap() as f64;
@@ -101,31 +131,55 @@ fn add_js_entries(js_items: &mut Vec<String>,
     }
 
     for action in action_list.actions.iter() {
-        js_actions.push(action_to_js(action, run_number, max_duration_nanos, index_offset));
+        js_actions.push(action_to_js(action, run_number, max_duration_nanos, index_offset, lanes[&action.thread_number]));
     }
 
 }
 
-/*
-fn add_actions_horizontal_threads(s: &mut String, action_list: &ActionList, _array_length: usize, max_time: u128) {
-    //let mut live_threads = LiveThreadList::new(0..array_length);
-    for action in action_list.iter() {
-
-
-        s.push_str(&format!("\n    {}", action_to_js(action, max_time)));
+// The lane for each thread number. A thread goes in the first lane whose last thread ended before it
+// started, going by when each thread holds a range as in ActionList::thread_usage().
+pub fn thread_lanes(action_list: &ActionList) -> BTreeMap<u8, usize> {
+    let mut threads = action_list.thread_usage();
+    threads.sort_by_key(|thread_usage| thread_usage.start);
+    let mut lane_ends: Vec<Duration> = vec![];
+    let mut lanes = BTreeMap::new();
+    for thread_usage in threads {
+        let lane = match lane_ends.iter().position(|lane_end| *lane_end < thread_usage.start) {
+            Some(lane) => lane,
+            None => {
+                lane_ends.push(thread_usage.end);
+                lane_ends.len() - 1
+            },
+        };
+        lane_ends[lane] = thread_usage.end;
+        lanes.insert(thread_usage.thread_number, lane);
+    }
+    // Threads that never hold a range only show up in MoveToThread actions.
+    for action in action_list.actions.iter() {
+        lanes.entry(action.thread_number).or_insert(0);
     }
+    lanes
 }
-*/
 fn write_js_file(content: &str) {
     fs::write(FILE_JS_DATA, content).unwrap();
     println!("{}", content);
 }
 
+fn thread_layout_to_js(thread_layout: ThreadLayout) -> &'static str {
+    match thread_layout {
+        ThreadLayout::Flat => "flat",
+        ThreadLayout::Horizontal => "horizontal",
+        ThreadLayout::Vertical => "vertical",
+    }
+}
+
 fn actor_to_js(actor: &Actor) -> &str {
     match actor {
         Actor::BubbleSort => "BUBBLE_SORT",
         Actor::Partition => "PARTITION",
         Actor::Quicksort => "QUICKSORT",
+        Actor::MergeSort => "MERGE_SORT",
+        Actor::InsertionSort => "INSERTION_SORT",
     }
 }
 
@@ -139,9 +193,24 @@ pub to: usize,
 pub action_type: ActionType,
 */
 
-fn action_to_js(action: &Action, run_number: usize, max_duration_nanos: u128, index_offset: usize) -> JSAction {
+// The time of the last action in any of the runs. Every step's time is a fraction of this so that runs
+// shown together play at the same rate.
+pub fn max_duration_nanos(action_lists: &[ActionList]) -> u128 {
+    action_lists
+        .iter()
+        .map(|list| list.actions.iter().map(|action| action.duration).max().unwrap())
+        .max()
+        .unwrap()
+        .as_nanos()
+}
+
+pub fn time_fraction(action: &Action, max_duration_nanos: u128) -> f32 {
+    action.duration.as_nanos() as f32 / max_duration_nanos as f32
+}
+
+fn action_to_js(action: &Action, run_number: usize, max_duration_nanos: u128, index_offset: usize, lane: usize) -> JSAction {
     let action_duration_nanos = action.duration.as_nanos();
-    let time_fraction = action_duration_nanos as f32 / max_duration_nanos as f32;
+    let time_fraction = time_fraction(action, max_duration_nanos);
     let prefix = {
         let time = format!("time: {}", time_fraction);
         let run_number = format!(", run_number: {}", run_number);
@@ -149,9 +218,10 @@ fn action_to_js(action: &Action, run_number: usize, max_duration_nanos: u128, in
     };
     let actor = format!(", actor: actor.{}", actor_to_js(&action.actor));
     let from_to = format!(", from: {}, to: {}", action.from + index_offset, action.to + index_offset);
+    let lane = format!(", lane: {}", lane);
     let suffix =  " },";
     let entry = match &action.action_type {
-        ActionType::MoveToThread => format!("{}MOVE_TO_THREAD, thread_number: {}{}{}", prefix, action.thread_number, from_to, suffix),
+        ActionType::MoveToThread => format!("{}MOVE_TO_THREAD, thread_number: {}{}{}{}", prefix, action.thread_number, lane, from_to, suffix),
         ActionType::MoveToCall { from_call_key: _ , to_call_key: _, ranges} => {
             let mut s = String::new();
             s.push_str(&format!("{}MOVE_TO_CALL, call_segments: [", prefix));
@@ -159,13 +229,17 @@ fn action_to_js(action: &Action, run_number: usize, max_duration_nanos: u128, in
                 // s.push_str(&format!("{{ from: {}, to: {} }}, ", range.start, range.end));
                 s.push_str(&format!("{}, ", range.end));
             }
-            s.push_str(&format!("]{}", suffix));
+            s.push_str(&format!("]{}{}{}", lane, from_to, suffix));
             s
         }
-        ActionType::Take => format!("{}TAKE{}{}{}", prefix, actor, from_to, suffix),
-        ActionType::Release => format!("{}RELEASE{}{}", prefix, from_to, suffix),
+        ActionType::Take => format!("{}TAKE, thread_number: {}{}{}{}{}", prefix, action.thread_number, lane, actor, from_to, suffix),
+        ActionType::Release => format!("{}RELEASE, thread_number: {}{}{}{}", prefix, action.thread_number, lane, from_to, suffix),
         ActionType::Swap { a, b } => format!("{}SWAP, a: {}, b: {}{}", prefix, a + index_offset, b + index_offset, suffix),
         ActionType::MarkFinal{ from, to } => format!("{}MARK_FINAL, from: {}, to: {}{}", prefix, from + index_offset, to + index_offset, suffix),
+        ActionType::Compare { a, b } => format!("{}COMPARE, a: {}, b: {}{}", prefix, a + index_offset, b + index_offset, suffix),
+        ActionType::WriteToBuffer { index, buffer_index } => format!("{}WRITE_TO_BUFFER, index: {}, buffer_index: {}{}", prefix, index + index_offset, buffer_index + index_offset, suffix),
+        ActionType::CopyBack { buffer_index, index } => format!("{}COPY_BACK, buffer_index: {}, index: {}{}", prefix, buffer_index + index_offset, index + index_offset, suffix),
+        ActionType::Rotate { from, to } => format!("{}ROTATE, from: {}, to: {}{}", prefix, from + index_offset, to + index_offset, suffix),
     };
     JSAction {
         time: action_duration_nanos,
@@ -216,7 +290,33 @@ fn try_gen_multiple_2() {
     gen_js(&action_lists);
 }
 
-fn make_action_list(size: usize, min_split_size: usize, thread_splits: u8, shared_v: Option<Vec<usize>>) -> ActionList {
+// The same shuffled array through the traced merge sorts and insertion sort, one run each.
+fn try_gen_traced() {
+    let size = 100;
+    let v = test_data::vec_usize_shuffled(size);
+    let action_lists = vec![
+        traced_slice::trace_merge_sort(&v, Some("merge sort with buffer")),
+        traced_slice::trace_merge_sort_loop(&v, 5, 4, Some("merge_sort_loop; max threads = 4")),
+        traced_slice::trace_insertion_sort(&v, Some("insertion sort"), false),
+    ];
+    for action_list in action_lists.iter() {
+        action_list.report_types();
+    }
+    gen_js(&action_lists);
+}
+
+// The runs from try_gen_multiple_2() with each thread in its own lane.
+fn try_gen_lanes() {
+    let size = 500;
+    let min_split_size = 20;
+    let v = test_data::vec_usize_shuffled(size);
+    let action_lists: Vec<ActionList> = (0..=3)
+        .map(|thread_splits| make_action_list(size, min_split_size, thread_splits, Some(v.clone())))
+        .collect();
+    gen_js_with_layout(&action_lists, ThreadLayout::Horizontal);
+}
+
+pub fn make_action_list(size: usize, min_split_size: usize, thread_splits: u8, shared_v: Option<Vec<usize>>) -> ActionList {
     let label = &format!("Linux: count = {}; simple sort threshold = {}; max thread splits = {}", size, min_split_size, thread_splits);
     let max_threads = 2u8.pow(thread_splits.into());
     let mut v = if let Some(shared_v) = shared_v {
@@ -0,0 +1,381 @@
+#![allow(dead_code)]
+
+use std::cmp;
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::thread::{self, JoinHandle, ThreadId};
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::{self, Receiver};
+use serde::{Serialize, Deserialize};
+
+use super::model::Actor;
+use super::traced_slice::{TraceEvent, TraceKind, Tracer};
+use crate::sort::bubble_sort;
+use crate::sort::insertion_sort;
+use crate::sort::merge_sort;
+use crate::sort::merge_sort_loop;
+use crate::sort::quicksort_rayon;
+use crate::sort::test_data;
+
+pub fn main() {
+    try_print_messages();
+}
+
+// A sort run on a background thread whose events can be read while it's still going, for showing
+// progress as it happens rather than writing an ActionList once the run is over. The sort works on
+// a TracedSlice from a live Tracer, and each event it sends is turned into a LiveMessage with the
+// call key in place of the call index and a small thread number in place of the ThreadId.
+//
+// The first message has the unsorted array and the last one says whether the result was sorted, so
+// a page can keep its own copy of the array in step by applying the swaps, rotates and buffer copies
+// in between. http::sort_stream serves the messages as Server-Sent Events or as JSON lines.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LiveSort {
+    BubbleSort,
+    InsertionSort,
+    MergeSort,
+    MergeSortLoop,
+    QuicksortRayon,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LiveData {
+    Shuffled,
+    Ordered,
+    Reversed,
+}
+
+#[derive(Clone, Debug)]
+pub struct LiveOptions {
+    pub sort: LiveSort,
+    pub data: LiveData,
+    pub size: usize,
+    pub min_split_size: u8,
+    pub max_threads: u8,
+    pub min_thread_size: u16,
+    // How long the sort waits after each event it sends.
+    pub delay: Duration,
+    pub include_compares: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LiveFormat {
+    // Server-Sent Events, one "data:" line per message.
+    EventStream,
+    // One JSON object per line.
+    JsonLines,
+}
+
+// Indexes are absolute. thread is numbered from 0 in the order the threads first did something.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveMessage {
+    Start { sort: LiveSort, unsorted_array: Vec<usize> },
+    CallStart { nanos: u64, thread: usize, key: String, actor: Actor, from: usize, len: usize },
+    CallEnd { nanos: u64, thread: usize, key: String },
+    // With b_in_buffer set b is a buffer slot, numbered like buffer_index in WriteToBuffer.
+    Compare { nanos: u64, thread: usize, key: String, a: usize, b: usize, b_in_buffer: bool },
+    Swap { nanos: u64, thread: usize, key: String, a: usize, b: usize },
+    WriteToBuffer { nanos: u64, thread: usize, key: String, index: usize, buffer_index: usize },
+    CopyBack { nanos: u64, thread: usize, key: String, buffer_index: usize, index: usize },
+    // Moves the item at to - 1 to from and everything from..to - 1 up by one.
+    Rotate { nanos: u64, thread: usize, key: String, from: usize, to: usize },
+    Done { nanos: u64, message_count: usize, thread_count: usize, sorted: bool },
+}
+
+pub struct LiveRun {
+    start: Option<LiveMessage>,
+    receiver: Receiver<TraceEvent>,
+    handle: Option<JoinHandle<bool>>,
+    first_instant: Instant,
+    include_compares: bool,
+    call_keys: HashMap<usize, String>,
+    thread_numbers: HashMap<ThreadId, usize>,
+    message_count: usize,
+}
+
+pub struct LiveReader {
+    run: LiveRun,
+    format: LiveFormat,
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl LiveSort {
+
+    pub fn all() -> Vec<LiveSort> {
+        vec![LiveSort::BubbleSort, LiveSort::InsertionSort, LiveSort::MergeSort, LiveSort::MergeSortLoop, LiveSort::QuicksortRayon]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LiveSort::BubbleSort => "bubble_sort",
+            LiveSort::InsertionSort => "insertion_sort",
+            LiveSort::MergeSort => "merge_sort",
+            LiveSort::MergeSortLoop => "merge_sort_loop",
+            LiveSort::QuicksortRayon => "quicksort_rayon",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().into_iter().find(|sort| sort.name() == name)
+    }
+}
+
+impl LiveData {
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "shuffled" => Some(LiveData::Shuffled),
+            "ordered" => Some(LiveData::Ordered),
+            "reversed" => Some(LiveData::Reversed),
+            _ => None,
+        }
+    }
+
+    pub fn generate(&self, size: usize) -> Vec<usize> {
+        match self {
+            LiveData::Shuffled => test_data::vec_usize_shuffled(size),
+            LiveData::Ordered => test_data::vec_usize_ordered(size),
+            LiveData::Reversed => test_data::vec_usize_reversed(size),
+        }
+    }
+}
+
+impl Default for LiveOptions {
+    fn default() -> Self {
+        LiveOptions {
+            sort: LiveSort::QuicksortRayon,
+            data: LiveData::Shuffled,
+            size: 100,
+            min_split_size: 10,
+            max_threads: 4,
+            min_thread_size: 20,
+            delay: Duration::from_millis(5),
+            include_compares: false,
+        }
+    }
+}
+
+impl LiveMessage {
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn format(&self, format: LiveFormat) -> String {
+        match format {
+            LiveFormat::EventStream => format!("data: {}\n\n", self.to_json()),
+            LiveFormat::JsonLines => format!("{}\n", self.to_json()),
+        }
+    }
+}
+
+// Starts the sort on data generated from the options and returns straight away.
+pub fn start(options: &LiveOptions) -> LiveRun {
+    let v = options.data.generate(options.size);
+    let start = LiveMessage::Start { sort: options.sort, unsorted_array: v.clone() };
+    let (sender, receiver) = channel::unbounded();
+    let first_instant = Instant::now();
+    let run_options = options.clone();
+    let handle = thread::spawn(move || {
+        let mut v = v;
+        let tracer = Tracer::live(sender, run_options.delay, run_options.include_compares);
+        run_sort(&tracer, &mut v, &run_options);
+        // The tracer has to go first so that the channel is closed by the time the run is joined.
+        drop(tracer);
+        v.is_sorted()
+    });
+    LiveRun {
+        start: Some(start),
+        receiver,
+        handle: Some(handle),
+        first_instant,
+        include_compares: options.include_compares,
+        call_keys: HashMap::new(),
+        thread_numbers: HashMap::new(),
+        message_count: 0,
+    }
+}
+
+fn run_sort(tracer: &Tracer, v: &mut [usize], options: &LiveOptions) {
+    match options.sort {
+        LiveSort::BubbleSort => bubble_sort::bubble_sort_on(&mut tracer.slice(v, Actor::BubbleSort)),
+        LiveSort::InsertionSort => insertion_sort::insertion_sort_on(&mut tracer.slice(v, Actor::InsertionSort)),
+        LiveSort::MergeSort => { merge_sort::merge_sort_on(tracer.slice(v, Actor::MergeSort)); },
+        LiveSort::MergeSortLoop => { merge_sort_loop::merge_sort_loop_on(tracer.slice(v, Actor::MergeSort), options.min_split_size, options.max_threads); },
+        LiveSort::QuicksortRayon => quicksort_rayon::quicksort_rayon_on(tracer.slice(v, Actor::Quicksort), options.min_split_size, options.min_thread_size),
+    }
+}
+
+impl LiveRun {
+
+    pub fn into_reader(self, format: LiveFormat) -> LiveReader {
+        LiveReader {
+            run: self,
+            format,
+            bytes: vec![],
+            position: 0,
+        }
+    }
+
+    fn to_message(&mut self, event: TraceEvent) -> Option<LiveMessage> {
+        let nanos = event.instant.duration_since(self.first_instant).as_nanos() as u64;
+        let next_thread_number = self.thread_numbers.len();
+        let thread = *self.thread_numbers.entry(event.thread.thread_id).or_insert(next_thread_number);
+        if let TraceKind::CallStart { key, .. } = &event.kind {
+            self.call_keys.insert(event.call_index, key.clone());
+        }
+        let key = self.call_keys.get(&event.call_index).cloned().unwrap_or_default();
+        match event.kind {
+            TraceKind::CallStart { key, parent_index: _, actor, from, len } => Some(LiveMessage::CallStart { nanos, thread, key, actor, from, len }),
+            TraceKind::CallEnd => Some(LiveMessage::CallEnd { nanos, thread, key }),
+            TraceKind::Compare { a, b, b_in_buffer } if self.include_compares => Some(LiveMessage::Compare { nanos, thread, key, a, b, b_in_buffer }),
+            TraceKind::Swap { a, b } => Some(LiveMessage::Swap { nanos, thread, key, a, b }),
+            TraceKind::WriteToBuffer { index, buffer_index } => Some(LiveMessage::WriteToBuffer { nanos, thread, key, index, buffer_index }),
+            TraceKind::CopyBack { buffer_index, index } => Some(LiveMessage::CopyBack { nanos, thread, key, buffer_index, index }),
+            TraceKind::Rotate { from, to } => Some(LiveMessage::Rotate { nanos, thread, key, from, to }),
+            _ => None,
+        }
+    }
+}
+
+impl Iterator for LiveRun {
+    type Item = LiveMessage;
+
+    // Blocks until the sort sends something or finishes.
+    fn next(&mut self) -> Option<LiveMessage> {
+        if let Some(start) = self.start.take() {
+            self.message_count += 1;
+            return Some(start);
+        }
+        while let Ok(event) = self.receiver.recv() {
+            if let Some(message) = self.to_message(event) {
+                self.message_count += 1;
+                return Some(message);
+            }
+        }
+        let handle = self.handle.take()?;
+        let sorted = handle.join().unwrap_or(false);
+        self.message_count += 1;
+        Some(LiveMessage::Done {
+            nanos: self.first_instant.elapsed().as_nanos() as u64,
+            message_count: self.message_count,
+            thread_count: self.thread_numbers.len(),
+            sorted,
+        })
+    }
+}
+
+// Gives the messages as bytes in the chosen format, one message per read() at most, so that whatever
+// is copying from it can pass each message on as soon as it's ready.
+impl Read for LiveReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.bytes.len() {
+            match self.run.next() {
+                Some(message) => {
+                    self.bytes = message.format(self.format).into_bytes();
+                    self.position = 0;
+                },
+                None => return Ok(0),
+            }
+        }
+        let count = cmp::min(buf.len(), self.bytes.len() - self.position);
+        buf[..count].copy_from_slice(&self.bytes[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+fn try_print_messages() {
+    let options = LiveOptions {
+        sort: LiveSort::MergeSortLoop,
+        size: 20,
+        delay: Duration::from_millis(20),
+        ..Default::default()
+    };
+    let mut reader = start(&options).into_reader(LiveFormat::JsonLines);
+    io::copy(&mut reader, &mut io::stdout()).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Applies the messages to a copy of the array the way the page does.
+    fn replay(messages: &[LiveMessage]) -> Vec<usize> {
+        let mut v = vec![];
+        let mut buffer = vec![];
+        for message in messages {
+            match message {
+                LiveMessage::Start { unsorted_array, .. } => {
+                    v = unsorted_array.clone();
+                    buffer = vec![0; v.len()];
+                },
+                LiveMessage::Swap { a, b, .. } => v.swap(*a, *b),
+                LiveMessage::WriteToBuffer { index, buffer_index, .. } => buffer[*buffer_index] = v[*index],
+                LiveMessage::CopyBack { buffer_index, index, .. } => v[*index] = buffer[*buffer_index],
+                LiveMessage::Rotate { from, to, .. } => v[*from..*to].rotate_right(1),
+                _ => {},
+            }
+        }
+        v
+    }
+
+    #[test]
+    fn test_messages_replay_to_sorted() {
+        for sort in LiveSort::all() {
+            let options = LiveOptions { sort, size: 60, delay: Duration::from_secs(0), ..Default::default() };
+            let messages: Vec<LiveMessage> = start(&options).collect();
+            match messages.last() {
+                Some(LiveMessage::Done { message_count, sorted, .. }) => {
+                    assert!(*sorted);
+                    assert_eq!(messages.len(), *message_count);
+                },
+                _ => panic!("{:?} didn't finish with Done.", sort),
+            }
+            assert_eq!(test_data::vec_usize_ordered(60), replay(&messages), "{:?}", sort);
+        }
+    }
+
+    #[test]
+    fn test_no_delay_for_left_out_compares() {
+        // Bubble sorting ordered data only compares, so without the compares there's nothing to wait for.
+        let delay = Duration::from_millis(200);
+        let options = LiveOptions { sort: LiveSort::BubbleSort, data: LiveData::Ordered, size: 50, delay, include_compares: false, ..Default::default() };
+        let started = Instant::now();
+        let messages: Vec<LiveMessage> = start(&options).collect();
+        assert!(started.elapsed() < delay);
+        assert!(!messages.iter().any(|message| match message { LiveMessage::Compare { .. } => true, _ => false }));
+    }
+
+    #[test]
+    fn test_merge_compares_are_sent() {
+        let options = LiveOptions { sort: LiveSort::MergeSort, size: 40, delay: Duration::from_secs(0), include_compares: true, ..Default::default() };
+        let messages: Vec<LiveMessage> = start(&options).collect();
+        assert!(messages.iter().any(|message| match message { LiveMessage::Compare { b_in_buffer: true, .. } => true, _ => false }));
+    }
+
+    #[test]
+    fn test_live_tracer_keeps_no_events() {
+        let (sender, receiver) = channel::unbounded();
+        let tracer = Tracer::live(sender, Duration::from_secs(0), true);
+        let mut v = test_data::vec_usize_shuffled(50);
+        merge_sort::merge_sort_on(tracer.slice(&mut v, Actor::MergeSort));
+        assert!(v.is_sorted());
+        assert!(receiver.try_iter().count() > 0);
+        assert!(tracer.into_events().is_empty());
+    }
+
+    #[test]
+    fn test_event_stream_format() {
+        let options = LiveOptions { sort: LiveSort::BubbleSort, data: LiveData::Reversed, size: 3, delay: Duration::from_secs(0), ..Default::default() };
+        let mut text = String::new();
+        start(&options).into_reader(LiveFormat::EventStream).read_to_string(&mut text).unwrap();
+        let first = text.split("\n\n").next().unwrap();
+        assert_eq!("data: {\"type\":\"start\",\"sort\":\"BubbleSort\",\"unsorted_array\":[3,2,1]}", first);
+        assert!(text.ends_with("\"sorted\":true}\n\n"));
+    }
+}
@@ -0,0 +1,234 @@
+#![allow(dead_code)]
+
+use super::bubble_sort;
+use super::model::*;
+use super::persist::SavedRangeCall;
+use super::quicksort;
+
+use std::time::Duration;
+
+pub fn main() {
+    try_compare_runs();
+    try_golden_text();
+}
+
+// Puts a recording on a logical clock so that the same input gives the same ActionList every time.
+//
+// A RangeCall records Instant::now() for everything, so the times in an ActionList differ from one
+// run to the next and from one machine to the next. The order of the events on any one thread
+// doesn't change though, so the recording is made as usual and then each instant is replaced with a
+// tick. Each tick is LOGICAL_TICK long, which leaves room for the few nanoseconds ActionList puts
+// between a Release, a MoveToCall and a Take, and an animation of the result moves at one event per
+// tick however fast the sort ran.
+//
+// With StepCounter every event gets the next tick in the order it was recorded. That's reproducible
+// when the sort ran on one thread but not otherwise, since the order of events on different threads
+// is down to timing.
+//
+// With Lamport each thread keeps its own count. A call on a new thread starts from its parent's
+// count, the parent goes on from the same count, and the parent's next event after the child calls
+// waits for one past the latest of them. That only depends on the shape of the call tree, so a
+// multithreaded sort is reproducible too as long as it makes the same calls on the same kind of
+// thread each time, which vis::quicksort does. traced_slice decides is_new_thread by looking at the
+// OS thread, so its recordings may still differ.
+//
+// Either way the workers are left out, since which worker ran what is also down to timing.
+
+pub const LOGICAL_TICK: Duration = Duration::from_micros(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogicalClock {
+    StepCounter,
+    Lamport,
+}
+
+impl RangeCall {
+
+    pub fn with_logical_clock(&self, clock: LogicalClock) -> RangeCall {
+        let first_instant = self.start_instant();
+        let mut saved = self.to_saved(first_instant);
+        clear_workers(&mut saved);
+        match clock {
+            LogicalClock::StepCounter => step_counter(&mut saved),
+            LogicalClock::Lamport => { lamport(&mut saved, 0); },
+        }
+        for_each_nanos(&mut saved, &mut |nanos| *nanos *= LOGICAL_TICK.as_nanos() as u64);
+        RangeCall::from_saved(saved, first_instant)
+    }
+}
+
+impl ActionList {
+
+    pub fn from_range_call_logical(range_call: &RangeCall, label: Option<&str>, clock: LogicalClock) -> Self {
+        ActionList::from_range_call(&range_call.with_logical_clock(clock), label)
+    }
+
+    // One line per action, for comparing a recording against a known good one.
+    pub fn golden_text(&self) -> String {
+        self.actions.iter()
+            .map(|action| format!("{}; call = {}\n", action.description(), action.call_key))
+            .collect()
+    }
+}
+
+fn clear_workers(saved: &mut SavedRangeCall) {
+    saved.workers.clear();
+    saved.child_calls.iter_mut().for_each(clear_workers);
+}
+
+fn step_counter(saved: &mut SavedRangeCall) {
+    let mut recorded = vec![];
+    for_each_nanos(saved, &mut |nanos| recorded.push(*nanos));
+    // Ties keep the order in which for_each_nanos visits them so the ticks are the same each time.
+    let mut order: Vec<usize> = (0..recorded.len()).collect();
+    order.sort_by_key(|&index| (recorded[index], index));
+    let mut ticks = vec![0; recorded.len()];
+    for (tick, index) in order.into_iter().enumerate() {
+        ticks[index] = tick as u64;
+    }
+    let mut ticks = ticks.into_iter();
+    for_each_nanos(saved, &mut |nanos| *nanos = ticks.next().unwrap());
+}
+
+// Visits the start, the end, the swaps, the mark finals and the steps of a call and then those of its
+// child calls.
+fn for_each_nanos<F>(saved: &mut SavedRangeCall, f: &mut F)
+    where F: FnMut(&mut u64)
+{
+    f(&mut saved.start_nanos);
+    if let Some(end_nanos) = saved.end_nanos.as_mut() {
+        f(end_nanos);
+    }
+    saved.swaps.iter_mut().for_each(|swap| f(&mut swap.nanos));
+    saved.mark_finals.iter_mut().for_each(|mark_final| f(&mut mark_final.nanos));
+    saved.steps.iter_mut().for_each(|step| f(&mut step.nanos));
+    for child_call in saved.child_calls.iter_mut() {
+        for_each_nanos(child_call, f);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Event {
+    Swap(usize),
+    MarkFinal(usize),
+    Step(usize),
+}
+
+// Sets the ticks of a call that starts at the given tick and returns the tick after its end.
+//
+// The call's own events are all on its thread, so comparing their recorded times with the start of a
+// child call says reliably whether they came before it or after it. The child calls themselves are
+// taken in the order the call added them rather than by their start times, since two child calls on
+// different threads can start in either order.
+fn lamport(saved: &mut SavedRangeCall, start: u64) -> u64 {
+    let mut events: Vec<(u64, Event)> = vec![];
+    events.extend(saved.swaps.iter().enumerate().map(|(index, swap)| (swap.nanos, Event::Swap(index))));
+    events.extend(saved.mark_finals.iter().enumerate().map(|(index, mark_final)| (mark_final.nanos, Event::MarkFinal(index))));
+    events.extend(saved.steps.iter().enumerate().map(|(index, step)| (step.nanos, Event::Step(index))));
+    events.sort();
+
+    saved.start_nanos = start;
+    let mut tick = start + 1;
+    // The ticks after the ends of child calls on other threads that this thread hasn't waited for yet.
+    let mut pending: Vec<u64> = vec![];
+    let mut events = events.into_iter().peekable();
+    let mut child_calls = saved.child_calls.iter_mut().peekable();
+    loop {
+        let event_is_next = match (events.peek(), child_calls.peek()) {
+            (Some((nanos, _)), Some(child_call)) => *nanos < child_call.start_nanos,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        if event_is_next {
+            let (_, event) = events.next().unwrap();
+            tick = pending.drain(..).fold(tick, u64::max);
+            match event {
+                Event::Swap(index) => saved.swaps[index].nanos = tick,
+                Event::MarkFinal(index) => saved.mark_finals[index].nanos = tick,
+                Event::Step(index) => saved.steps[index].nanos = tick,
+            }
+            tick += 1;
+        } else {
+            let child_call = child_calls.next().unwrap();
+            let after_child_call = lamport(child_call, tick);
+            if child_call.is_new_thread {
+                pending.push(after_child_call);
+            } else {
+                tick = after_child_call;
+            }
+        }
+    }
+    tick = pending.drain(..).fold(tick, u64::max);
+    if let Some(end_nanos) = saved.end_nanos.as_mut() {
+        *end_nanos = tick;
+    }
+    tick + 1
+}
+
+fn try_compare_runs() {
+    let mut v: Vec<usize> = (0..200).map(|i| (i * 37) % 200).collect();
+    let range_call_a = quicksort::quicksort(&mut v.clone(), 20, 8);
+    let range_call_b = quicksort::quicksort(&mut v, 20, 8);
+    for clock in [LogicalClock::StepCounter, LogicalClock::Lamport].iter() {
+        let action_list_a = ActionList::from_range_call_logical(&range_call_a, None, *clock);
+        let action_list_b = ActionList::from_range_call_logical(&range_call_b, None, *clock);
+        let last_tick = action_list_a.actions.iter().map(|action| action.duration).max().unwrap_or_default();
+        println!("\n{:?}: {} actions, last tick = {}, runs match = {}",
+            clock, action_list_a.actions.len(), last_tick.as_nanos() / LOGICAL_TICK.as_nanos(),
+            action_list_a.to_saved() == action_list_b.to_saved());
+    }
+    let wall_a = ActionList::from_range_call(&range_call_a, None);
+    let wall_b = ActionList::from_range_call(&range_call_b, None);
+    println!("\nWall clock: runs match = {}", wall_a.to_saved() == wall_b.to_saved());
+}
+
+fn try_golden_text() {
+    let mut v = vec![3, 1, 2];
+    let range_call = bubble_sort::bubble_sort(&mut v, 0, "0");
+    print!("\n{}", ActionList::from_range_call_logical(&range_call, None, LogicalClock::StepCounter).golden_text());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(min_split_size: usize, max_threads: u8) -> RangeCall {
+        let mut v: Vec<usize> = (0..100).map(|i| (i * 37) % 100).collect();
+        let range_call = quicksort::quicksort(&mut v, min_split_size, max_threads);
+        assert_eq!((0..100).collect::<Vec<usize>>(), v);
+        range_call
+    }
+
+    #[test]
+    fn test_step_counter_single_thread_is_reproducible() {
+        let action_list_a = ActionList::from_range_call_logical(&record(10, 1), None, LogicalClock::StepCounter);
+        let action_list_b = ActionList::from_range_call_logical(&record(10, 1), None, LogicalClock::StepCounter);
+        assert_eq!(action_list_a.to_saved(), action_list_b.to_saved());
+    }
+
+    #[test]
+    fn test_lamport_multithreaded_is_reproducible() {
+        let action_list_a = ActionList::from_range_call_logical(&record(10, 8), None, LogicalClock::Lamport);
+        let action_list_b = ActionList::from_range_call_logical(&record(10, 8), None, LogicalClock::Lamport);
+        assert_eq!(action_list_a.to_saved(), action_list_b.to_saved());
+        assert!(action_list_a.actions.iter().any(|action| action.thread_number > 0));
+    }
+
+    #[test]
+    fn test_golden_text() {
+        let mut v = vec![3, 1, 2];
+        let range_call = bubble_sort::bubble_sort(&mut v, 0, "0");
+        let golden_text = ActionList::from_range_call_logical(&range_call, None, LogicalClock::StepCounter).golden_text();
+        assert_eq!(GOLDEN_BUBBLE_SORT, golden_text);
+    }
+
+    const GOLDEN_BUBBLE_SORT: &str = "\
+time = 0; thread = 0; BubbleSort; [0..3]; Take        ; call = 0
+time = 1,000; thread = 0; BubbleSort; [0..3]; Swap         (0, 1); call = 0
+time = 2,000; thread = 0; BubbleSort; [0..3]; Swap         (1, 2); call = 0
+time = 3,000; thread = 0; BubbleSort; [0..3]; MarkFinal         (2..3); call = 0
+time = 4,000; thread = 0; BubbleSort; [0..3]; MarkFinal         (0..2); call = 0
+time = 5,000; thread = 0; BubbleSort; [0..3]; Release     ; call = 0
+";
+}
@@ -1,4 +1,14 @@
+pub mod analysis;
 pub mod bubble_sort;
+pub mod chrome_trace;
+pub mod gen_html;
 pub mod gen_js;
+pub mod live;
+pub mod logical_clock;
 pub mod model;
+pub mod persist;
 pub mod quicksort;
+pub mod raster;
+pub mod replay;
+pub mod terminal;
+pub mod traced_slice;
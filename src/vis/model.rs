@@ -4,9 +4,12 @@ use std::fmt::{self, Debug};
 use std::time;
 use std::marker::Sync;
 use std::ops::{Deref, DerefMut, Range};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use crate::util::format;
-use crate::range;
+use crate::range::IntervalSet;
+use super::persist::{SavedRangeCall, SavedSwap, SavedMarkFinal, SavedStep, SavedWorkerChange};
+use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 use itertools::Itertools;
 // use std::iter::Filter;
@@ -16,11 +19,35 @@ use itertools::Itertools;
 pub fn main() {
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Actor {
     Quicksort,
     BubbleSort,
     Partition,
+    MergeSort,
+    InsertionSort,
+}
+
+// The thread that actually ran part of a call, as opposed to thread_number, which is worked out from
+// is_new_thread and so doesn't follow rayon's work stealing. rayon_index is the index of the rayon
+// worker if it was one. os_thread numbers the OS threads of the process in the order they first
+// recorded anything, since a ThreadId can't be turned into a number.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Worker {
+    pub rayon_index: Option<usize>,
+    pub os_thread: usize,
+}
+
+#[derive(Debug)]
+pub struct WorkerChange {
+    instant: time::Instant,
+    worker: Worker,
+}
+
+static NEXT_OS_THREAD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static OS_THREAD: usize = NEXT_OS_THREAD.fetch_add(1, Ordering::Relaxed);
 }
 
 #[derive(Debug)]
@@ -37,6 +64,14 @@ pub struct MarkFinal {
     to: usize,
 }
 
+// Any of the other kinds of action recorded by a call, such as a compare or a rotate. Offsets are
+// relative to the start of the call as with Swap and MarkFinal.
+#[derive(Debug)]
+pub struct Step {
+    instant: time::Instant,
+    action_type: ActionType,
+}
+
 #[derive(Debug)]
 pub struct RangeCall {
     key: String,
@@ -48,11 +83,15 @@ pub struct RangeCall {
     end_instant: Option<time::Instant>,
     swaps: Vec<Swap>,
     mark_finals: Vec<MarkFinal>,
+    steps: Vec<Step>,
     child_calls: Vec<RangeCall>,
     thread_number: u8,
+    // The worker at the start of the call, then the worker each time that changed. Only the call's
+    // own events count, not those of its child calls.
+    workers: Vec<WorkerChange>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ActionType {
     MoveToThread,
     MoveToCall {
@@ -70,12 +109,34 @@ pub enum ActionType {
         from: usize,
         to: usize,
     },
+    Compare {
+        a: usize,
+        b: usize,
+    },
+    // Copies the item at index into the auxiliary buffer. The buffer is laid out alongside the array
+    // so buffer_index is the position in the array that the buffer slot sits under.
+    WriteToBuffer {
+        index: usize,
+        buffer_index: usize,
+    },
+    CopyBack {
+        buffer_index: usize,
+        index: usize,
+    },
+    // Moves the last item of from..to to from and shifts the others up by one, as with
+    // <[T]>::rotate_right(1).
+    Rotate {
+        from: usize,
+        to: usize,
+    },
 }
 
 #[derive(Clone)]
 pub struct Action {
     pub duration: time::Duration,
     pub thread_number: u8,
+    // None if the RangeCall didn't record its workers, as when it was built from a trace by hand.
+    pub worker: Option<Worker>,
     // pub run_number: Option<u8>,
     pub call_key: String,
     pub actor: Actor,
@@ -91,25 +152,86 @@ pub struct ActionList {
     pub unsorted_array: Option<Vec<usize>>,
 }
 
+impl Worker {
+
+    pub fn current() -> Self {
+        Worker {
+            rayon_index: rayon::current_thread_index(),
+            os_thread: OS_THREAD.with(|os_thread| *os_thread),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self.rayon_index {
+            Some(rayon_index) => format!("rayon worker {} (OS thread {})", rayon_index, self.os_thread),
+            None => format!("OS thread {}", self.os_thread),
+        }
+    }
+}
+
 impl RangeCall {
     pub fn new(key: &str, actor: Actor, from: usize, len: usize, is_new_thread: bool) -> Self {
+        let start_instant = time::Instant::now();
+        let mut range_call = Self::new_at(key, actor, from, len, is_new_thread, start_instant);
+        range_call.worker_at(Worker::current(), start_instant);
+        range_call
+    }
+
+    // The *_at() functions are for building a RangeCall after the fact from a recorded trace.
+    pub fn new_at(key: &str, actor: Actor, from: usize, len: usize, is_new_thread: bool, start_instant: time::Instant) -> Self {
         Self {
             key: key.to_string(),
             actor,
             from,
             len,
             is_new_thread,
-            start_instant: time::Instant::now(),
+            start_instant,
             end_instant: None,
             swaps: vec![],
             mark_finals: vec![],
+            steps: vec![],
             child_calls: vec![],
             thread_number: 0,
+            workers: vec![],
+        }
+    }
+
+    // Notes the worker running the call at the given instant if it's not the one it was already on.
+    // The functions without _at do this for themselves.
+    pub fn worker_at(&mut self, worker: Worker, instant: time::Instant) {
+        if self.workers.last().map_or(true, |change| change.worker != worker) {
+            self.workers.push(WorkerChange { instant, worker });
         }
     }
 
+    fn now_on_current_worker(&mut self) -> time::Instant {
+        let instant = time::Instant::now();
+        self.worker_at(Worker::current(), instant);
+        instant
+    }
+
+    // The worker that was running the call at the given instant, or the first one if it's before the
+    // start.
+    pub fn worker_at_instant(&self, instant: time::Instant) -> Option<Worker> {
+        self.workers.iter()
+            .take_while(|change| change.instant <= instant)
+            .last()
+            .or(self.workers.first())
+            .map(|change| change.worker)
+    }
+
+    // The number of times the call moved to another worker.
+    pub fn migration_count(&self) -> usize {
+        self.workers.len().saturating_sub(1)
+    }
+
     pub fn end(&mut self) {
-        self.end_instant = Some(time::Instant::now());
+        let instant = self.now_on_current_worker();
+        self.end_at(instant);
+    }
+
+    pub fn end_at(&mut self, end_instant: time::Instant) {
+        self.end_instant = Some(end_instant);
     }
 
     pub fn add_child_call(&mut self, child_call: RangeCall) {
@@ -122,11 +244,16 @@ impl RangeCall {
     }
 
     pub fn swap(&mut self, a: usize, b: usize) {
+        let instant = self.now_on_current_worker();
+        self.swap_at(a, b, instant);
+    }
+
+    pub fn swap_at(&mut self, a: usize, b: usize, instant: time::Instant) {
         // These are relative offsets. We'll work out the absolute offsets later.
         debug_assert!(a != b, "a == b == {}", a);
         debug_assert!(a < self.len, "len = {}; a = {}", self.len, a);
         debug_assert!(b < self.len, "len = {}; b = {}", self.len, b);
-        self.swaps.push(Swap { instant: time::Instant::now(), a, b });
+        self.swaps.push(Swap { instant, a, b });
     }
 
     pub fn mark_final_one(&mut self, index: usize) {
@@ -134,10 +261,55 @@ impl RangeCall {
     }
 
     pub fn mark_final(&mut self, from: usize, to: usize) {
+        let instant = self.now_on_current_worker();
+        self.mark_final_at(from, to, instant);
+    }
+
+    pub fn mark_final_at(&mut self, from: usize, to: usize, instant: time::Instant) {
         debug_assert!(from < to);
         debug_assert!(from < self.len);
         debug_assert!(to <= self.len);
-        self.mark_finals.push(MarkFinal { instant: time::Instant::now(), from, to });
+        self.mark_finals.push(MarkFinal { instant, from, to });
+    }
+
+    pub fn compare(&mut self, a: usize, b: usize) {
+        let instant = self.now_on_current_worker();
+        self.compare_at(a, b, instant);
+    }
+
+    pub fn compare_at(&mut self, a: usize, b: usize, instant: time::Instant) {
+        debug_assert!(a < self.len && b < self.len);
+        self.steps.push(Step { instant, action_type: ActionType::Compare { a, b } });
+    }
+
+    pub fn write_to_buffer(&mut self, index: usize, buffer_index: usize) {
+        let instant = self.now_on_current_worker();
+        self.write_to_buffer_at(index, buffer_index, instant);
+    }
+
+    pub fn write_to_buffer_at(&mut self, index: usize, buffer_index: usize, instant: time::Instant) {
+        debug_assert!(index < self.len && buffer_index < self.len);
+        self.steps.push(Step { instant, action_type: ActionType::WriteToBuffer { index, buffer_index } });
+    }
+
+    pub fn copy_back(&mut self, buffer_index: usize, index: usize) {
+        let instant = self.now_on_current_worker();
+        self.copy_back_at(buffer_index, index, instant);
+    }
+
+    pub fn copy_back_at(&mut self, buffer_index: usize, index: usize, instant: time::Instant) {
+        debug_assert!(index < self.len && buffer_index < self.len);
+        self.steps.push(Step { instant, action_type: ActionType::CopyBack { buffer_index, index } });
+    }
+
+    pub fn rotate(&mut self, from: usize, to: usize) {
+        let instant = self.now_on_current_worker();
+        self.rotate_at(from, to, instant);
+    }
+
+    pub fn rotate_at(&mut self, from: usize, to: usize, instant: time::Instant) {
+        debug_assert!(from < to && to <= self.len);
+        self.steps.push(Step { instant, action_type: ActionType::Rotate { from, to } });
     }
 
     fn to(&self) -> usize {
@@ -230,6 +402,53 @@ impl RangeCall {
     }
 }
 
+// The conversions for vis::persist, which can't see the fields. Every instant is saved as
+// nanoseconds since first_instant, which is normally the start of the outermost call.
+impl RangeCall {
+
+    pub fn start_instant(&self) -> time::Instant {
+        self.start_instant
+    }
+
+    pub fn to_saved(&self, first_instant: time::Instant) -> SavedRangeCall {
+        let nanos_since = |instant: time::Instant| instant.duration_since(first_instant).as_nanos() as u64;
+        SavedRangeCall {
+            key: self.key.clone(),
+            actor: self.actor,
+            from: self.from,
+            len: self.len,
+            is_new_thread: self.is_new_thread,
+            start_nanos: nanos_since(self.start_instant),
+            end_nanos: self.end_instant.map(nanos_since),
+            swaps: self.swaps.iter().map(|swap| SavedSwap { nanos: nanos_since(swap.instant), a: swap.a, b: swap.b }).collect(),
+            mark_finals: self.mark_finals.iter().map(|mark_final| SavedMarkFinal { nanos: nanos_since(mark_final.instant), from: mark_final.from, to: mark_final.to }).collect(),
+            steps: self.steps.iter().map(|step| SavedStep { nanos: nanos_since(step.instant), action_type: step.action_type.clone() }).collect(),
+            child_calls: self.child_calls.iter().map(|child_call| child_call.to_saved(first_instant)).collect(),
+            thread_number: self.thread_number,
+            workers: self.workers.iter().map(|change| SavedWorkerChange { nanos: nanos_since(change.instant), worker: change.worker }).collect(),
+        }
+    }
+
+    pub fn from_saved(saved: SavedRangeCall, first_instant: time::Instant) -> Self {
+        let instant_at = |nanos: u64| first_instant + time::Duration::from_nanos(nanos);
+        RangeCall {
+            key: saved.key,
+            actor: saved.actor,
+            from: saved.from,
+            len: saved.len,
+            is_new_thread: saved.is_new_thread,
+            start_instant: instant_at(saved.start_nanos),
+            end_instant: saved.end_nanos.map(instant_at),
+            swaps: saved.swaps.into_iter().map(|swap| Swap { instant: instant_at(swap.nanos), a: swap.a, b: swap.b }).collect(),
+            mark_finals: saved.mark_finals.into_iter().map(|mark_final| MarkFinal { instant: instant_at(mark_final.nanos), from: mark_final.from, to: mark_final.to }).collect(),
+            steps: saved.steps.into_iter().map(|step| Step { instant: instant_at(step.nanos), action_type: step.action_type }).collect(),
+            child_calls: saved.child_calls.into_iter().map(|child_call| RangeCall::from_saved(child_call, first_instant)).collect(),
+            thread_number: saved.thread_number,
+            workers: saved.workers.into_iter().map(|change| WorkerChange { instant: instant_at(change.nanos), worker: change.worker }).collect(),
+        }
+    }
+}
+
 impl ActionType {
 
     pub fn move_to_thread() -> Self { ActionType::MoveToThread }
@@ -248,6 +467,20 @@ impl ActionType {
         ActionType::MarkFinal { from, to }
     }
 
+    // The same action with every index moved up by offset, to go from a call's relative indexes to
+    // absolute ones.
+    pub fn offset(&self, offset: usize) -> Self {
+        match self.clone() {
+            ActionType::Swap { a, b } => ActionType::Swap { a: a + offset, b: b + offset },
+            ActionType::MarkFinal { from, to } => ActionType::MarkFinal { from: from + offset, to: to + offset },
+            ActionType::Compare { a, b } => ActionType::Compare { a: a + offset, b: b + offset },
+            ActionType::WriteToBuffer { index, buffer_index } => ActionType::WriteToBuffer { index: index + offset, buffer_index: buffer_index + offset },
+            ActionType::CopyBack { buffer_index, index } => ActionType::CopyBack { buffer_index: buffer_index + offset, index: index + offset },
+            ActionType::Rotate { from, to } => ActionType::Rotate { from: from + offset, to: to + offset },
+            action_type => action_type,
+        }
+    }
+
     pub fn description_width(&self, range_width: usize) -> String {
         match self {
             ActionType::MoveToThread => "MoveToThread".to_string(),
@@ -256,6 +489,10 @@ impl ActionType {
             ActionType::Release => "Release     ".to_string(),
             ActionType::Swap { a, b } => format!("Swap         ({:>width$}, {:>width$})", util::format::format_count(*a), util::format::format_count(*b), width = range_width),
             ActionType::MarkFinal { from, to } => format!("MarkFinal   {}({:>width$}..{:>width$})", " ".repeat((2 * range_width) + 6), util::format::format_count(*from), util::format::format_count(*to), width = range_width),
+            ActionType::Compare { a, b } => format!("Compare      ({:>width$}, {:>width$})", util::format::format_count(*a), util::format::format_count(*b), width = range_width),
+            ActionType::WriteToBuffer { index, buffer_index } => format!("ToBuffer     ({:>width$} -> buffer {:>width$})", util::format::format_count(*index), util::format::format_count(*buffer_index), width = range_width),
+            ActionType::CopyBack { buffer_index, index } => format!("CopyBack     (buffer {:>width$} -> {:>width$})", util::format::format_count(*buffer_index), util::format::format_count(*index), width = range_width),
+            ActionType::Rotate { from, to } => format!("Rotate      {}({:>width$}..{:>width$})", " ".repeat((2 * range_width) + 6), util::format::format_count(*from), util::format::format_count(*to), width = range_width),
         }
     }
 
@@ -272,21 +509,23 @@ impl fmt::Debug for ActionType {
 
 impl Action {
 
-    fn new(duration: time::Duration, thread_number: u8, range_call: &RangeCall, action_type: ActionType) -> Self {
-        Self::new_detail(duration, thread_number, &range_call.key, range_call.actor, range_call.from, range_call.to(), action_type)
+    fn new(duration: time::Duration, thread_number: u8, worker: Option<Worker>, range_call: &RangeCall, action_type: ActionType) -> Self {
+        Self::new_detail(duration, thread_number, worker, &range_call.key, range_call.actor, range_call.from, range_call.to(), action_type)
     }
 
-    fn new_detail(duration: time::Duration, thread_number: u8, call_key: &str, actor: Actor, from: usize, to: usize, action_type: ActionType) -> Self {
+    fn new_detail(duration: time::Duration, thread_number: u8, worker: Option<Worker>, call_key: &str, actor: Actor, from: usize, to: usize, action_type: ActionType) -> Self {
         debug_assert!(
             match action_type {
                 ActionType::Swap { a, b } => a >= from && a < to && b >= from && b < to,
                 ActionType::MarkFinal { from: final_from, to: final_to } => final_from >= from && final_from < to && final_to > from && final_to <= to,
+                ActionType::Rotate { from: rotate_from, to: rotate_to } => rotate_from >= from && rotate_to <= to,
                 _ => true,
             }
         );
         Self {
             duration,
             thread_number,
+            worker,
             call_key: call_key.to_string(),
             actor,
             from,
@@ -301,26 +540,33 @@ impl Action {
         let end_duration = range_call.end_instant.unwrap().duration_since(first_instant);
 
         let parent_thread_number = thread_number;
+        let start_worker = range_call.worker_at_instant(range_call.start_instant);
+        let end_worker = range_call.worker_at_instant(range_call.end_instant.unwrap());
 
         if range_call.is_new_thread {
             let range_call_key = range_call.key.clone();
             thread_number = Self::key_to_thread_number(thread_starters, range_call_key);
             let thread_duration = just_before(&start_duration);
-            action_list.push(Action::new(thread_duration, thread_number, range_call, ActionType::move_to_thread()));
+            action_list.push(Action::new(thread_duration, thread_number, start_worker, range_call, ActionType::move_to_thread()));
         }
 
-        action_list.push(Action::new(start_duration, thread_number, range_call,ActionType::take()));
+        action_list.push(Action::new(start_duration, thread_number, start_worker, range_call, ActionType::take()));
 
         for swap in range_call.swaps.iter() {
             let swap_duration = swap.instant.duration_since(first_instant);
             let action_type = ActionType::swap(range_call.from + swap.a, range_call.from + swap.b);
-            action_list.push(Action::new(swap_duration, thread_number, range_call, action_type));
+            action_list.push(Action::new(swap_duration, thread_number, range_call.worker_at_instant(swap.instant), range_call, action_type));
         }
 
         for mark_final in range_call.mark_finals.iter() {
             let mark_final_duration = mark_final.instant.duration_since(first_instant);
             let action_type = ActionType::mark_final(range_call.from + mark_final.from, range_call.from + mark_final.to);
-            action_list.push(Action::new(mark_final_duration, thread_number, range_call, action_type));
+            action_list.push(Action::new(mark_final_duration, thread_number, range_call.worker_at_instant(mark_final.instant), range_call, action_type));
+        }
+
+        for step in range_call.steps.iter() {
+            let step_duration = step.instant.duration_since(first_instant);
+            action_list.push(Action::new(step_duration, thread_number, range_call.worker_at_instant(step.instant), range_call, step.action_type.offset(range_call.from)));
         }
 
         if range_call.child_calls.len() > 0 {
@@ -330,31 +576,33 @@ impl Action {
             let latest_end_instant = range_call.child_calls.iter().map(|x| x.end_instant.unwrap()).max().unwrap();
             let after_latest_end_duration = just_after(&just_after(&latest_end_instant.duration_since(first_instant)));
 
-            action_list.push(Action::new(before_earliest_start_duration, thread_number, range_call, ActionType::Release));
+            action_list.push(Action::new(before_earliest_start_duration, thread_number, range_call.worker_at_instant(earliest_start_instant), range_call, ActionType::Release));
 
             for child_call in range_call.child_calls.iter() {
 
                 let before_call_duration = just_before(&child_call.start_instant.duration_since(first_instant));
+                let before_call_worker = range_call.worker_at_instant(child_call.start_instant);
                 let move_to_call_action_type = ActionType::MoveToCall { from_call_key: range_call.key.clone(), to_call_key: child_call.key.clone(), ranges: vec![] };
-                action_list.push(Action::new_detail(before_call_duration, thread_number, &range_call.key, range_call.actor, child_call.from, child_call.to(), move_to_call_action_type));
+                action_list.push(Action::new_detail(before_call_duration, thread_number, before_call_worker, &range_call.key, range_call.actor, child_call.from, child_call.to(), move_to_call_action_type));
 
                 Action::add_actions_from_range_call(action_list, child_call, first_instant.clone(), thread_starters, thread_number);
 
                 let after_call_duration = just_after(&child_call.end_instant.unwrap().duration_since(first_instant));
+                let after_call_worker = range_call.worker_at_instant(child_call.end_instant.unwrap());
                 let move_to_call_action_type = ActionType::MoveToCall { from_call_key: child_call.key.clone(), to_call_key: range_call.key.clone(), ranges: vec![] };
-                action_list.push(Action::new_detail(after_call_duration, thread_number, &range_call.key, range_call.actor, child_call.from, child_call.to(), move_to_call_action_type));
+                action_list.push(Action::new_detail(after_call_duration, thread_number, after_call_worker, &range_call.key, range_call.actor, child_call.from, child_call.to(), move_to_call_action_type));
 
             }
 
-            action_list.push(Action::new(after_latest_end_duration, thread_number, range_call, ActionType::Take));
+            action_list.push(Action::new(after_latest_end_duration, thread_number, range_call.worker_at_instant(latest_end_instant), range_call, ActionType::Take));
         }
 
-        action_list.push(Action::new(end_duration, thread_number, range_call, ActionType::Release));
+        action_list.push(Action::new(end_duration, thread_number, end_worker, range_call, ActionType::Release));
 
         if range_call.is_new_thread {
             // Return this range to the parent thread.
             let thread_duration = just_after(&end_duration);
-            action_list.push(Action::new(thread_duration, parent_thread_number, range_call, ActionType::move_to_thread()));
+            action_list.push(Action::new(thread_duration, parent_thread_number, end_worker, range_call, ActionType::move_to_thread()));
         }
 
     }
@@ -375,7 +623,10 @@ impl Action {
         let actor = format!( "{:<width$}", format!("{:?}", self.actor), width = actor_width);
         let range = format!("[{:>width$}..{:>width$}]", util::format::format_count(self.from), util::format::format_count(self.to), width = range_width);
         let action_type = self.action_type.description_width(range_width);
-        format!("{}; {}; {}; {}; {}", duration, thread, actor, range, action_type)
+        match self.worker {
+            Some(worker) => format!("{}; {}; {}; {}; {}; on {}", duration, thread, actor, range, action_type, worker.description()),
+            None => format!("{}; {}; {}; {}; {}", duration, thread, actor, range, action_type),
+        }
     }
 
     pub fn description(&self) -> String {
@@ -434,9 +685,8 @@ impl ActionList {
     fn resolve_call_ranges(&mut self, full_range_end: usize) {
         let mut map = HashMap::new();
 
-        let mut first_call = range::RangeSet::new(true, true, Some(0..full_range_end));
-        first_call.add_range(&(0..full_range_end));
-        debug_assert!(first_call.check_ranges(false, Some("first_call"), None));
+        let mut first_call = IntervalSet::new(true, Some(0..full_range_end));
+        first_call.insert(0..full_range_end).unwrap();
         map.insert("0".to_string(), first_call);
 
         for action in self.iter_mut().sorted_by_key(|action| action.duration) {
@@ -444,29 +694,27 @@ impl ActionList {
                 ActionType::MoveToCall { from_call_key, to_call_key, ranges: action_ranges } => {
                     {
                         let from_call = map.get_mut(from_call_key).unwrap();
-                        from_call.subtract_range(&(action.from..action.to));
-                        debug_assert!(from_call.check_ranges(false, Some("from_call"), None));
+                        from_call.remove(action.from..action.to)
+                            .unwrap_or_else(|error| panic!("Moving out of call \"{}\": {}", from_call_key, error));
                     }
 
                     if let Some(to_call) = map.get_mut(to_call_key) {
-                        to_call.add_range(&(action.from..action.to));
-                        debug_assert!(to_call.check_ranges(false, Some("to_call"), None));
+                        to_call.insert(action.from..action.to)
+                            .unwrap_or_else(|error| panic!("Moving into call \"{}\": {}", to_call_key, error));
                     } else {
-                        let mut new_call = range::RangeSet::new(true, true, Some(action.from..action.to));
-                        new_call.add_range(&(action.from..action.to));
-                        debug_assert!(new_call.check_ranges(false, Some("new_call"), None));
+                        let mut new_call = IntervalSet::new(true, Some(action.from..action.to));
+                        new_call.insert(action.from..action.to).unwrap();
                         map.insert(to_call_key.clone(), new_call);
                     }
 
-                    let mut combined_ranges = range::RangeSet::new(true, false, Some(0..full_range_end));
-                    for range in map.values().map(|range_set| range_set.ranges_in_order()).flatten() {
-                        combined_ranges.add_range(&(range.start..range.end));
-                    }
-                    combined_ranges.allow_gaps = false;
-                    debug_assert!(combined_ranges.check_ranges(false, Some("combined_ranges"), None));
-                    for range in combined_ranges.ranges_in_order() {
-                        action_ranges.push(range.start..range.end);
+                    // Keep the ranges of different calls apart even where they touch.
+                    let mut combined_ranges = IntervalSet::new(false, Some(0..full_range_end));
+                    for range in map.values().map(|interval_set| interval_set.iter()).flatten() {
+                        combined_ranges.insert(range)
+                            .unwrap_or_else(|error| panic!("Combining the ranges of all calls: {}", error));
                     }
+                    debug_assert!(combined_ranges.is_complete(), "Gaps in the combined ranges: {:?}", combined_ranges.gaps());
+                    action_ranges.extend(combined_ranges.iter());
                 },
                 _ => (),
             }
@@ -482,6 +730,11 @@ impl ActionList {
         self.display_internal("Actions by Thread", include_detail_actions, |x|  (x.thread_number, x.duration), |_x| 1);
     }
 
+    // Actions without a worker come first.
+    pub fn display_by_worker(&self, include_detail_actions: bool) {
+        self.display_internal("Actions by Worker", include_detail_actions, |x| (x.worker, x.duration), |_x| 1);
+    }
+
     pub fn display_by_range(&self, include_detail_actions: bool) {
         self.display_internal("Actions by Range", include_detail_actions, |x| (x.from, 0 - x.to as isize, x.duration), |_x| 1);
     }
@@ -520,6 +773,10 @@ impl ActionList {
         let mut release = 0;
         let mut swap = 0;
         let mut mark_final = 0;
+        let mut compare = 0;
+        let mut write_to_buffer = 0;
+        let mut copy_back = 0;
+        let mut rotate = 0;
         for action in self.actions.iter() {
             match action.action_type {
                 ActionType::MoveToThread => { move_to_thread += 1; },
@@ -528,6 +785,10 @@ impl ActionList {
                 ActionType::Release => { release += 1; },
                 ActionType::Swap { a: _, b: _ } => { swap += 1; },
                 ActionType::MarkFinal { from: _ , to: _ } => { mark_final += 1; },
+                ActionType::Compare { .. } => { compare += 1; },
+                ActionType::WriteToBuffer { .. } => { write_to_buffer += 1; },
+                ActionType::CopyBack { .. } => { copy_back += 1; },
+                ActionType::Rotate { .. } => { rotate += 1; },
             }
         }
         println!("\nmove to thread = {}", format::format_count(move_to_thread));
@@ -535,7 +796,11 @@ impl ActionList {
         println!("take = {}", format::format_count(take));
         println!("release = {}", format::format_count(release));
         println!("swap = {}", format::format_count(swap));
-        println!("mark_final = {}\n", format::format_count(mark_final));
+        println!("mark_final = {}", format::format_count(mark_final));
+        println!("compare = {}", format::format_count(compare));
+        println!("write_to_buffer = {}", format::format_count(write_to_buffer));
+        println!("copy_back = {}", format::format_count(copy_back));
+        println!("rotate = {}\n", format::format_count(rotate));
     }
 
 }
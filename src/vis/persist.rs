@@ -0,0 +1,729 @@
+#![allow(dead_code)]
+
+use super::model::*;
+use super::gen_html;
+use super::quicksort;
+use super::traced_slice;
+use crate::sort::test_data;
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub fn main() {
+    try_save_and_load();
+}
+
+// Saving and loading ActionLists and RangeCalls so a run can be kept and looked at again without
+// rerunning the sort. As with counter::persist, instants become nanoseconds: an ActionList's
+// durations are already relative to the start of the run, and a RangeCall's instants are saved
+// relative to the start of the outermost call. Loading a RangeCall counts them from the moment of
+// loading, which keeps every duration and ordering the same as in the original run.
+//
+// There are two formats: JSON through serde, and a compact binary format for large runs. Both go
+// through the same Saved* types. Persist::load() tells them apart by the binary format's header.
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedActionList {
+    pub label: Option<String>,
+    pub unsorted_array: Option<Vec<usize>>,
+    pub actions: Vec<SavedAction>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedAction {
+    pub nanos: u64,
+    pub thread_number: u8,
+    pub call_key: String,
+    pub actor: Actor,
+    pub from: usize,
+    pub to: usize,
+    pub action_type: ActionType,
+    // Missing from files saved before workers were recorded.
+    #[serde(default)]
+    pub worker: Option<Worker>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedRangeCall {
+    pub key: String,
+    pub actor: Actor,
+    pub from: usize,
+    pub len: usize,
+    pub is_new_thread: bool,
+    pub start_nanos: u64,
+    pub end_nanos: Option<u64>,
+    pub swaps: Vec<SavedSwap>,
+    pub mark_finals: Vec<SavedMarkFinal>,
+    pub steps: Vec<SavedStep>,
+    pub child_calls: Vec<SavedRangeCall>,
+    pub thread_number: u8,
+    #[serde(default)]
+    pub workers: Vec<SavedWorkerChange>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSwap {
+    pub nanos: u64,
+    pub a: usize,
+    pub b: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedMarkFinal {
+    pub nanos: u64,
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedStep {
+    pub nanos: u64,
+    pub action_type: ActionType,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedWorkerChange {
+    pub nanos: u64,
+    pub worker: Worker,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BinaryError {
+    UnexpectedEnd,
+    BadMagic,
+    UnsupportedVersion(u8),
+    WrongKind { expected: u8, found: u8 },
+    BadTag { what: &'static str, tag: u8 },
+    BadString,
+    VarintTooLong,
+    TrailingBytes(usize),
+}
+
+// The binary format starts with the magic bytes, the version and a byte for the kind of thing saved.
+// After that every integer is an unsigned LEB128 varint. Strings are a length and UTF-8 bytes, and
+// options and enums start with a tag byte.
+//
+// Version 2 added the workers. Version 1 files can still be read and come back without them.
+const BINARY_MAGIC: &[u8; 4] = b"AVIS";
+const BINARY_VERSION: u8 = 2;
+const BINARY_VERSION_WITHOUT_WORKERS: u8 = 1;
+const KIND_ACTION_LIST: u8 = 1;
+const KIND_RANGE_CALL: u8 = 2;
+
+// The save and load functions shared by ActionList and RangeCall.
+pub trait Persist: Serialize + for<'de> Deserialize<'de> + Sized {
+    const BINARY_KIND: u8;
+
+    fn write_binary(&self, writer: &mut BinaryWriter);
+
+    fn read_binary(reader: &mut BinaryReader) -> Result<Self, BinaryError>;
+
+    fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    fn to_binary(&self) -> Vec<u8> {
+        let mut writer = BinaryWriter::new();
+        writer.bytes.extend_from_slice(BINARY_MAGIC);
+        writer.u8(BINARY_VERSION);
+        writer.u8(Self::BINARY_KIND);
+        self.write_binary(&mut writer);
+        writer.bytes
+    }
+
+    fn from_binary(bytes: &[u8]) -> Result<Self, BinaryError> {
+        let mut reader = BinaryReader::new(bytes);
+        if reader.bytes(BINARY_MAGIC.len())? != BINARY_MAGIC {
+            return Err(BinaryError::BadMagic);
+        }
+        let version = reader.u8()?;
+        if version != BINARY_VERSION && version != BINARY_VERSION_WITHOUT_WORKERS {
+            return Err(BinaryError::UnsupportedVersion(version));
+        }
+        reader.version = version;
+        let kind = reader.u8()?;
+        if kind != Self::BINARY_KIND {
+            return Err(BinaryError::WrongKind { expected: Self::BINARY_KIND, found: kind });
+        }
+        let value = Self::read_binary(&mut reader)?;
+        if reader.remaining() > 0 {
+            return Err(BinaryError::TrailingBytes(reader.remaining()));
+        }
+        Ok(value)
+    }
+
+    fn save_json<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    fn save_binary<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_binary())
+    }
+
+    fn load_json<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Self::from_json(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn load_binary<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        Self::from_binary(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    // Either format.
+    fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        if bytes.starts_with(BINARY_MAGIC) {
+            Self::from_binary(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        } else {
+            serde_json::from_slice(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+    }
+}
+
+impl ActionList {
+
+    pub fn to_saved(&self) -> SavedActionList {
+        SavedActionList {
+            label: self.label.clone(),
+            unsorted_array: self.unsorted_array.clone(),
+            actions: self.actions.iter()
+                .map(|action| SavedAction {
+                    nanos: action.duration.as_nanos() as u64,
+                    thread_number: action.thread_number,
+                    call_key: action.call_key.clone(),
+                    actor: action.actor,
+                    from: action.from,
+                    to: action.to,
+                    action_type: action.action_type.clone(),
+                    worker: action.worker,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn from_saved(saved: SavedActionList) -> Self {
+        ActionList {
+            label: saved.label,
+            unsorted_array: saved.unsorted_array,
+            actions: saved.actions.into_iter()
+                .map(|action| Action {
+                    duration: Duration::from_nanos(action.nanos),
+                    thread_number: action.thread_number,
+                    worker: action.worker,
+                    call_key: action.call_key,
+                    actor: action.actor,
+                    from: action.from,
+                    to: action.to,
+                    action_type: action.action_type,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Serialize for ActionList {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_saved().serialize(serializer)
+    }
+}
+
+impl <'de> Deserialize<'de> for ActionList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SavedActionList::deserialize(deserializer).map(ActionList::from_saved)
+    }
+}
+
+impl Serialize for RangeCall {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_saved(self.start_instant()).serialize(serializer)
+    }
+}
+
+impl <'de> Deserialize<'de> for RangeCall {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SavedRangeCall::deserialize(deserializer).map(|saved| RangeCall::from_saved(saved, Instant::now()))
+    }
+}
+
+// Actions are mostly in time order so each duration is saved as the signed difference from the one
+// before. Call keys repeat for every action of a call so they're saved once in a table and each
+// action refers to one by its position.
+impl Persist for ActionList {
+    const BINARY_KIND: u8 = KIND_ACTION_LIST;
+
+    fn write_binary(&self, writer: &mut BinaryWriter) {
+        let saved = self.to_saved();
+        writer.option(&saved.label, |writer, label| writer.string(label));
+        writer.option(&saved.unsorted_array, |writer, unsorted_array| {
+            writer.usize(unsorted_array.len());
+            unsorted_array.iter().for_each(|value| writer.usize(*value));
+        });
+        let mut call_keys: Vec<&str> = saved.actions.iter().map(|action| action.call_key.as_str()).collect();
+        call_keys.sort();
+        call_keys.dedup();
+        writer.usize(call_keys.len());
+        call_keys.iter().for_each(|call_key| writer.string(call_key));
+        writer.usize(saved.actions.len());
+        let mut previous_nanos = 0;
+        for action in saved.actions.iter() {
+            writer.i64(action.nanos as i64 - previous_nanos as i64);
+            previous_nanos = action.nanos;
+            writer.u8(action.thread_number);
+            writer.usize(call_keys.binary_search(&action.call_key.as_str()).unwrap());
+            writer.actor(action.actor);
+            writer.usize(action.from);
+            writer.usize(action.to);
+            writer.action_type(&action.action_type);
+            writer.option(&action.worker, |writer, worker| writer.worker(worker));
+        }
+    }
+
+    fn read_binary(reader: &mut BinaryReader) -> Result<Self, BinaryError> {
+        let label = reader.option(|reader| reader.string())?;
+        let unsorted_array = reader.option(|reader| {
+            let len = reader.usize()?;
+            (0..len).map(|_| reader.usize()).collect::<Result<Vec<usize>, BinaryError>>()
+        })?;
+        let call_key_count = reader.usize()?;
+        let call_keys = (0..call_key_count).map(|_| reader.string()).collect::<Result<Vec<String>, BinaryError>>()?;
+        let action_count = reader.usize()?;
+        let mut actions = Vec::with_capacity(action_count.min(reader.remaining()));
+        let mut previous_nanos = 0;
+        for _ in 0..action_count {
+            let nanos = (previous_nanos as i64 + reader.i64()?) as u64;
+            previous_nanos = nanos;
+            let thread_number = reader.u8()?;
+            let call_key_index = reader.usize()?;
+            let call_key = call_keys.get(call_key_index).ok_or(BinaryError::BadTag { what: "call key", tag: call_key_index.min(255) as u8 })?.clone();
+            actions.push(SavedAction {
+                nanos,
+                thread_number,
+                call_key,
+                actor: reader.actor()?,
+                from: reader.usize()?,
+                to: reader.usize()?,
+                action_type: reader.action_type()?,
+                worker: if reader.has_workers() { reader.option(|reader| reader.worker())? } else { None },
+            });
+        }
+        Ok(ActionList::from_saved(SavedActionList { label, unsorted_array, actions }))
+    }
+}
+
+impl Persist for RangeCall {
+    const BINARY_KIND: u8 = KIND_RANGE_CALL;
+
+    fn write_binary(&self, writer: &mut BinaryWriter) {
+        write_saved_range_call(writer, &self.to_saved(self.start_instant()));
+    }
+
+    fn read_binary(reader: &mut BinaryReader) -> Result<Self, BinaryError> {
+        Ok(RangeCall::from_saved(read_saved_range_call(reader)?, Instant::now()))
+    }
+}
+
+fn write_saved_range_call(writer: &mut BinaryWriter, saved: &SavedRangeCall) {
+    writer.string(&saved.key);
+    writer.actor(saved.actor);
+    writer.usize(saved.from);
+    writer.usize(saved.len);
+    writer.bool(saved.is_new_thread);
+    writer.u64(saved.start_nanos);
+    writer.option(&saved.end_nanos, |writer, end_nanos| writer.u64(*end_nanos));
+    writer.u8(saved.thread_number);
+    writer.usize(saved.swaps.len());
+    for swap in saved.swaps.iter() {
+        writer.u64(swap.nanos);
+        writer.usize(swap.a);
+        writer.usize(swap.b);
+    }
+    writer.usize(saved.mark_finals.len());
+    for mark_final in saved.mark_finals.iter() {
+        writer.u64(mark_final.nanos);
+        writer.usize(mark_final.from);
+        writer.usize(mark_final.to);
+    }
+    writer.usize(saved.steps.len());
+    for step in saved.steps.iter() {
+        writer.u64(step.nanos);
+        writer.action_type(&step.action_type);
+    }
+    writer.usize(saved.child_calls.len());
+    for child_call in saved.child_calls.iter() {
+        write_saved_range_call(writer, child_call);
+    }
+    writer.usize(saved.workers.len());
+    for change in saved.workers.iter() {
+        writer.u64(change.nanos);
+        writer.worker(&change.worker);
+    }
+}
+
+fn read_saved_range_call(reader: &mut BinaryReader) -> Result<SavedRangeCall, BinaryError> {
+    let key = reader.string()?;
+    let actor = reader.actor()?;
+    let from = reader.usize()?;
+    let len = reader.usize()?;
+    let is_new_thread = reader.bool()?;
+    let start_nanos = reader.u64()?;
+    let end_nanos = reader.option(|reader| reader.u64())?;
+    let thread_number = reader.u8()?;
+    let swap_count = reader.usize()?;
+    let swaps = (0..swap_count)
+        .map(|_| Ok(SavedSwap { nanos: reader.u64()?, a: reader.usize()?, b: reader.usize()? }))
+        .collect::<Result<Vec<SavedSwap>, BinaryError>>()?;
+    let mark_final_count = reader.usize()?;
+    let mark_finals = (0..mark_final_count)
+        .map(|_| Ok(SavedMarkFinal { nanos: reader.u64()?, from: reader.usize()?, to: reader.usize()? }))
+        .collect::<Result<Vec<SavedMarkFinal>, BinaryError>>()?;
+    let step_count = reader.usize()?;
+    let steps = (0..step_count)
+        .map(|_| Ok(SavedStep { nanos: reader.u64()?, action_type: reader.action_type()? }))
+        .collect::<Result<Vec<SavedStep>, BinaryError>>()?;
+    let child_call_count = reader.usize()?;
+    let child_calls = (0..child_call_count)
+        .map(|_| read_saved_range_call(reader))
+        .collect::<Result<Vec<SavedRangeCall>, BinaryError>>()?;
+    let workers = if reader.has_workers() {
+        let change_count = reader.usize()?;
+        (0..change_count)
+            .map(|_| Ok(SavedWorkerChange { nanos: reader.u64()?, worker: reader.worker()? }))
+            .collect::<Result<Vec<SavedWorkerChange>, BinaryError>>()?
+    } else {
+        vec![]
+    };
+    Ok(SavedRangeCall { key, actor, from, len, is_new_thread, start_nanos, end_nanos, swaps, mark_finals, steps, child_calls, thread_number, workers })
+}
+
+pub struct BinaryWriter {
+    bytes: Vec<u8>,
+}
+
+impl BinaryWriter {
+
+    fn new() -> Self {
+        BinaryWriter { bytes: vec![] }
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn u64(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.bytes.push(byte);
+                return;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    // Zigzag encoded so that small negative numbers stay small.
+    pub fn i64(&mut self, value: i64) {
+        self.u64(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    pub fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    pub fn string(&mut self, value: &str) {
+        self.usize(value.len());
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    pub fn option<T, F: FnOnce(&mut Self, &T)>(&mut self, value: &Option<T>, write: F) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                write(self, value);
+            },
+            None => self.u8(0),
+        }
+    }
+
+    pub fn actor(&mut self, actor: Actor) {
+        self.u8(match actor {
+            Actor::Quicksort => 0,
+            Actor::BubbleSort => 1,
+            Actor::Partition => 2,
+            Actor::MergeSort => 3,
+            Actor::InsertionSort => 4,
+        });
+    }
+
+    pub fn worker(&mut self, worker: &Worker) {
+        self.option(&worker.rayon_index, |writer, rayon_index| writer.usize(*rayon_index));
+        self.usize(worker.os_thread);
+    }
+
+    pub fn action_type(&mut self, action_type: &ActionType) {
+        match action_type {
+            ActionType::MoveToThread => self.u8(0),
+            ActionType::MoveToCall { from_call_key, to_call_key, ranges } => {
+                self.u8(1);
+                self.string(from_call_key);
+                self.string(to_call_key);
+                self.usize(ranges.len());
+                for range in ranges.iter() {
+                    self.usize(range.start);
+                    self.usize(range.end);
+                }
+            },
+            ActionType::Take => self.u8(2),
+            ActionType::Release => self.u8(3),
+            ActionType::Swap { a, b } => self.tag_and_pair(4, *a, *b),
+            ActionType::MarkFinal { from, to } => self.tag_and_pair(5, *from, *to),
+            ActionType::Compare { a, b } => self.tag_and_pair(6, *a, *b),
+            ActionType::WriteToBuffer { index, buffer_index } => self.tag_and_pair(7, *index, *buffer_index),
+            ActionType::CopyBack { buffer_index, index } => self.tag_and_pair(8, *buffer_index, *index),
+            ActionType::Rotate { from, to } => self.tag_and_pair(9, *from, *to),
+        }
+    }
+
+    fn tag_and_pair(&mut self, tag: u8, first: usize, second: usize) {
+        self.u8(tag);
+        self.usize(first);
+        self.usize(second);
+    }
+}
+
+pub struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    version: u8,
+}
+
+impl <'a> BinaryReader<'a> {
+
+    fn new(bytes: &'a [u8]) -> Self {
+        BinaryReader { bytes, position: 0, version: BINARY_VERSION }
+    }
+
+    fn has_workers(&self) -> bool {
+        self.version != BINARY_VERSION_WITHOUT_WORKERS
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], BinaryError> {
+        if len > self.remaining() {
+            return Err(BinaryError::UnexpectedEnd);
+        }
+        let bytes = &self.bytes[self.position..self.position + len];
+        self.position += len;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, BinaryError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, BinaryError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(BinaryError::BadTag { what: "bool", tag }),
+        }
+    }
+
+    pub fn u64(&mut self) -> Result<u64, BinaryError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(BinaryError::VarintTooLong)
+    }
+
+    pub fn i64(&mut self) -> Result<i64, BinaryError> {
+        let value = self.u64()?;
+        Ok(((value >> 1) as i64) ^ -((value & 1) as i64))
+    }
+
+    pub fn usize(&mut self) -> Result<usize, BinaryError> {
+        Ok(self.u64()? as usize)
+    }
+
+    pub fn string(&mut self) -> Result<String, BinaryError> {
+        let len = self.usize()?;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| BinaryError::BadString)
+    }
+
+    pub fn option<T, F: FnOnce(&mut Self) -> Result<T, BinaryError>>(&mut self, read: F) -> Result<Option<T>, BinaryError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => read(self).map(Some),
+            tag => Err(BinaryError::BadTag { what: "option", tag }),
+        }
+    }
+
+    pub fn actor(&mut self) -> Result<Actor, BinaryError> {
+        match self.u8()? {
+            0 => Ok(Actor::Quicksort),
+            1 => Ok(Actor::BubbleSort),
+            2 => Ok(Actor::Partition),
+            3 => Ok(Actor::MergeSort),
+            4 => Ok(Actor::InsertionSort),
+            tag => Err(BinaryError::BadTag { what: "actor", tag }),
+        }
+    }
+
+    pub fn worker(&mut self) -> Result<Worker, BinaryError> {
+        Ok(Worker {
+            rayon_index: self.option(|reader| reader.usize())?,
+            os_thread: self.usize()?,
+        })
+    }
+
+    pub fn action_type(&mut self) -> Result<ActionType, BinaryError> {
+        let tag = self.u8()?;
+        Ok(match tag {
+            0 => ActionType::MoveToThread,
+            1 => {
+                let from_call_key = self.string()?;
+                let to_call_key = self.string()?;
+                let range_count = self.usize()?;
+                let ranges = (0..range_count)
+                    .map(|_| Ok(Range { start: self.usize()?, end: self.usize()? }))
+                    .collect::<Result<Vec<Range<usize>>, BinaryError>>()?;
+                ActionType::MoveToCall { from_call_key, to_call_key, ranges }
+            },
+            2 => ActionType::Take,
+            3 => ActionType::Release,
+            4 => ActionType::Swap { a: self.usize()?, b: self.usize()? },
+            5 => ActionType::MarkFinal { from: self.usize()?, to: self.usize()? },
+            6 => ActionType::Compare { a: self.usize()?, b: self.usize()? },
+            7 => ActionType::WriteToBuffer { index: self.usize()?, buffer_index: self.usize()? },
+            8 => ActionType::CopyBack { buffer_index: self.usize()?, index: self.usize()? },
+            9 => ActionType::Rotate { from: self.usize()?, to: self.usize()? },
+            tag => return Err(BinaryError::BadTag { what: "action type", tag }),
+        })
+    }
+}
+
+impl Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryError::UnexpectedEnd => write!(f, "the data ended early"),
+            BinaryError::BadMagic => write!(f, "not a saved vis file"),
+            BinaryError::UnsupportedVersion(version) => write!(f, "version {} isn't supported", version),
+            BinaryError::WrongKind { expected, found } => write!(f, "expected kind {} but found {}", expected, found),
+            BinaryError::BadTag { what, tag } => write!(f, "{} is not a valid {}", tag, what),
+            BinaryError::BadString => write!(f, "a string is not valid UTF-8"),
+            BinaryError::VarintTooLong => write!(f, "a number is longer than 64 bits"),
+            BinaryError::TrailingBytes(count) => write!(f, "{} bytes left over at the end", count),
+        }
+    }
+}
+
+impl Error for BinaryError {}
+
+fn try_save_and_load() {
+    let size = 1_000;
+    let mut v = test_data::vec_usize_shuffled(size);
+    let unsorted_array = v.clone();
+    let range_call = quicksort::quicksort(&mut v, 20, 4);
+    let mut action_list = ActionList::from_range_call(&range_call, Some("quicksort saved and loaded"));
+    action_list.unsorted_array = Some(unsorted_array);
+
+    action_list.save_json("sort_run.json").unwrap();
+    action_list.save_binary("sort_run.bin").unwrap();
+    println!("JSON = {} bytes; binary = {} bytes", action_list.to_json().len(), action_list.to_binary().len());
+    let from_json = ActionList::load("sort_run.json").unwrap();
+    let from_binary = ActionList::load("sort_run.bin").unwrap();
+    assert_eq!(action_list.to_saved(), from_json.to_saved());
+    assert_eq!(action_list.to_saved(), from_binary.to_saved());
+
+    let loaded_range_call = RangeCall::from_binary(&range_call.to_binary()).unwrap();
+    assert_eq!(range_call.to_saved(range_call.start_instant()), loaded_range_call.to_saved(loaded_range_call.start_instant()));
+
+    // A loaded run can go straight to the exporters.
+    let traced = traced_slice::trace_merge_sort(&test_data::vec_usize_shuffled(100), Some("merge sort"));
+    let traced = ActionList::from_binary(&traced.to_binary()).unwrap();
+    gen_html::gen_html(&[from_binary, traced], "Loaded runs", "sort_run.html").unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorded_action_list() -> ActionList {
+        let mut v = test_data::vec_usize_shuffled(500);
+        let unsorted_array = v.clone();
+        let mut action_list = ActionList::from_range_call(&quicksort::quicksort(&mut v, 20, 4), Some("quicksort \"saved\""));
+        action_list.unsorted_array = Some(unsorted_array);
+        action_list
+    }
+
+    #[test]
+    fn test_action_list_round_trip() {
+        let action_list = recorded_action_list();
+        let from_json = ActionList::from_json(&action_list.to_json()).unwrap();
+        assert_eq!(action_list.to_saved(), from_json.to_saved());
+        let bytes = action_list.to_binary();
+        let from_binary = ActionList::from_binary(&bytes).unwrap();
+        assert_eq!(action_list.to_saved(), from_binary.to_saved());
+        assert!(bytes.len() < action_list.to_json().len() / 4);
+
+        let traced = traced_slice::trace_merge_sort_loop(&test_data::vec_usize_shuffled(200), 5, 4, None);
+        assert_eq!(traced.to_saved(), ActionList::from_binary(&traced.to_binary()).unwrap().to_saved());
+    }
+
+    #[test]
+    fn test_range_call_round_trip() {
+        let mut v = test_data::vec_usize_shuffled(500);
+        let range_call = quicksort::quicksort(&mut v, 20, 4);
+        let saved = range_call.to_saved(range_call.start_instant());
+        let from_json = RangeCall::from_json(&range_call.to_json()).unwrap();
+        assert_eq!(saved, from_json.to_saved(from_json.start_instant()));
+        let from_binary = RangeCall::from_binary(&range_call.to_binary()).unwrap();
+        assert_eq!(saved, from_binary.to_saved(from_binary.start_instant()));
+    }
+
+    #[test]
+    fn test_workers() {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(3).build().unwrap();
+        let mut v = test_data::vec_usize_shuffled(300);
+        let action_list = ActionList::from_range_call(&quicksort::quicksort_in_pool(&mut v, 20, 4, &pool), None);
+        assert!(action_list.actions.iter().all(|action| action.worker.map_or(false, |worker| worker.rayon_index.map_or(false, |rayon_index| rayon_index < 3))));
+        let from_binary = ActionList::from_binary(&action_list.to_binary()).unwrap();
+        assert_eq!(action_list.actions[0].worker, from_binary.actions[0].worker);
+
+        // Saved before there were workers.
+        let json = r#"{"label":null,"unsorted_array":null,"actions":[{"nanos":0,"thread_number":0,"call_key":"0","actor":"Quicksort","from":0,"to":2,"action_type":"Take"}]}"#;
+        assert_eq!(None, ActionList::from_json(json).unwrap().actions[0].worker);
+    }
+
+    #[test]
+    fn test_bad_binary() {
+        let bytes = recorded_action_list().to_binary();
+        assert_eq!(Err(BinaryError::UnexpectedEnd), ActionList::from_binary(&bytes[..bytes.len() - 1]).map(|_| ()));
+        assert_eq!(Err(BinaryError::BadMagic), ActionList::from_binary(b"JSON....").map(|_| ()));
+        assert_eq!(Err(BinaryError::WrongKind { expected: KIND_RANGE_CALL, found: KIND_ACTION_LIST }), RangeCall::from_binary(&bytes).map(|_| ()));
+    }
+}
@@ -19,6 +19,15 @@ pub fn quicksort<T>(s: &mut [T], min_split_size: usize, max_threads: u8) -> Rang
     quicksort_internal(s, 0, false, min_split_size, max_threads, "0")
 }
 
+// Records the same run as quicksort() but with the rayon::join() calls on the given pool's workers
+// rather than the global pool's. The thread numbers in the resulting ActionList still come from
+// max_threads, since they count the splits that went to a new thread rather than the workers.
+pub fn quicksort_in_pool<T>(s: &mut [T], min_split_size: usize, max_threads: u8, pool: &rayon::ThreadPool) -> RangeCall
+    where T: Ord + Send + Debug
+{
+    pool.install(|| quicksort(s, min_split_size, max_threads))
+}
+
 fn quicksort_internal<T>(s: &mut [T], from: usize, is_new_thread: bool, min_split_size: usize, max_threads: u8, range_call_key: &str) -> RangeCall
     where T: Ord + Send + Debug
 {
@@ -0,0 +1,723 @@
+#![allow(dead_code)]
+
+use super::gen_js;
+use super::model::*;
+use super::replay::{Replay, ReplayError};
+use super::traced_slice;
+use crate::sort::test_data;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+const FILE_GIF: &str = "sort_animation.gif";
+const DIR_PNG_FRAMES: &str = "sort_frames";
+
+pub fn main() {
+    try_gif_multiple();
+    // try_png_frames_traced();
+}
+
+// Draws ActionLists as bar charts into images for design docs and code reviews: a sequence of PNG
+// frames or a single animated GIF. Frames are taken at even steps of the time fraction that
+// gen_js::action_to_js() gives each step, so the runs move at the same rate as in the HTML player and
+// several runs stacked in one image keep in step with each other. Each frame shows every action up to
+// its time, and the items touched since the previous frame are highlighted.
+//
+// The images are palette based, which both formats support directly, and the encoders are the least
+// that produces valid files: PNG with the Up filter and fixed-Huffman deflate that only looks for runs
+// of the same byte, and GIF with plain LZW. Bar charts are mostly long runs so this is enough.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorBy {
+    Thread,
+    Actor,
+    // The rayon worker or OS thread that actually ran the action, which shows work stealing that
+    // the thread numbers hide. Actions without a worker fall back to their thread number.
+    Worker,
+}
+
+#[derive(Clone, Debug)]
+pub struct RasterOptions {
+    pub width: usize,
+    pub height: usize,
+    pub frames_per_second: u32,
+    // The length of the animation, not counting hold_last_frame.
+    pub seconds: f32,
+    pub hold_last_frame: Duration,
+    pub color_by: ColorBy,
+}
+
+impl Default for RasterOptions {
+    fn default() -> Self {
+        RasterOptions {
+            width: 640,
+            height: 360,
+            frames_per_second: 20,
+            seconds: 8.0,
+            hold_last_frame: Duration::from_secs(2),
+            color_by: ColorBy::Thread,
+        }
+    }
+}
+
+impl RasterOptions {
+    pub fn frame_count(&self) -> usize {
+        ((self.frames_per_second as f32 * self.seconds).round() as usize).max(2)
+    }
+}
+
+// Palette indexes. The GIF color table has to have a power of two entries so the palette is padded to
+// PALETTE_SIZE.
+const BACKGROUND: u8 = 0;
+const IDLE: u8 = 1;
+const FINAL: u8 = 2;
+const SWAP: u8 = 3;
+const COMPARE: u8 = 4;
+const MOVE: u8 = 5;
+const BUFFER: u8 = 6;
+const THREAD_FIRST: u8 = 8;
+const THREAD_COLOR_COUNT: u8 = 8;
+const ACTOR_FIRST: u8 = 16;
+const PALETTE_SIZE: usize = 32;
+
+const PALETTE: [[u8; 3]; 21] = [
+    [255, 255, 255],
+    [190, 190, 190],
+    [60, 170, 80],
+    [220, 40, 40],
+    [240, 200, 0],
+    [200, 60, 200],
+    [150, 150, 150],
+    [0, 0, 0],
+    // Threads.
+    [40, 110, 220],
+    [0, 170, 200],
+    [130, 80, 200],
+    [240, 130, 20],
+    [90, 120, 240],
+    [190, 80, 160],
+    [0, 130, 130],
+    [230, 170, 40],
+    // Actors in the order of actor_color().
+    [40, 110, 220],
+    [240, 130, 20],
+    [130, 80, 200],
+    [0, 150, 140],
+    [150, 100, 60],
+];
+
+fn actor_color(actor: Actor) -> u8 {
+    ACTOR_FIRST + match actor {
+        Actor::Quicksort => 0,
+        Actor::BubbleSort => 1,
+        Actor::Partition => 2,
+        Actor::MergeSort => 3,
+        Actor::InsertionSort => 4,
+    }
+}
+
+fn palette_rgb() -> Vec<u8> {
+    let mut rgb: Vec<u8> = PALETTE.iter().flat_map(|color| color.iter().cloned()).collect();
+    rgb.resize(PALETTE_SIZE * 3, 0);
+    rgb
+}
+
+// One image as palette indexes, a row at a time from the top.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Frame {
+
+    fn new(width: usize, height: usize) -> Self {
+        Frame { width, height, pixels: vec![BACKGROUND; width * height] }
+    }
+
+    fn fill(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, color: u8) {
+        for y in y0..y1.min(self.height) {
+            let row = y * self.width;
+            self.pixels[row + x0.min(self.width)..row + x1.min(self.width)].iter_mut().for_each(|pixel| *pixel = color);
+        }
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        // Every row uses the Up filter, so a row that's the same as the one above is all zeros.
+        let mut filtered = Vec::with_capacity((self.width + 1) * self.height);
+        for y in 0..self.height {
+            filtered.push(2);
+            let row = &self.pixels[y * self.width..(y + 1) * self.width];
+            if y == 0 {
+                filtered.extend_from_slice(row);
+            } else {
+                let above = &self.pixels[(y - 1) * self.width..y * self.width];
+                filtered.extend(row.iter().zip(above.iter()).map(|(pixel, above)| pixel.wrapping_sub(*above)));
+            }
+        }
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut header = vec![];
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // Bit depth 8, color type 3 (palette), then the standard compression, filter and interlace methods.
+        header.extend_from_slice(&[8, 3, 0, 0, 0]);
+        png_chunk(&mut png, b"IHDR", &header);
+        png_chunk(&mut png, b"PLTE", &palette_rgb());
+        png_chunk(&mut png, b"IDAT", &zlib(&filtered));
+        png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+struct RasterRun<'a> {
+    replay: Replay<'a>,
+    // The color for each item while a thread holds it, by thread or by the actor of the call.
+    owner: Vec<Option<u8>>,
+    highlight: Vec<Option<u8>>,
+    uses_buffer: bool,
+    max_value: usize,
+}
+
+impl <'a> RasterRun<'a> {
+
+    fn new(action_list: &'a ActionList) -> Result<Self, ReplayError> {
+        let replay = Replay::new(action_list)?;
+        let len = replay.array().len();
+        let max_value = replay.array().iter().max().map_or(1, |max_value| (*max_value).max(1));
+        let uses_buffer = action_list.actions.iter().any(|action| match action.action_type {
+            ActionType::WriteToBuffer { .. } => true,
+            _ => false,
+        });
+        Ok(RasterRun { replay, owner: vec![None; len], highlight: vec![None; len], uses_buffer, max_value })
+    }
+
+    fn step(&mut self, color_by: ColorBy) -> Result<(), ReplayError> {
+        let action = match self.replay.step()? {
+            Some(action) => action,
+            None => return Ok(()),
+        };
+        let owner_color = match color_by {
+            ColorBy::Thread => THREAD_FIRST + action.thread_number % THREAD_COLOR_COUNT,
+            ColorBy::Actor => actor_color(action.actor),
+            ColorBy::Worker => {
+                let worker_number = action.worker.map_or(action.thread_number as usize, |worker| worker.rayon_index.unwrap_or(worker.os_thread));
+                THREAD_FIRST + (worker_number % THREAD_COLOR_COUNT as usize) as u8
+            },
+        };
+        match action.action_type {
+            ActionType::Take => self.owner[action.from..action.to].iter_mut().for_each(|owner| *owner = Some(owner_color)),
+            ActionType::Release => self.owner[action.from..action.to].iter_mut().for_each(|owner| *owner = None),
+            ActionType::Swap { a, b } => {
+                self.highlight[a] = Some(SWAP);
+                self.highlight[b] = Some(SWAP);
+            },
+            ActionType::Compare { a, b } => {
+                self.highlight[a] = self.highlight[a].or(Some(COMPARE));
+                self.highlight[b] = self.highlight[b].or(Some(COMPARE));
+            },
+            ActionType::WriteToBuffer { index, .. } | ActionType::CopyBack { index, .. } => self.highlight[index] = Some(MOVE),
+            ActionType::Rotate { from, to } => self.highlight[from..to].iter_mut().for_each(|highlight| *highlight = Some(MOVE)),
+            ActionType::MarkFinal { .. } | ActionType::MoveToThread | ActionType::MoveToCall { .. } => {},
+        }
+        Ok(())
+    }
+
+    fn color(&self, index: usize) -> u8 {
+        self.highlight[index].unwrap_or_else(|| {
+            if self.replay.is_final()[index] {
+                FINAL
+            } else {
+                self.owner[index].unwrap_or(IDLE)
+            }
+        })
+    }
+
+    // Draws the run into the panel from top to top + height. The buffer, if the run has one, gets a
+    // strip along the bottom of the panel.
+    fn draw(&self, frame: &mut Frame, top: usize, height: usize) {
+        let len = self.replay.array().len();
+        if len == 0 {
+            return;
+        }
+        let (chart_height, buffer_height) = if self.uses_buffer {
+            let buffer_height = height / 5;
+            (height.saturating_sub(buffer_height + 2), buffer_height)
+        } else {
+            (height, 0)
+        };
+        // A panel can be too short to draw in when there are many runs in a small frame.
+        if chart_height == 0 {
+            return;
+        }
+        let chart_bottom = top + chart_height;
+        let buffer_bottom = top + height;
+        let buffer = self.replay.buffer();
+        for index in 0..len {
+            let x0 = index * frame.width / len;
+            let mut x1 = (index + 1) * frame.width / len;
+            if x1 - x0 >= 3 {
+                x1 -= 1;
+            }
+            let bar_height = (self.replay.array()[index] * chart_height / self.max_value).max(1);
+            frame.fill(x0, chart_bottom - bar_height, x1, chart_bottom, self.color(index));
+            if let Some(value) = buffer_height.checked_sub(1).and(buffer[index]) {
+                let bar_height = (value * buffer_height / self.max_value).max(1);
+                frame.fill(x0, buffer_bottom - bar_height, x1, buffer_bottom, BUFFER);
+            }
+        }
+    }
+}
+
+// Frame number n of frame_count shows every action with a time fraction up to n / (frame_count - 1),
+// so the first frame is before any action has been taken and the last has all of them, with nothing
+// highlighted. Each run gets an equal share of the height.
+pub fn render_frames(action_lists: &[ActionList], options: &RasterOptions) -> Result<Vec<Frame>, ReplayError> {
+    let mut runs = action_lists.iter().map(RasterRun::new).collect::<Result<Vec<RasterRun>, ReplayError>>()?;
+    let max_duration_nanos = gen_js::max_duration_nanos(action_lists);
+    let frame_count = options.frame_count();
+    let gap = 4;
+    let panel_height = options.height.saturating_sub(gap * (runs.len() + 1)) / runs.len().max(1);
+    let mut frames = Vec::with_capacity(frame_count);
+    for frame_number in 0..frame_count {
+        let time = frame_number as f32 / (frame_count - 1) as f32;
+        let is_last = frame_number == frame_count - 1;
+        let mut frame = Frame::new(options.width, options.height);
+        for (run_number, run) in runs.iter_mut().enumerate() {
+            run.highlight.iter_mut().for_each(|highlight| *highlight = None);
+            while let Some(action) = run.replay.next_action() {
+                if !is_last && !(gen_js::time_fraction(action, max_duration_nanos) <= time) {
+                    break;
+                }
+                run.step(options.color_by)?;
+            }
+            // The last frame is the one held at the end, so it shows how the run finished.
+            if is_last {
+                run.highlight.iter_mut().for_each(|highlight| *highlight = None);
+            }
+            run.draw(&mut frame, gap + run_number * (panel_height + gap), panel_height);
+        }
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+// Writes frame_0000.png, frame_0001.png and so on into the directory, creating it if needed, and
+// returns the number of frames.
+pub fn save_png_frames<P: AsRef<Path>>(action_lists: &[ActionList], options: &RasterOptions, dir: P) -> io::Result<usize> {
+    let frames = render_frames(action_lists, options).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::create_dir_all(&dir)?;
+    for (frame_number, frame) in frames.iter().enumerate() {
+        fs::write(dir.as_ref().join(format!("frame_{:04}.png", frame_number)), frame.to_png())?;
+    }
+    Ok(frames.len())
+}
+
+pub fn gif(action_lists: &[ActionList], options: &RasterOptions) -> Result<Vec<u8>, ReplayError> {
+    let frames = render_frames(action_lists, options)?;
+    let frame_delay = (100.0 / options.frames_per_second.max(1) as f32).round().max(1.0) as u16;
+    let last_frame_delay = frame_delay + (options.hold_last_frame.as_millis() / 10) as u16;
+
+    let mut gif = b"GIF89a".to_vec();
+    gif.extend_from_slice(&(options.width as u16).to_le_bytes());
+    gif.extend_from_slice(&(options.height as u16).to_le_bytes());
+    // A global color table of 2^(4 + 1) entries, then the background color and aspect ratio.
+    gif.extend_from_slice(&[0xf0 | 4, BACKGROUND, 0]);
+    gif.extend_from_slice(&palette_rgb());
+    // Loop forever.
+    gif.extend_from_slice(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+    for (frame_number, frame) in frames.iter().enumerate() {
+        let delay = if frame_number == frames.len() - 1 { last_frame_delay } else { frame_delay };
+        gif.extend_from_slice(&[0x21, 0xf9, 0x04, 0x00]);
+        gif.extend_from_slice(&delay.to_le_bytes());
+        gif.extend_from_slice(&[0x00, 0x00]);
+        gif.push(0x2c);
+        gif.extend_from_slice(&[0, 0, 0, 0]);
+        gif.extend_from_slice(&(frame.width as u16).to_le_bytes());
+        gif.extend_from_slice(&(frame.height as u16).to_le_bytes());
+        gif.push(0);
+        let min_code_size = 5;
+        gif.push(min_code_size);
+        for block in lzw(&frame.pixels, min_code_size).chunks(255) {
+            gif.push(block.len() as u8);
+            gif.extend_from_slice(block);
+        }
+        gif.push(0);
+    }
+    gif.push(0x3b);
+    Ok(gif)
+}
+
+pub fn save_gif<P: AsRef<Path>>(action_lists: &[ActionList], options: &RasterOptions, path: P) -> io::Result<()> {
+    let gif = gif(action_lists, options).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, gif)
+}
+
+// Bits are packed starting from the least significant bit of each byte, as both deflate and GIF's LZW
+// expect.
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit_count: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+
+    fn new() -> Self {
+        BitWriter { bytes: vec![], bit_count: 0, bits: 0 }
+    }
+
+    fn write(&mut self, value: u32, bit_count: u32) {
+        self.bits |= value << self.bit_count;
+        self.bit_count += bit_count;
+        while self.bit_count >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    // Huffman codes go most significant bit first.
+    fn write_reversed(&mut self, code: u32, bit_count: u32) {
+        let reversed = (0..bit_count).fold(0, |reversed, bit| (reversed << 1) | ((code >> bit) & 1));
+        self.write(reversed, bit_count);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+        self.bytes
+    }
+}
+
+fn png_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65_521;
+        b = (b + a) % 65_521;
+    }
+    (b << 16) | a
+}
+
+const LENGTH_BASES: [u32; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA_BITS: [u32; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+// A zlib stream holding one deflate block with the fixed Huffman codes. The only matches are repeats of
+// the previous byte (distance 1).
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    // Final block, fixed Huffman codes.
+    writer.write(1, 1);
+    writer.write(1, 2);
+    let mut index = 0;
+    while index < data.len() {
+        let run = if index == 0 {
+            0
+        } else {
+            data[index..].iter().take(258).take_while(|byte| **byte == data[index - 1]).count()
+        };
+        if run >= 3 {
+            let code = LENGTH_BASES.iter().rposition(|base| *base as usize <= run).unwrap();
+            write_fixed_literal_or_length(&mut writer, 257 + code as u32);
+            writer.write(run as u32 - LENGTH_BASES[code], LENGTH_EXTRA_BITS[code]);
+            // Distance code 0 is a distance of 1, written in 5 bits.
+            writer.write_reversed(0, 5);
+            index += run;
+        } else {
+            write_fixed_literal_or_length(&mut writer, data[index] as u32);
+            index += 1;
+        }
+    }
+    write_fixed_literal_or_length(&mut writer, 256);
+
+    let mut zlib = vec![0x78, 0x01];
+    zlib.extend(writer.finish());
+    zlib.extend_from_slice(&adler32(data).to_be_bytes());
+    zlib
+}
+
+fn write_fixed_literal_or_length(writer: &mut BitWriter, symbol: u32) {
+    match symbol {
+        0..=143 => writer.write_reversed(0x30 + symbol, 8),
+        144..=255 => writer.write_reversed(0x190 + symbol - 144, 9),
+        256..=279 => writer.write_reversed(symbol - 256, 7),
+        _ => writer.write_reversed(0xc0 + symbol - 280, 8),
+    }
+}
+
+const LZW_MAX_CODE: u32 = 4096;
+
+// GIF's variable-width LZW. The table starts over with a clear code once it's full.
+fn lzw(pixels: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear_code = 1u32 << min_code_size;
+    let end_code = clear_code + 1;
+    let mut writer = BitWriter::new();
+    let mut table: HashMap<(u32, u8), u32> = HashMap::new();
+    let mut code_size = min_code_size as u32 + 1;
+    let mut next_code = end_code + 1;
+    writer.write(clear_code, code_size);
+    let mut current: Option<u32> = None;
+    for pixel in pixels {
+        current = match current {
+            None => Some(*pixel as u32),
+            Some(prefix) => match table.get(&(prefix, *pixel)) {
+                Some(code) => Some(*code),
+                None => {
+                    writer.write(prefix, code_size);
+                    if next_code < LZW_MAX_CODE {
+                        table.insert((prefix, *pixel), next_code);
+                        next_code += 1;
+                        // The decoder adds its entries a code behind, so it widens the codes after
+                        // this one once the table has gone past what the current size can hold.
+                        if next_code > (1 << code_size) && code_size < 12 {
+                            code_size += 1;
+                        }
+                    } else {
+                        writer.write(clear_code, code_size);
+                        table.clear();
+                        code_size = min_code_size as u32 + 1;
+                        next_code = end_code + 1;
+                    }
+                    Some(*pixel as u32)
+                },
+            },
+        };
+    }
+    if let Some(prefix) = current {
+        writer.write(prefix, code_size);
+    }
+    writer.write(end_code, code_size);
+    writer.finish()
+}
+
+// The same runs as gen_js::try_gen_multiple_2(), stacked in one GIF.
+fn try_gif_multiple() {
+    let size = 200;
+    let min_split_size = 20;
+    let v = test_data::vec_usize_shuffled(size);
+    let action_lists: Vec<ActionList> = (0..=3)
+        .map(|thread_splits| gen_js::make_action_list(size, min_split_size, thread_splits, Some(v.clone())))
+        .collect();
+    let options = RasterOptions { height: 480, ..RasterOptions::default() };
+    save_gif(&action_lists, &options, FILE_GIF).unwrap();
+    println!("Wrote {}.", FILE_GIF);
+}
+
+fn try_png_frames_traced() {
+    let v = test_data::vec_usize_shuffled(100);
+    let action_lists = vec![
+        traced_slice::trace_merge_sort(&v, Some("merge sort with buffer")),
+        traced_slice::trace_merge_sort_loop(&v, 5, 4, Some("merge_sort_loop; max threads = 4")),
+    ];
+    let options = RasterOptions { color_by: ColorBy::Actor, ..RasterOptions::default() };
+    let frame_count = save_png_frames(&action_lists, &options, DIR_PNG_FRAMES).unwrap();
+    println!("Wrote {} frames to {}.", frame_count, DIR_PNG_FRAMES);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_frames() {
+        let v = test_data::vec_usize_shuffled(50);
+        let action_lists = vec![
+            traced_slice::trace_merge_sort(&v, None),
+            gen_js::make_action_list(50, 10, 1, Some(v.clone())),
+        ];
+        let options = RasterOptions { width: 100, height: 60, frames_per_second: 10, seconds: 1.0, ..RasterOptions::default() };
+        let frames = render_frames(&action_lists, &options).unwrap();
+        assert_eq!(10, frames.len());
+        // Nothing is final in the first frame and everything is in the last.
+        assert!(!frames[0].pixels.contains(&FINAL));
+        let last = &frames[frames.len() - 1];
+        assert!(last.pixels.iter().all(|pixel| [BACKGROUND, FINAL, BUFFER].contains(pixel)));
+        assert!(last.pixels.contains(&BUFFER));
+
+        let png = last.to_png();
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &png[..8]);
+        assert_eq!(b"IEND", &png[png.len() - 8..png.len() - 4]);
+        let gif = gif(&action_lists, &options).unwrap();
+        assert_eq!(b"GIF89a", &gif[..6]);
+        assert_eq!(Some(&0x3b), gif.last());
+    }
+
+    #[test]
+    fn test_small_panels() {
+        let v = test_data::vec_usize_shuffled(20);
+        let action_lists = vec![traced_slice::trace_merge_sort(&v, None), traced_slice::trace_merge_sort(&v, None)];
+        for height in 0..=20 {
+            let options = RasterOptions { width: 40, height, frames_per_second: 2, seconds: 1.0, ..RasterOptions::default() };
+            render_frames(&action_lists, &options).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_zlib_round_trip() {
+        let mut data: Vec<u8> = (0..=255).collect();
+        data.extend(vec![7; 1_000]);
+        data.extend((0..5_000).map(|i: u32| (i.wrapping_mul(2_654_435_761) >> 24) as u8));
+        data.extend(vec![200; 3]);
+        for len in [0, 1, 2, 5, data.len()].iter() {
+            assert_eq!(&data[..*len], &inflate(&zlib(&data[..*len]))[..]);
+        }
+    }
+
+    #[test]
+    fn test_lzw_round_trip() {
+        let min_code_size = 5;
+        // Noisy pixels fill the table quickly, so this goes past 4,096 codes several times.
+        let mut state = 0x2545_f491u32;
+        let pixels: Vec<u8> = (0..60_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state >> 27) as u8
+            })
+            .collect();
+        let (decoded, clear_count) = unlzw(&lzw(&pixels, min_code_size), min_code_size);
+        assert_eq!(pixels, decoded);
+        assert!(clear_count > 2);
+        let pixels = vec![3; 10_000];
+        assert_eq!(pixels, unlzw(&lzw(&pixels, min_code_size), min_code_size).0);
+        assert_eq!(Vec::<u8>::new(), unlzw(&lzw(&[], min_code_size), min_code_size).0);
+    }
+
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        position: usize,
+    }
+
+    impl <'a> BitReader<'a> {
+        fn read(&mut self, bit_count: u32) -> u32 {
+            let mut value = 0;
+            for bit in 0..bit_count {
+                let byte = self.bytes[self.position / 8];
+                value |= (((byte >> (self.position % 8)) & 1) as u32) << bit;
+                self.position += 1;
+            }
+            value
+        }
+
+        fn read_reversed(&mut self, bit_count: u32) -> u32 {
+            (0..bit_count).fold(0, |code, _| (code << 1) | self.read(1))
+        }
+
+        fn read_fixed_literal_or_length(&mut self) -> u32 {
+            let code = self.read_reversed(7);
+            if code <= 0x17 {
+                return 256 + code;
+            }
+            let code = (code << 1) | self.read(1);
+            match code {
+                0x30..=0xbf => return code - 0x30,
+                0xc0..=0xc7 => return 280 + code - 0xc0,
+                _ => {},
+            }
+            let code = (code << 1) | self.read(1);
+            assert!((0x190..=0x1ff).contains(&code));
+            144 + code - 0x190
+        }
+    }
+
+    // Just enough of inflate to read what zlib() writes: one final block with the fixed codes.
+    fn inflate(zlib: &[u8]) -> Vec<u8> {
+        assert_eq!(&[0x78, 0x01], &zlib[..2]);
+        let mut reader = BitReader { bytes: &zlib[2..zlib.len() - 4], position: 0 };
+        assert_eq!((1, 1), (reader.read(1), reader.read(2)));
+        let mut data = vec![];
+        loop {
+            match reader.read_fixed_literal_or_length() {
+                256 => break,
+                symbol if symbol < 256 => data.push(symbol as u8),
+                symbol => {
+                    let code = (symbol - 257) as usize;
+                    let length = LENGTH_BASES[code] + reader.read(LENGTH_EXTRA_BITS[code]);
+                    let distance = reader.read_reversed(5) as usize + 1;
+                    assert!(distance <= 4);
+                    for _ in 0..length {
+                        let byte = data[data.len() - distance];
+                        data.push(byte);
+                    }
+                },
+            }
+        }
+        assert_eq!(&adler32(&data).to_be_bytes(), &zlib[zlib.len() - 4..]);
+        data
+    }
+
+    // A GIF LZW decoder written separately from lzw(). Returns the pixels and the number of clear
+    // codes read, counting the one at the start.
+    fn unlzw(bytes: &[u8], min_code_size: u8) -> (Vec<u8>, usize) {
+        let clear_code = 1u32 << min_code_size;
+        let end_code = clear_code + 1;
+        let mut reader = BitReader { bytes, position: 0 };
+        let mut table: Vec<Vec<u8>> = vec![];
+        let mut code_size = min_code_size as u32 + 1;
+        let mut previous: Option<Vec<u8>> = None;
+        let mut pixels = vec![];
+        let mut clear_count = 0;
+        loop {
+            let code = reader.read(code_size);
+            if code == clear_code {
+                table = (0..clear_code).map(|pixel| vec![pixel as u8]).collect();
+                table.push(vec![]);
+                table.push(vec![]);
+                code_size = min_code_size as u32 + 1;
+                previous = None;
+                clear_count += 1;
+                continue;
+            }
+            if code == end_code {
+                break;
+            }
+            let entry = match table.get(code as usize) {
+                Some(entry) => entry.clone(),
+                None => {
+                    // The code the encoder has only just added: the previous entry plus its own first pixel.
+                    let mut entry = previous.clone().unwrap();
+                    entry.push(entry[0]);
+                    entry
+                },
+            };
+            if let Some(mut new_entry) = previous.take() {
+                if table.len() < LZW_MAX_CODE as usize {
+                    new_entry.push(entry[0]);
+                    table.push(new_entry);
+                }
+            }
+            if table.len() == 1 << code_size && code_size < 12 {
+                code_size += 1;
+            }
+            pixels.extend_from_slice(&entry);
+            previous = Some(entry);
+        }
+        (pixels, clear_count)
+    }
+}
@@ -0,0 +1,339 @@
+#![allow(dead_code)]
+
+use super::model::*;
+use super::quicksort;
+use crate::sort::test_data;
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+pub fn main() {
+    try_replay();
+}
+
+// Plays an ActionList's actions back in order of duration against a copy of its unsorted_array,
+// applying each swap, rotate and buffer copy. The replay stops at the first action that's
+// inconsistent with the array at that point:
+//   - an index outside the array;
+//   - a MarkFinal over an item that isn't where it will be in the sorted array;
+//   - a swap, rotate or copy back that moves an item already marked final;
+//   - a copy back from a buffer slot that was never written.
+// Once every action has been applied the array has to be sorted. Actions with the same duration are
+// taken in the order they appear in the list.
+pub struct Replay<'a> {
+    action_list: &'a ActionList,
+    // Indexes into action_list.actions in the order they're replayed.
+    order: Vec<usize>,
+    sorted_array: Vec<usize>,
+    array: Vec<usize>,
+    buffer: Vec<Option<usize>>,
+    is_final: Vec<bool>,
+    // The number of actions applied so far, which is also the index of the next one in order.
+    position: usize,
+}
+
+// step is the position of the action in replay order, starting at zero, and action is its description.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    NoUnsortedArray,
+    IndexOutOfRange { step: usize, action: String, index: usize, len: usize },
+    NotInFinalPosition { step: usize, action: String, index: usize, expected: usize, found: usize },
+    FinalItemMoved { step: usize, action: String, index: usize },
+    EmptyBufferSlot { step: usize, action: String, buffer_index: usize },
+    NotSorted { index: usize },
+}
+
+impl <'a> Replay<'a> {
+
+    pub fn new(action_list: &'a ActionList) -> Result<Self, ReplayError> {
+        let unsorted_array = action_list.unsorted_array.as_ref().ok_or(ReplayError::NoUnsortedArray)?;
+        let mut order: Vec<usize> = (0..action_list.actions.len()).collect();
+        // sort_by_key() is stable so ties keep their order in the list.
+        order.sort_by_key(|index| action_list.actions[*index].duration);
+        let mut sorted_array = unsorted_array.clone();
+        sorted_array.sort();
+        Ok(Replay {
+            action_list,
+            order,
+            sorted_array,
+            array: unsorted_array.clone(),
+            buffer: vec![None; unsorted_array.len()],
+            is_final: vec![false; unsorted_array.len()],
+            position: 0,
+        })
+    }
+
+    pub fn step_count(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.position == self.order.len()
+    }
+
+    pub fn array(&self) -> &[usize] {
+        &self.array
+    }
+
+    // The buffer lines up with the array. None for a slot that hasn't been written.
+    pub fn buffer(&self) -> &[Option<usize>] {
+        &self.buffer
+    }
+
+    pub fn is_final(&self) -> &[bool] {
+        &self.is_final
+    }
+
+    // The action at the given position in replay order.
+    pub fn action_at(&self, step: usize) -> &'a Action {
+        &self.action_list.actions[self.order[step]]
+    }
+
+    pub fn next_action(&self) -> Option<&'a Action> {
+        if self.is_done() { None } else { Some(self.action_at(self.position)) }
+    }
+
+    pub fn reset(&mut self) {
+        let unsorted_array = self.action_list.unsorted_array.as_ref().unwrap();
+        self.array.copy_from_slice(unsorted_array);
+        self.buffer.iter_mut().for_each(|slot| *slot = None);
+        self.is_final.iter_mut().for_each(|is_final| *is_final = false);
+        self.position = 0;
+    }
+
+    // Applies the next action and returns it, or None if there are none left. If the action is
+    // inconsistent nothing is changed and the replay stays at that action.
+    pub fn step(&mut self) -> Result<Option<&'a Action>, ReplayError> {
+        let action = match self.next_action() {
+            Some(action) => action,
+            None => return Ok(None),
+        };
+        self.apply(action)?;
+        self.position += 1;
+        Ok(Some(action))
+    }
+
+    // Goes to the state after the first `position` actions, starting over if that's behind the
+    // current position.
+    pub fn seek(&mut self, position: usize) -> Result<(), ReplayError> {
+        let position = position.min(self.order.len());
+        if position < self.position {
+            self.reset();
+        }
+        while self.position < position {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    // A copy of the array after the first `position` actions.
+    pub fn array_at(&mut self, position: usize) -> Result<Vec<usize>, ReplayError> {
+        self.seek(position)?;
+        Ok(self.array.clone())
+    }
+
+    // Replays whatever is left and checks that the result is sorted.
+    pub fn run(&mut self) -> Result<(), ReplayError> {
+        self.seek(self.order.len())?;
+        match self.array.windows(2).position(|pair| pair[0] > pair[1]) {
+            Some(index) => Err(ReplayError::NotSorted { index: index + 1 }),
+            None => Ok(()),
+        }
+    }
+
+    fn apply(&mut self, action: &Action) -> Result<(), ReplayError> {
+        match action.action_type {
+            ActionType::Swap { a, b } => {
+                self.check_movable(action, a)?;
+                self.check_movable(action, b)?;
+                self.array.swap(a, b);
+            },
+            ActionType::MarkFinal { from, to } => {
+                for index in from..to {
+                    self.check_index(action, index)?;
+                    if self.array[index] != self.sorted_array[index] {
+                        return Err(ReplayError::NotInFinalPosition {
+                            step: self.position,
+                            action: action.description(),
+                            index,
+                            expected: self.sorted_array[index],
+                            found: self.array[index],
+                        });
+                    }
+                }
+                self.is_final[from..to].iter_mut().for_each(|is_final| *is_final = true);
+            },
+            ActionType::Compare { a, b } => {
+                self.check_index(action, a)?;
+                self.check_index(action, b)?;
+            },
+            ActionType::WriteToBuffer { index, buffer_index } => {
+                self.check_index(action, index)?;
+                self.check_index(action, buffer_index)?;
+                self.buffer[buffer_index] = Some(self.array[index]);
+            },
+            ActionType::CopyBack { buffer_index, index } => {
+                self.check_index(action, buffer_index)?;
+                self.check_movable(action, index)?;
+                let value = self.buffer[buffer_index].ok_or_else(|| ReplayError::EmptyBufferSlot { step: self.position, action: action.description(), buffer_index })?;
+                self.array[index] = value;
+            },
+            ActionType::Rotate { from, to } => {
+                for index in from..to {
+                    self.check_movable(action, index)?;
+                }
+                self.array[from..to].rotate_right(1);
+            },
+            ActionType::MoveToThread | ActionType::MoveToCall { .. } | ActionType::Take | ActionType::Release => {},
+        }
+        Ok(())
+    }
+
+    fn check_index(&self, action: &Action, index: usize) -> Result<(), ReplayError> {
+        if index >= self.array.len() {
+            return Err(ReplayError::IndexOutOfRange { step: self.position, action: action.description(), index, len: self.array.len() });
+        }
+        Ok(())
+    }
+
+    fn check_movable(&self, action: &Action, index: usize) -> Result<(), ReplayError> {
+        self.check_index(action, index)?;
+        if self.is_final[index] {
+            return Err(ReplayError::FinalItemMoved { step: self.position, action: action.description(), index });
+        }
+        Ok(())
+    }
+}
+
+impl ActionList {
+    pub fn verify_replay(&self) -> Result<(), ReplayError> {
+        Replay::new(self)?.run()
+    }
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::NoUnsortedArray =>
+                write!(f, "the action list has no unsorted_array to replay against"),
+            ReplayError::IndexOutOfRange { step, action, index, len } =>
+                write!(f, "step {} ({}): index {} is outside the array of {} items", step, action, index, len),
+            ReplayError::NotInFinalPosition { step, action, index, expected, found } =>
+                write!(f, "step {} ({}): index {} was marked final holding {} but {} belongs there", step, action, index, found, expected),
+            ReplayError::FinalItemMoved { step, action, index } =>
+                write!(f, "step {} ({}): moves index {} which was already marked final", step, action, index),
+            ReplayError::EmptyBufferSlot { step, action, buffer_index } =>
+                write!(f, "step {} ({}): buffer slot {} was never written", step, action, buffer_index),
+            ReplayError::NotSorted { index } =>
+                write!(f, "after the last step the item at index {} is smaller than the one before it", index),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+fn try_replay() {
+    let mut v = test_data::vec_usize_shuffled(200);
+    let unsorted_array = v.clone();
+    let range_call = quicksort::quicksort(&mut v, 10, 4);
+    let mut action_list = ActionList::from_range_call(&range_call, Some("quicksort"));
+    action_list.unsorted_array = Some(unsorted_array);
+    let mut replay = Replay::new(&action_list).unwrap();
+    dbg!(replay.step_count());
+    dbg!(&replay.array_at(replay.step_count() / 2).unwrap()[..20]);
+    dbg!(replay.run());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vis::traced_slice;
+    use std::time::Duration;
+
+    fn action(nanos: u64, action_type: ActionType) -> Action {
+        Action {
+            duration: Duration::from_nanos(nanos),
+            thread_number: 0,
+            worker: None,
+            call_key: "0".to_string(),
+            actor: Actor::Quicksort,
+            from: 0,
+            to: 3,
+            action_type,
+        }
+    }
+
+    fn make_action_list(actions: Vec<Action>) -> ActionList {
+        ActionList {
+            label: None,
+            actions,
+            unsorted_array: Some(vec![2, 0, 1]),
+        }
+    }
+
+    #[test]
+    fn test_recorded_sorts() {
+        let v = test_data::vec_usize_shuffled(300);
+        let action_lists = vec![
+            traced_slice::trace_quicksort_rayon(&v, 10, 50, None),
+            traced_slice::trace_merge_sort(&v, None),
+            traced_slice::trace_merge_sort_loop(&v, 5, 4, None),
+            traced_slice::trace_insertion_sort(&v, None, true),
+        ];
+        for action_list in action_lists.iter() {
+            assert_eq!(Ok(()), action_list.verify_replay());
+        }
+        let mut v = v.clone();
+        let unsorted_array = v.clone();
+        let mut action_list = ActionList::from_range_call(&quicksort::quicksort(&mut v, 10, 4), None);
+        action_list.unsorted_array = Some(unsorted_array);
+        assert_eq!(Ok(()), action_list.verify_replay());
+    }
+
+    #[test]
+    fn test_state_at_step() {
+        // Listed out of order to check that they're replayed by duration.
+        let action_list = make_action_list(vec![
+            action(3, ActionType::swap(1, 2)),
+            action(1, ActionType::swap(0, 1)),
+            action(4, ActionType::mark_final(0, 3)),
+        ]);
+        let mut replay = Replay::new(&action_list).unwrap();
+        assert_eq!(vec![0, 2, 1], replay.array_at(1).unwrap());
+        assert_eq!(vec![0, 1, 2], replay.array_at(2).unwrap());
+        assert_eq!(vec![0, 2, 1], replay.array_at(1).unwrap());
+        assert_eq!(Ok(()), replay.run());
+        assert!(replay.is_final().iter().all(|is_final| *is_final));
+    }
+
+    #[test]
+    fn test_first_inconsistent_action() {
+        let action_list = make_action_list(vec![
+            action(1, ActionType::swap(0, 1)),
+            action(2, ActionType::mark_final(0, 1)),
+            action(3, ActionType::mark_final(1, 2)),
+            action(4, ActionType::swap(1, 2)),
+        ]);
+        match action_list.verify_replay() {
+            Err(ReplayError::NotInFinalPosition { step: 2, index: 1, expected: 1, found: 2, .. }) => {},
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let action_list = make_action_list(vec![
+            action(1, ActionType::swap(0, 1)),
+            action(2, ActionType::mark_final(0, 1)),
+            action(3, ActionType::swap(0, 2)),
+        ]);
+        match action_list.verify_replay() {
+            Err(ReplayError::FinalItemMoved { step: 2, index: 0, .. }) => {},
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let action_list = make_action_list(vec![action(1, ActionType::swap(0, 1))]);
+        assert_eq!(Err(ReplayError::NotSorted { index: 2 }), action_list.verify_replay());
+    }
+}
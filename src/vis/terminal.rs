@@ -0,0 +1,398 @@
+#![allow(dead_code)]
+
+use super::gen_js;
+use super::model::*;
+use super::replay::{Replay, ReplayError};
+use crate::sort::test_data;
+
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+pub fn main() {
+    try_play_multiple();
+}
+
+// Plays ActionLists back in the terminal as bar charts drawn with ANSI colors, one chart per run
+// stacked top to bottom, without the round trip through gen_js or gen_html and a browser. Each bar
+// is an item, colored by what's happening to it:
+//   - the color of the thread holding it, following Take and Release as the HTML player does;
+//   - red for a swap, yellow for a compare, and magenta for a rotate or a copy to or from the buffer,
+//     for the actions applied since the last frame;
+//   - green once it's marked final;
+//   - grey when no thread holds it.
+// A run with a buffer gets a row under its chart showing the values in the buffer. When there are
+// more items than columns each column shows the first item of its share of the array.
+
+const ESC_HOME: &str = "\x1b[H";
+const ESC_CLEAR_SCREEN: &str = "\x1b[2J";
+const ESC_CLEAR_TO_END: &str = "\x1b[J";
+const ESC_CLEAR_LINE: &str = "\x1b[K";
+const ESC_HIDE_CURSOR: &str = "\x1b[?25l";
+const ESC_SHOW_CURSOR: &str = "\x1b[?25h";
+const ESC_RESET: &str = "\x1b[0m";
+
+// 256-color codes. The thread colors stay clear of the highlight colors.
+const COLOR_THREADS: [u8; 8] = [33, 45, 135, 208, 69, 170, 37, 214];
+const COLOR_IDLE: u8 = 240;
+const COLOR_FINAL: u8 = 34;
+const COLOR_SWAP: u8 = 196;
+const COLOR_COMPARE: u8 = 226;
+const COLOR_MOVE: u8 = 201;
+
+// Eighths of a cell, from empty to full.
+const BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// How far the runs move between frames.
+#[derive(Clone, Debug)]
+pub enum Pace {
+    // This many actions in each run.
+    Steps(usize),
+    // This much of the recorded time, so that runs shown together keep in step with each other.
+    RecordedTime(Duration),
+    // Whatever recorded time gets the longest run through in this many frames.
+    Frames(usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct TerminalOptions {
+    // Rows in each chart, not counting the label and the buffer.
+    pub height: usize,
+    pub max_columns: usize,
+    pub frame_delay: Duration,
+    pub pace: Pace,
+    pub use_color: bool,
+}
+
+impl Default for TerminalOptions {
+    fn default() -> Self {
+        TerminalOptions {
+            height: 12,
+            max_columns: 120,
+            frame_delay: Duration::from_millis(30),
+            pace: Pace::Frames(300),
+            use_color: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Highlight {
+    Swap,
+    Compare,
+    Move,
+}
+
+struct TerminalRun<'a> {
+    label: String,
+    replay: Replay<'a>,
+    // The thread holding each item, if any.
+    owner: Vec<Option<u8>>,
+    // Items touched by the actions applied since the last frame.
+    highlight: Vec<Option<Highlight>>,
+    thread_numbers: Vec<u8>,
+    max_value: usize,
+}
+
+pub struct TerminalAnimation<'a> {
+    runs: Vec<TerminalRun<'a>>,
+    options: TerminalOptions,
+    // For Pace::RecordedTime, how far into the recorded time the runs have got.
+    recorded_time: Duration,
+    frame_number: usize,
+}
+
+impl <'a> TerminalRun<'a> {
+
+    fn new(action_list: &'a ActionList, run_number: usize) -> Result<Self, ReplayError> {
+        let replay = Replay::new(action_list)?;
+        let len = replay.array().len();
+        let max_value = replay.array().iter().max().map_or(1, |max_value| (*max_value).max(1));
+        let label = action_list.label.clone().unwrap_or_else(|| format!("run {}", run_number + 1));
+        let mut thread_numbers: Vec<u8> = action_list.actions.iter().map(|action| action.thread_number).collect();
+        thread_numbers.sort();
+        thread_numbers.dedup();
+        Ok(TerminalRun {
+            label,
+            replay,
+            owner: vec![None; len],
+            highlight: vec![None; len],
+            thread_numbers,
+            max_value,
+        })
+    }
+
+    fn step(&mut self) -> Result<bool, ReplayError> {
+        let action = match self.replay.step()? {
+            Some(action) => action,
+            None => return Ok(false),
+        };
+        match action.action_type {
+            ActionType::Take => self.owner[action.from..action.to].iter_mut().for_each(|owner| *owner = Some(action.thread_number)),
+            ActionType::Release => self.owner[action.from..action.to].iter_mut().for_each(|owner| *owner = None),
+            ActionType::Swap { a, b } => {
+                self.highlight[a] = Some(Highlight::Swap);
+                self.highlight[b] = Some(Highlight::Swap);
+            },
+            ActionType::Compare { a, b } => {
+                self.highlight[a] = self.highlight[a].or(Some(Highlight::Compare));
+                self.highlight[b] = self.highlight[b].or(Some(Highlight::Compare));
+            },
+            ActionType::WriteToBuffer { index, .. } | ActionType::CopyBack { index, .. } => self.highlight[index] = Some(Highlight::Move),
+            ActionType::Rotate { from, to } => self.highlight[from..to].iter_mut().for_each(|highlight| *highlight = Some(Highlight::Move)),
+            ActionType::MarkFinal { .. } | ActionType::MoveToThread | ActionType::MoveToCall { .. } => {},
+        }
+        Ok(true)
+    }
+
+    fn clear_highlight(&mut self) {
+        self.highlight.iter_mut().for_each(|highlight| *highlight = None);
+    }
+
+    fn recorded_length(&self) -> Duration {
+        if self.replay.step_count() == 0 {
+            Duration::from_nanos(0)
+        } else {
+            self.replay.action_at(self.replay.step_count() - 1).duration
+        }
+    }
+
+    fn render(&self, s: &mut String, options: &TerminalOptions) {
+        let len = self.replay.array().len();
+        let column_count = len.min(options.max_columns);
+        // The first item shown by each column.
+        let column_indexes: Vec<usize> = (0..column_count).map(|column| column * len / column_count).collect();
+        let column_ranges: Vec<(usize, usize)> = (0..column_count).map(|column| (column_indexes[column], (column + 1) * len / column_count)).collect();
+        let colors: Vec<u8> = column_ranges.iter().map(|(from, to)| self.color(*from, *to)).collect();
+
+        write!(s, "{}  [{}/{}]", self.label, self.replay.position(), self.replay.step_count()).unwrap();
+        end_line(s, options);
+        let eighths: Vec<usize> = column_indexes.iter()
+            .map(|index| self.replay.array()[*index] * options.height * 8 / self.max_value)
+            .collect();
+        for row in (0..options.height).rev() {
+            let cells = eighths.iter().map(|eighths| BLOCKS[eighths.saturating_sub(row * 8).min(8)]);
+            write_cells(s, cells, &colors, options);
+            end_line(s, options);
+        }
+        let buffer = self.replay.buffer();
+        if buffer.iter().any(|slot| slot.is_some()) {
+            let cells = column_indexes.iter()
+                .map(|index| buffer[*index].map_or(' ', |value| BLOCKS[(value * 8 / self.max_value).max(1).min(8)]));
+            write_cells(s, cells, &vec![COLOR_IDLE; column_count], options);
+            s.push_str("  buffer");
+            end_line(s, options);
+        }
+    }
+
+    // For the items from..to shown by one column.
+    fn color(&self, from: usize, to: usize) -> u8 {
+        let highlight = self.highlight[from..to].iter().filter_map(|highlight| *highlight).min_by_key(|highlight| *highlight as u8);
+        match highlight {
+            Some(Highlight::Swap) => COLOR_SWAP,
+            Some(Highlight::Compare) => COLOR_COMPARE,
+            Some(Highlight::Move) => COLOR_MOVE,
+            None if self.replay.is_final()[from..to].iter().all(|is_final| *is_final) => COLOR_FINAL,
+            None => match self.owner[from] {
+                Some(thread_number) => thread_color(thread_number),
+                None => COLOR_IDLE,
+            },
+        }
+    }
+}
+
+impl <'a> TerminalAnimation<'a> {
+
+    // Fails if any of the action lists has no unsorted_array.
+    pub fn new(action_lists: &'a [ActionList], options: TerminalOptions) -> Result<Self, ReplayError> {
+        let runs = action_lists.iter().enumerate()
+            .map(|(run_number, action_list)| TerminalRun::new(action_list, run_number))
+            .collect::<Result<Vec<TerminalRun>, ReplayError>>()?;
+        let mut options = options;
+        if let Pace::Frames(frame_count) = options.pace {
+            let recorded_length = runs.iter().map(|run| run.recorded_length()).max().unwrap_or(Duration::from_nanos(0));
+            let per_frame = recorded_length.as_nanos() as u64 / frame_count.max(1) as u64;
+            options.pace = Pace::RecordedTime(Duration::from_nanos(per_frame.max(1)));
+        }
+        Ok(TerminalAnimation {
+            runs,
+            options,
+            recorded_time: Duration::from_nanos(0),
+            frame_number: 0,
+        })
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.runs.iter().all(|run| run.replay.is_done())
+    }
+
+    pub fn frame_number(&self) -> usize {
+        self.frame_number
+    }
+
+    // Moves every run on by one frame's worth of actions.
+    pub fn advance(&mut self) -> Result<(), ReplayError> {
+        self.frame_number += 1;
+        for run in self.runs.iter_mut() {
+            run.clear_highlight();
+        }
+        match self.options.pace {
+            Pace::Steps(step_count) => {
+                for run in self.runs.iter_mut() {
+                    for _ in 0..step_count.max(1) {
+                        if !run.step()? {
+                            break;
+                        }
+                    }
+                }
+            },
+            Pace::RecordedTime(per_frame) => {
+                self.recorded_time += per_frame;
+                let recorded_time = self.recorded_time;
+                for run in self.runs.iter_mut() {
+                    while run.replay.next_action().map_or(false, |action| action.duration <= recorded_time) {
+                        run.step()?;
+                    }
+                }
+            },
+            Pace::Frames(_) => unreachable!(),
+        }
+        Ok(())
+    }
+
+    // The charts for the current state, without moving the cursor.
+    pub fn frame(&self) -> String {
+        let mut s = String::new();
+        for (run_number, run) in self.runs.iter().enumerate() {
+            if run_number > 0 {
+                end_line(&mut s, &self.options);
+            }
+            run.render(&mut s, &self.options);
+        }
+        if self.options.use_color {
+            s.push_str(&self.legend());
+            end_line(&mut s, &self.options);
+        }
+        s
+    }
+
+    fn legend(&self) -> String {
+        let mut thread_numbers: Vec<u8> = self.runs.iter()
+            .flat_map(|run| run.thread_numbers.iter().cloned())
+            .collect();
+        thread_numbers.sort();
+        thread_numbers.dedup();
+        let mut s = String::new();
+        for thread_number in thread_numbers {
+            write!(s, "{} thread {}{}  ", color_on(thread_color(thread_number)), thread_number, ESC_RESET).unwrap();
+        }
+        for (color, label) in [(COLOR_SWAP, "swap"), (COLOR_COMPARE, "compare"), (COLOR_MOVE, "move"), (COLOR_FINAL, "final")].iter() {
+            write!(s, "{} {}{}  ", color_on(*color), label, ESC_RESET).unwrap();
+        }
+        s
+    }
+
+    // Draws a frame, waits, and moves on until every run is done, redrawing in place.
+    pub fn play<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        write!(out, "{}{}", ESC_HIDE_CURSOR, ESC_CLEAR_SCREEN)?;
+        let result = self.play_frames(out);
+        write!(out, "{}", ESC_SHOW_CURSOR)?;
+        out.flush()?;
+        result
+    }
+
+    fn play_frames<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        loop {
+            write!(out, "{}{}{}", ESC_HOME, self.frame(), ESC_CLEAR_TO_END)?;
+            out.flush()?;
+            if self.is_done() {
+                return Ok(());
+            }
+            if self.options.frame_delay > Duration::from_nanos(0) {
+                thread::sleep(self.options.frame_delay);
+            }
+            self.advance().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+    }
+}
+
+pub fn play_in_terminal(action_lists: &[ActionList], options: TerminalOptions) -> io::Result<()> {
+    let mut animation = TerminalAnimation::new(action_lists, options).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    animation.play(&mut out)
+}
+
+fn write_cells<I: Iterator<Item = char>>(s: &mut String, cells: I, colors: &[u8], options: &TerminalOptions) {
+    let mut current_color = None;
+    for (cell, color) in cells.zip(colors.iter()) {
+        if options.use_color && current_color != Some(*color) {
+            s.push_str(&color_on(*color));
+            current_color = Some(*color);
+        }
+        s.push(cell);
+    }
+    if options.use_color {
+        s.push_str(ESC_RESET);
+    }
+}
+
+fn end_line(s: &mut String, options: &TerminalOptions) {
+    if options.use_color {
+        s.push_str(ESC_CLEAR_LINE);
+    }
+    s.push('\n');
+}
+
+fn thread_color(thread_number: u8) -> u8 {
+    COLOR_THREADS[thread_number as usize % COLOR_THREADS.len()]
+}
+
+fn color_on(color: u8) -> String {
+    format!("\x1b[38;5;{}m", color)
+}
+
+// The same runs as gen_js::try_gen_multiple_2(), played together so that the runs with more threads
+// can be seen finishing first.
+fn try_play_multiple() {
+    let size = 500;
+    let min_split_size = 20;
+    let v = test_data::vec_usize_shuffled(size);
+    let action_lists: Vec<ActionList> = (0..=3)
+        .map(|thread_splits| gen_js::make_action_list(size, min_split_size, thread_splits, Some(v.clone())))
+        .collect();
+    let options = TerminalOptions { height: 8, ..TerminalOptions::default() };
+    play_in_terminal(&action_lists, options).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vis::traced_slice;
+
+    #[test]
+    fn test_play_to_end() {
+        let v = test_data::vec_usize_shuffled(40);
+        let action_lists = vec![
+            traced_slice::trace_merge_sort(&v, Some("merge sort")),
+            traced_slice::trace_insertion_sort(&v, None, true),
+        ];
+        let options = TerminalOptions { height: 4, max_columns: 20, frame_delay: Duration::from_nanos(0), pace: Pace::Steps(7), use_color: false };
+        let mut animation = TerminalAnimation::new(&action_lists, options).unwrap();
+        let mut out = vec![];
+        animation.play(&mut out).unwrap();
+        assert!(animation.is_done());
+
+        // Label and chart for each run, with the buffer row for the merge sort and a blank line between.
+        let frame = animation.frame();
+        let lines: Vec<&str> = frame.lines().collect();
+        assert_eq!(1 + 4 + 1 + 1 + 1 + 4, lines.len());
+        assert_eq!("run 2  [", &lines[7][..8]);
+        // The columns are sorted so each row is blank up to where the bars are tall enough to reach it.
+        for line in lines[8..].iter() {
+            assert!(line.trim_start().chars().all(|cell| cell != ' '));
+        }
+        assert_eq!(20, lines[11].chars().count());
+    }
+}
@@ -0,0 +1,511 @@
+#![allow(dead_code)]
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::Sender;
+use crossbeam::queue::SegQueue;
+
+use super::model::*;
+use crate::sort::bubble_sort;
+use crate::sort::insertion_sort;
+use crate::sort::merge_sort;
+use crate::sort::merge_sort_loop;
+use crate::sort::quicksort_rayon;
+use crate::sort::sort_slice::SortSlice;
+use crate::sort::test_data;
+
+pub fn main() {
+    try_trace_bubble_sort();
+    // try_trace_quicksort_rayon();
+    // try_trace_merge_sorts();
+}
+
+// Records what a sort does to a slice without the sort knowing about it. Any sort written against
+// SortSlice can be given a TracedSlice in place of a plain slice, and each read, compare, swap and
+// write is recorded with the time and the thread it ran on, along with the buffer copies and rotates
+// used by the merge sorts.
+//
+// Splitting a TracedSlice starts a new call for each half, with keys built the same way as RangeCall
+// keys: "0" for the whole slice, then "0" or "1" added for the low or high part. A call starts when
+// it's first used rather than when it's split off, so a half that's handed to another thread is
+// recorded on the thread that actually ran it. The Tracer can then be turned into a RangeCall tree
+// and from there an ActionList for the existing vis output.
+//
+// A live Tracer also sends each event other than a read or a plain write down a channel as it
+// happens, leaving out the compares unless include_compares is set. After each compare, swap, buffer
+// copy or rotate it sends it waits for the given delay so that someone watching can keep up. The
+// starts and ends of calls go without a delay since they don't change anything that's drawn. See
+// vis::live. A live Tracer doesn't keep the events as well, since a long run would hold millions of
+// them for nothing, so there's nothing to get back from into_events() or into_range_call().
+pub struct Tracer {
+    first_instant: Instant,
+    next_call_index: AtomicUsize,
+    events: SegQueue<TraceEvent>,
+    live: Option<(Sender<TraceEvent>, Duration, bool)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TraceEvent {
+    pub instant: Instant,
+    pub thread: TraceThread,
+    pub call_index: usize,
+    pub kind: TraceKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceThread {
+    pub thread_id: ThreadId,
+    // None if the thread isn't one of rayon's workers.
+    pub rayon_index: Option<usize>,
+    // As in model::Worker.
+    pub os_thread: usize,
+}
+
+// Indexes are absolute, that is relative to the start of the slice that was traced.
+#[derive(Clone, Debug)]
+pub enum TraceKind {
+    CallStart { key: String, parent_index: Option<usize>, actor: Actor, from: usize, len: usize },
+    CallEnd,
+    Read { index: usize },
+    // b_in_buffer means b is a buffer slot, numbered the same way as buffer_index below.
+    Compare { a: usize, b: usize, b_in_buffer: bool },
+    Swap { a: usize, b: usize },
+    Write { index: usize },
+    // buffer_index is the array position the buffer slot lines up with.
+    WriteToBuffer { index: usize, buffer_index: usize },
+    CopyBack { buffer_index: usize, index: usize },
+    Rotate { from: usize, to: usize },
+}
+
+pub struct TracedSlice<'a, T> {
+    s: &'a mut [T],
+    tracer: &'a Tracer,
+    call_index: usize,
+    // The call indexes from the root down to this call's parent, so that join() can go back up to the
+    // call that was split.
+    ancestor_indexes: Vec<usize>,
+    key: String,
+    actor: Actor,
+    from: usize,
+    started: Cell<bool>,
+}
+
+// One call as put back together from the events.
+struct TracedCall {
+    key: String,
+    parent_index: Option<usize>,
+    actor: Actor,
+    from: usize,
+    len: usize,
+    thread: TraceThread,
+    start_instant: Instant,
+    end_instant: Option<Instant>,
+    swaps: Vec<(usize, usize, Instant)>,
+    // Compares, buffer copies and rotates with absolute indexes.
+    steps: Vec<(ActionType, Instant)>,
+    child_indexes: Vec<usize>,
+    // The worker behind every event of the call, including the reads.
+    workers: Vec<(Instant, Worker)>,
+}
+
+impl Tracer {
+
+    pub fn new() -> Self {
+        Tracer {
+            first_instant: Instant::now(),
+            next_call_index: AtomicUsize::new(0),
+            events: SegQueue::new(),
+            live: None,
+        }
+    }
+
+    pub fn live(sender: Sender<TraceEvent>, delay: Duration, include_compares: bool) -> Self {
+        Tracer {
+            live: Some((sender, delay, include_compares)),
+            ..Self::new()
+        }
+    }
+
+    // The root call over the whole slice, with the key "0".
+    pub fn slice<'a, T>(&'a self, s: &'a mut [T], actor: Actor) -> TracedSlice<'a, T> {
+        TracedSlice::new(s, self, vec![], "0".to_string(), actor, 0)
+    }
+
+    pub fn first_instant(&self) -> Instant {
+        self.first_instant
+    }
+
+    // All of the events in the order they happened.
+    pub fn into_events(self) -> Vec<TraceEvent> {
+        let mut events = vec![];
+        while let Ok(event) = self.events.pop() {
+            events.push(event);
+        }
+        events.sort_by_key(|event| event.instant);
+        events
+    }
+
+    // If leaves_are_final is true each call that wasn't split marks its range final when it ends,
+    // which is right for quicksort. Otherwise the items are only marked final when the first call
+    // ends, as in a merge sort where nothing is in place until the last merge.
+    //
+    // Compares usually outnumber everything else put together so they're only kept if
+    // include_compares is true. Reads and plain writes aren't kept since there's no action for them.
+    pub fn into_range_call(self, leaves_are_final: bool, include_compares: bool) -> RangeCall {
+        let mut calls: BTreeMap<usize, TracedCall> = BTreeMap::new();
+        for event in self.into_events() {
+            let worker = event.thread.worker();
+            if let Some(call) = calls.get_mut(&event.call_index) {
+                call.workers.push((event.instant, worker));
+            }
+            match event.kind {
+                TraceKind::CallStart { key, parent_index, actor, from, len } => {
+                    if let Some(parent_index) = parent_index {
+                        calls.get_mut(&parent_index).unwrap().child_indexes.push(event.call_index);
+                    }
+                    calls.insert(event.call_index, TracedCall {
+                        key,
+                        parent_index,
+                        actor,
+                        from,
+                        len,
+                        thread: event.thread,
+                        start_instant: event.instant,
+                        end_instant: None,
+                        swaps: vec![],
+                        steps: vec![],
+                        child_indexes: vec![],
+                        workers: vec![(event.instant, worker)],
+                    });
+                },
+                TraceKind::CallEnd => {
+                    calls.get_mut(&event.call_index).unwrap().end_instant = Some(event.instant);
+                },
+                TraceKind::Swap { a, b } => {
+                    calls.get_mut(&event.call_index).unwrap().swaps.push((a, b, event.instant));
+                },
+                // ActionType::Compare has no buffer side, but the buffer slot sits under the array
+                // position with the same number so that's what's highlighted.
+                TraceKind::Compare { a, b, .. } if include_compares => {
+                    calls.get_mut(&event.call_index).unwrap().steps.push((ActionType::Compare { a, b }, event.instant));
+                },
+                TraceKind::WriteToBuffer { index, buffer_index } => {
+                    calls.get_mut(&event.call_index).unwrap().steps.push((ActionType::WriteToBuffer { index, buffer_index }, event.instant));
+                },
+                TraceKind::CopyBack { buffer_index, index } => {
+                    calls.get_mut(&event.call_index).unwrap().steps.push((ActionType::CopyBack { buffer_index, index }, event.instant));
+                },
+                TraceKind::Rotate { from, to } => {
+                    calls.get_mut(&event.call_index).unwrap().steps.push((ActionType::Rotate { from, to }, event.instant));
+                },
+                _ => {},
+            }
+        }
+        let root_index = calls.iter()
+            .find(|(_, call)| call.parent_index.is_none())
+            .map(|(index, _)| *index)
+            .expect("Nothing was traced.");
+        make_range_call(&calls, root_index, None, leaves_are_final).0
+    }
+
+    pub fn into_action_list(self, label: Option<&str>, unsorted_array: Vec<usize>, leaves_are_final: bool, include_compares: bool) -> ActionList {
+        let range_call = self.into_range_call(leaves_are_final, include_compares);
+        let mut action_list = ActionList::from_range_call(&range_call, label);
+        action_list.unsorted_array = Some(unsorted_array);
+        action_list
+    }
+
+    fn record(&self, call_index: usize, kind: TraceKind) {
+        let event = TraceEvent {
+            instant: Instant::now(),
+            thread: TraceThread::current(),
+            call_index,
+            kind,
+        };
+        match (&self.live, &event.kind) {
+            (Some(_), TraceKind::Read { .. }) | (Some(_), TraceKind::Write { .. }) | (None, _) => {},
+            (Some((_, _, false)), TraceKind::Compare { .. }) => {},
+            (Some((sender, _, _)), TraceKind::CallStart { .. }) | (Some((sender, _, _)), TraceKind::CallEnd) => {
+                let _ = sender.send(event.clone());
+            },
+            (Some((sender, delay, _)), _) => {
+                // Once nobody is listening the sort carries on without the delay.
+                if sender.send(event.clone()).is_ok() && *delay > Duration::from_secs(0) {
+                    thread::sleep(*delay);
+                }
+            },
+        }
+        if self.live.is_none() {
+            self.events.push(event);
+        }
+    }
+}
+
+// A call's end is when its last child ended if that's later than its own end, since the slice that
+// represents a call is used up by split_at() before the halves run.
+fn make_range_call(calls: &BTreeMap<usize, TracedCall>, index: usize, parent_thread: Option<&TraceThread>, leaves_are_final: bool) -> (RangeCall, Instant) {
+    let call = &calls[&index];
+    let is_new_thread = parent_thread.map_or(false, |parent_thread| *parent_thread != call.thread);
+    let mut range_call = RangeCall::new_at(&call.key, call.actor, call.from, call.len, is_new_thread, call.start_instant);
+    for (instant, worker) in call.workers.iter() {
+        range_call.worker_at(*worker, *instant);
+    }
+    for (a, b, instant) in call.swaps.iter() {
+        range_call.swap_at(a - call.from, b - call.from, *instant);
+    }
+    for (action_type, instant) in call.steps.iter() {
+        let from = call.from;
+        match *action_type {
+            ActionType::Compare { a, b } => range_call.compare_at(a - from, b - from, *instant),
+            ActionType::WriteToBuffer { index, buffer_index } => range_call.write_to_buffer_at(index - from, buffer_index - from, *instant),
+            ActionType::CopyBack { buffer_index, index } => range_call.copy_back_at(buffer_index - from, index - from, *instant),
+            ActionType::Rotate { from: rotate_from, to: rotate_to } => range_call.rotate_at(rotate_from - from, rotate_to - from, *instant),
+            _ => unreachable!(),
+        }
+    }
+    // Empty pieces such as the low side of a partition with the smallest item as the pivot don't
+    // take part in the animation.
+    let mut child_indexes: Vec<usize> = call.child_indexes.iter().cloned().filter(|child_index| calls[child_index].len > 0).collect();
+    child_indexes.sort_by_key(|child_index| calls[child_index].key.clone());
+    let mut end_instant = call.end_instant.unwrap_or(call.start_instant);
+    for child_index in child_indexes {
+        let (child_call, child_end_instant) = make_range_call(calls, child_index, Some(&call.thread), leaves_are_final);
+        end_instant = end_instant.max(child_end_instant);
+        range_call.add_child_call(child_call);
+    }
+    let is_leaf = call.child_indexes.is_empty();
+    if call.len > 0 && ((leaves_are_final && is_leaf) || (!leaves_are_final && call.parent_index.is_none())) {
+        range_call.mark_final_at(0, call.len, end_instant);
+    }
+    range_call.end_at(end_instant);
+    (range_call, end_instant)
+}
+
+impl TraceThread {
+    pub fn current() -> Self {
+        let worker = Worker::current();
+        TraceThread {
+            thread_id: thread::current().id(),
+            rayon_index: worker.rayon_index,
+            os_thread: worker.os_thread,
+        }
+    }
+
+    pub fn worker(&self) -> Worker {
+        Worker { rayon_index: self.rayon_index, os_thread: self.os_thread }
+    }
+}
+
+impl <'a, T> TracedSlice<'a, T> {
+
+    fn new(s: &'a mut [T], tracer: &'a Tracer, ancestor_indexes: Vec<usize>, key: String, actor: Actor, from: usize) -> Self {
+        TracedSlice {
+            s,
+            tracer,
+            call_index: tracer.next_call_index.fetch_add(1, Ordering::Relaxed),
+            ancestor_indexes,
+            key,
+            actor,
+            from,
+            started: Cell::new(false),
+        }
+    }
+
+    fn ensure_started(&self) {
+        if !self.started.get() {
+            self.started.set(true);
+            self.tracer.record(self.call_index, TraceKind::CallStart {
+                key: self.key.clone(),
+                parent_index: self.ancestor_indexes.last().cloned(),
+                actor: self.actor,
+                from: self.from,
+                len: self.s.len(),
+            });
+        }
+    }
+
+    fn record(&self, kind: TraceKind) {
+        self.ensure_started();
+        self.tracer.record(self.call_index, kind);
+    }
+}
+
+impl <'a, T: PartialOrd> SortSlice for TracedSlice<'a, T> {
+    type Item = T;
+
+    fn len(&self) -> usize {
+        self.s.len()
+    }
+
+    fn read(&self, index: usize) -> &T {
+        self.record(TraceKind::Read { index: self.from + index });
+        &self.s[index]
+    }
+
+    fn less(&self, a: usize, b: usize) -> bool {
+        self.record(TraceKind::Compare { a: self.from + a, b: self.from + b, b_in_buffer: false });
+        self.s[a] < self.s[b]
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.record(TraceKind::Swap { a: self.from + a, b: self.from + b });
+        self.s.swap(a, b);
+    }
+
+    fn write(&mut self, index: usize, value: T) -> T {
+        self.record(TraceKind::Write { index: self.from + index });
+        mem::replace(&mut self.s[index], value)
+    }
+
+    fn less_than_buffer(&self, index: usize, buffer: &[T], buffer_index: usize) -> bool {
+        self.record(TraceKind::Compare { a: self.from + index, b: self.from + buffer_index, b_in_buffer: true });
+        self.s[index] < buffer[buffer_index]
+    }
+
+    fn rotate_right_one(&mut self, from: usize, to: usize) {
+        self.record(TraceKind::Rotate { from: self.from + from, to: self.from + to });
+        self.s[from..to].rotate_right(1);
+    }
+
+    fn copy_to_buffer(&self, index: usize, buffer: &mut Vec<T>)
+        where T: Clone
+    {
+        self.record(TraceKind::WriteToBuffer { index: self.from + index, buffer_index: self.from + buffer.len() });
+        buffer.push(self.s[index].clone());
+    }
+
+    fn copy_from_buffer(&mut self, index: usize, buffer: &[T], buffer_index: usize)
+        where T: Clone
+    {
+        self.record(TraceKind::CopyBack { buffer_index: self.from + buffer_index, index: self.from + index });
+        self.s[index] = buffer[buffer_index].clone();
+    }
+
+    fn split_at(mut self, mid: usize) -> (Self, Self) {
+        // This call has to start before its children even if it never touched an item.
+        self.ensure_started();
+        let s = mem::replace(&mut self.s, &mut []);
+        let (lo, hi) = s.split_at_mut(mid);
+        let mut ancestor_indexes = self.ancestor_indexes.clone();
+        ancestor_indexes.push(self.call_index);
+        let lo = TracedSlice::new(lo, self.tracer, ancestor_indexes.clone(), format!("{}0", self.key), self.actor, self.from);
+        let hi = TracedSlice::new(hi, self.tracer, ancestor_indexes, format!("{}1", self.key), self.actor, self.from + mid);
+        (lo, hi)
+    }
+
+    // Picks up the call that was split rather than starting a new one, so anything done after the
+    // halves are joined such as a merge belongs to that call. Its end is recorded again when the
+    // joined slice is dropped.
+    unsafe fn join(mut lo: Self, mut hi: Self) -> Self {
+        assert_eq!(lo.ancestor_indexes, hi.ancestor_indexes, "join() was given slices from different calls.");
+        let mut ancestor_indexes = mem::replace(&mut lo.ancestor_indexes, vec![]);
+        let call_index = ancestor_indexes.pop().expect("join() was given a slice that wasn't split off.");
+        let key = lo.key[..lo.key.len() - 1].to_string();
+        let (tracer, actor, from) = (lo.tracer, lo.actor, lo.from);
+        let lo_s = mem::replace(&mut lo.s, &mut []);
+        let hi_s = mem::replace(&mut hi.s, &mut []);
+        let s = <&mut [T] as SortSlice>::join(lo_s, hi_s);
+        TracedSlice {
+            s,
+            tracer,
+            call_index,
+            ancestor_indexes,
+            key,
+            actor,
+            from,
+            started: Cell::new(true),
+        }
+    }
+}
+
+impl <'a, T> Drop for TracedSlice<'a, T> {
+    fn drop(&mut self) {
+        // A call that was never used still gets a start so that its range is accounted for.
+        self.record(TraceKind::CallEnd);
+    }
+}
+
+// Runs the ordinary quicksort_rayon_on() over a copy of v and returns the recorded run.
+pub fn trace_quicksort_rayon(v: &[usize], min_split_size: u8, min_thread_size: u16, label: Option<&str>) -> ActionList {
+    let mut v = v.to_vec();
+    let unsorted_array = v.clone();
+    let tracer = Tracer::new();
+    quicksort_rayon::quicksort_rayon_on(tracer.slice(&mut v, Actor::Quicksort), min_split_size, min_thread_size);
+    debug_assert!(v.is_sorted());
+    tracer.into_action_list(label, unsorted_array, true, false)
+}
+
+pub fn trace_bubble_sort(v: &[usize], label: Option<&str>) -> ActionList {
+    let mut v = v.to_vec();
+    let unsorted_array = v.clone();
+    let tracer = Tracer::new();
+    {
+        let mut s = tracer.slice(&mut v, Actor::BubbleSort);
+        bubble_sort::bubble_sort_on(&mut s);
+    }
+    debug_assert!(v.is_sorted());
+    tracer.into_action_list(label, unsorted_array, false, false)
+}
+
+// The merge sorts don't leave anything in its final place until the last merge is done, so only the
+// whole range is marked final.
+pub fn trace_merge_sort(v: &[usize], label: Option<&str>) -> ActionList {
+    let mut v = v.to_vec();
+    let unsorted_array = v.clone();
+    let tracer = Tracer::new();
+    merge_sort::merge_sort_on(tracer.slice(&mut v, Actor::MergeSort));
+    debug_assert!(v.is_sorted());
+    tracer.into_action_list(label, unsorted_array, false, false)
+}
+
+pub fn trace_merge_sort_loop(v: &[usize], min_split_size: u8, max_threads: u8, label: Option<&str>) -> ActionList {
+    let mut v = v.to_vec();
+    let unsorted_array = v.clone();
+    let tracer = Tracer::new();
+    merge_sort_loop::merge_sort_loop_on(tracer.slice(&mut v, Actor::MergeSort), min_split_size, max_threads);
+    debug_assert!(v.is_sorted());
+    tracer.into_action_list(label, unsorted_array, false, false)
+}
+
+pub fn trace_insertion_sort(v: &[usize], label: Option<&str>, include_compares: bool) -> ActionList {
+    let mut v = v.to_vec();
+    let unsorted_array = v.clone();
+    let tracer = Tracer::new();
+    {
+        let mut s = tracer.slice(&mut v, Actor::InsertionSort);
+        insertion_sort::insertion_sort_on(&mut s);
+    }
+    debug_assert!(v.is_sorted());
+    tracer.into_action_list(label, unsorted_array, false, include_compares)
+}
+
+fn try_trace_bubble_sort() {
+    let v = test_data::vec_usize_shuffled(12);
+    let action_list = trace_bubble_sort(&v, Some("bubble_sort traced"));
+    action_list.display_by_time(true);
+    action_list.report_types();
+}
+
+fn try_trace_quicksort_rayon() {
+    let v = test_data::vec_usize_shuffled(200);
+    let action_list = trace_quicksort_rayon(&v, 10, 50, Some("quicksort_rayon_on traced"));
+    action_list.display_by_thread(false);
+    action_list.report_types();
+}
+
+fn try_trace_merge_sorts() {
+    let v = test_data::vec_usize_shuffled(40);
+    let action_list = trace_merge_sort(&v, Some("merge_sort_on traced"));
+    action_list.display_by_time(true);
+    action_list.report_types();
+    let action_list = trace_merge_sort_loop(&v, 5, 4, Some("merge_sort_loop_on traced"));
+    action_list.display_by_thread(true);
+    action_list.report_types();
+    let action_list = trace_insertion_sort(&v, Some("insertion_sort_on traced"), true);
+    action_list.report_types();
+}